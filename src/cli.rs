@@ -1,11 +1,19 @@
 #![cfg(feature = "cli")]
 
-use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use clap::builder::PossibleValuesParser;
-use clap::{Parser, Subcommand, value_parser};
+use clap::{Args, Parser, Subcommand, value_parser};
 
-use crate::client::Client;
-use crate::utils::resolve_env_path_or_create;
+use crate::client::{Client, Ledger, TxStatus};
+use crate::funding::{CandleTimeFrame, FundingMarket};
+use crate::{report, risk};
+use crate::trading::{OrderMeta, TradingMarket, TradingOrder, TradingTicker};
+use crate::utils::{FundingPeriod, OrderFlags, Rounding, resolve_env_path_or_create};
 
 /// A convenient CLI tool for Bitfinex
 #[derive(Parser)]
@@ -24,6 +32,159 @@ Author: {author-with-newline}
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        help = "Timeout of each request. Also read from BFX_TIMEOUT."
+    )]
+    timeout: Option<u64>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Retries of a failed request (default: 5). Also read from BFX_RETRIES."
+    )]
+    retries: Option<u8>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "Append the result of the command to this JSONL file. Also read from BFX_HISTORY."
+    )]
+    history: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_parser = DisplayLocale::parse,
+        help = "Number and date format of tables, e.g. de-DE or en-US (default: ISO 8601). Also read from BFX_LOCALE."
+    )]
+    locale: Option<DisplayLocale>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "CLASS=DECIMALS,...",
+        help = "Round numbers of JSON output and history, e.g. price=2,amount=8,rate=6. Also read from BFX_ROUND."
+    )]
+    round: Option<Rounding>,
+}
+
+/// Request settings from the global flags, see [`configure`].
+struct RequestConfig {
+    timeout: Option<u64>,
+    retries: Option<u8>,
+}
+
+static REQUEST_CONFIG: OnceLock<RequestConfig> = OnceLock::new();
+
+/// History file from the global flag, see [`record_result`].
+static HISTORY_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Number and date format of tables, see [`display_locale`].
+static DISPLAY_LOCALE: OnceLock<DisplayLocale> = OnceLock::new();
+static ROUNDING: OnceLock<Rounding> = OnceLock::new();
+
+/// Locale-dependent formatting of numbers and dates in tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DisplayLocale {
+    decimal_comma: bool,
+    /// Date format, RFC 3339 if not set.
+    date_format: Option<&'static str>,
+}
+
+impl DisplayLocale {
+    /// Parse a locale such as `de`, `de-DE`, `en_US.UTF-8` or `iso`.
+    fn parse(value: &str) -> Result<Self, String> {
+        let tag = value
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .replace('_', "-");
+        let mut parts = tag.split('-');
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        let region = parts.next().unwrap_or_default().to_uppercase();
+        if language == "iso" {
+            return Ok(DisplayLocale::default());
+        }
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return Err(format!("Invalid locale: {value}"));
+        }
+
+        let decimal_comma = match (language.as_str(), region.as_str()) {
+            // Regions using a decimal point with a decimal-comma language
+            ("de" | "it", "CH" | "LI")
+            | ("es", "419" | "DO" | "GT" | "HN" | "MX" | "NI" | "PA" | "PE" | "PR" | "SV" | "US") => {
+                false
+            }
+            // Regions using a decimal comma with a decimal-point language
+            ("en", "AT" | "BE" | "DE" | "DK" | "FI" | "NL" | "SE" | "SI" | "ZA") => true,
+            (
+                "bg" | "cs" | "da" | "de" | "el" | "es" | "et" | "fi" | "fr" | "hr" | "hu" | "id"
+                | "it" | "lt" | "lv" | "nb" | "nl" | "no" | "pl" | "pt" | "ro" | "ru" | "sk" | "sl"
+                | "sr" | "sv" | "tr" | "uk" | "vi",
+                _,
+            ) => true,
+            _ => false,
+        };
+        let date_format = match (language.as_str(), region.as_str()) {
+            ("en", "" | "US" | "PH") | ("es", "US") => "%m/%d/%Y %H:%M:%S",
+            ("en" | "fr", "CA") => "%Y-%m-%d %H:%M:%S",
+            ("ja" | "ko" | "zh" | "hu" | "lt" | "sv", _) => "%Y-%m-%d %H:%M:%S",
+            ("nl", _) => "%d-%m-%Y %H:%M:%S",
+            (
+                "bg" | "cs" | "da" | "de" | "et" | "fi" | "hr" | "lv" | "nb" | "no" | "pl" | "ro"
+                | "ru" | "sk" | "sl" | "sr" | "tr" | "uk",
+                _,
+            ) => "%d.%m.%Y %H:%M:%S",
+            _ => "%d/%m/%Y %H:%M:%S",
+        };
+        Ok(DisplayLocale {
+            decimal_comma,
+            date_format: Some(date_format),
+        })
+    }
+
+    /// Reformat a table cell holding a decimal number or an RFC 3339 time.
+    fn format_cell(&self, cell: &str) -> String {
+        if self.decimal_comma && cell.contains('.') && cell.parse::<f64>().is_ok() {
+            return cell.replace('.', ",");
+        }
+        if let Some(format) = self.date_format
+            && let Ok(time) = DateTime::parse_from_rfc3339(cell)
+        {
+            return time.format(format).to_string();
+        }
+        cell.to_string()
+    }
+}
+
+/// The locale from `--locale`, or else BFX_LOCALE (including the .env file
+/// once the key is loaded).
+fn display_locale() -> DisplayLocale {
+    *DISPLAY_LOCALE.get_or_init(|| {
+        let Ok(value) = std::env::var("BFX_LOCALE") else {
+            return DisplayLocale::default();
+        };
+        DisplayLocale::parse(&value).unwrap_or_else(|e| {
+            eprintln!("Ignoring BFX_LOCALE: {e}");
+            DisplayLocale::default()
+        })
+    })
+}
+
+/// The rounding from `--round`, or else BFX_ROUND.
+fn rounding() -> Rounding {
+    *ROUNDING.get_or_init(|| {
+        std::env::var("BFX_ROUND")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    })
 }
 
 #[derive(Subcommand)]
@@ -44,6 +205,35 @@ enum Commands {
         #[command(subcommand)]
         action: AuthAction,
     },
+    Verify {
+        #[command(subcommand)]
+        action: VerifyAction,
+    },
+    Report {
+        #[command(subcommand)]
+        action: ReportAction,
+    },
+    Pay {
+        #[command(subcommand)]
+        action: PayAction,
+    },
+    Pulse {
+        #[command(subcommand)]
+        action: PulseAction,
+    },
+    Analyze {
+        #[command(subcommand)]
+        action: AnalyzeAction,
+    },
+    /// Diagnose the configuration, API key and connectivity.
+    Doctor,
+    /// Replace this binary with the latest GitHub release, after verifying its
+    /// Ed25519 signature against the key built into this binary.
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        #[arg(long, help = "Only check whether a newer release exists.")]
+        check: bool,
+    },
 }
 
 /// Funding-related utilities
@@ -72,13 +262,8 @@ enum FundingAction {
     Candles {
         symbol: String,
 
-        #[arg(
-            short,
-            long,
-            default_value = "30",
-            value_parser = value_parser!(u8).range(2..=120),
-        )]
-        period: Option<u8>,
+        #[arg(short, long, default_value = "30")]
+        period: Option<FundingPeriod>,
 
         #[arg(
             short,
@@ -93,7 +278,7 @@ enum FundingAction {
             short,
             long,
             default_value = "30m",
-            value_parser = PossibleValuesParser::new(["1m", "5m", "15m", "30m", "1h", "3h", "4h", "6h", "12h", "1d", "1w", "2w", "1M"]),
+            value_parser = PossibleValuesParser::new(["1m", "5m", "15m", "30m", "1h", "3h", "6h", "12h", "1d", "1w", "2w", "1M"]),
             help = "Time frame for the candles. Default is 30 minutes.",
         )]
         time_frame: Option<String>,
@@ -110,13 +295,13 @@ enum FundingAction {
             long,
             help = "Start time for the candles in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the candles in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
 
     /// Get public funding trade data.
@@ -137,13 +322,45 @@ enum FundingAction {
             long,
             help = "Start time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
+    },
+    /// Rank the lending markets of all currencies by rate, depth, volume or volatility.
+    Scan {
+        #[arg(long, help = "Minimum FRR (daily rate) to include.")]
+        min_rate: Option<f64>,
+
+        #[arg(long, help = "Minimum total provided funding in USD to include.")]
+        min_depth: Option<f64>,
+
+        #[arg(
+            short,
+            long,
+            default_value = "rate",
+            value_parser = PossibleValuesParser::new(["rate", "depth", "volume", "volatility"]),
+            help = "Field to rank the markets by.",
+        )]
+        sort: String,
+    },
+    /// Stream executed funding trades with a rolling weighted-average rate.
+    #[cfg(feature = "ws")]
+    Tape {
+        /// Funding symbol (e.g., "fUSD").
+        symbol: String,
+
+        #[arg(
+            short,
+            long,
+            default_value = "15",
+            value_name = "MINUTES",
+            help = "Window of the weighted-average rate."
+        )]
+        window: u64,
     },
     // --- Authenticated actions --- ///
     /// Submit a new funding offer
@@ -166,10 +383,9 @@ enum FundingAction {
             short,
             long,
             required = true,
-            value_parser = value_parser!(u8).range(2..=120),
             help = "Period of the funding offer in days (2-120).",
         )]
-        period: u8,
+        period: FundingPeriod,
 
         #[arg(
             long,
@@ -193,10 +409,45 @@ enum FundingAction {
         /// Symbol to get the funding credit for (e.g., "fUSD", "fBTC").
         symbol: String,
     },
+    /// Return taken funding before it expires.
+    Close {
+        /// ID of the loan or credit.
+        id: u64,
+    },
+    /// Keep credits or loans open at expiry.
+    Keep {
+        /// IDs of the credits or loans.
+        #[arg(required = true)]
+        ids: Vec<u64>,
+
+        #[arg(
+            short,
+            long = "type",
+            default_value = "credit",
+            value_parser = PossibleValuesParser::new(["credit", "loan"]),
+            help = "Kind of the funding IDs."
+        )]
+        typ: String,
+    },
     /// Funds used in active positions
     Credits {
         /// Symbol to get the funding credit for (e.g., "fUSD", "fBTC").
         symbol: String,
+
+        #[arg(long, help = "Group the credits by the trading pair using them.")]
+        by_pair: bool,
+    },
+    /// Expected interest of active credits over the next 1, 7 and 30 days,
+    /// assuming they are held to maturity at their current rate.
+    Forecast {
+        /// Comma separated funding symbols (e.g. fUSD,fUST).
+        #[arg(value_delimiter = ',', required = true)]
+        symbols: Vec<String>,
+    },
+    /// Yield and duration of your lending and borrowing.
+    Info {
+        /// Symbol to get the funding info for (e.g., "fUSD", "fBTC").
+        symbol: String,
     },
     /// Get past inactive funding offers.
     HistOffers {
@@ -215,13 +466,13 @@ enum FundingAction {
             long,
             help = "Start time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
     /// Inactive funds used in positions.
     HistCredits {
@@ -240,13 +491,13 @@ enum FundingAction {
             long,
             help = "Start time for the credits in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the credits in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
 }
 
@@ -256,9 +507,21 @@ enum AuthAction {
     /// Get current user information.
     UserInfo,
     /// Get all wallets of current user.
-    Wallets,
+    Wallets {
+        #[arg(
+            long,
+            value_parser = parse_datetime_or_date,
+            help = "Show balances at a past point in time, as ISO 8601 or a date (end of day, e.g., 2024-12-31)."
+        )]
+        at: Option<DateTime<Utc>>,
+
+        #[arg(long, help = "Only show wallets of this currency, used with --at.")]
+        ccy: Option<String>,
+    },
     /// Get permissions of current API key.
     KeyPermission,
+    /// Current trading fees, 30-day volume and the volume needed for the next fee tier.
+    FeeTier,
     /// Get ledger records of current user.
     Ledger {
         /// Currency to filter the ledger records by.
@@ -276,11 +539,40 @@ enum AuthAction {
         #[arg(
             short,
             long,
-            default_value = "Interest",
+            value_delimiter = ',',
             value_parser = PossibleValuesParser::new(["Interest", "Exchange", "Transfer", "TradingFee"]),
-            help = "Type of ledger records to return.",
+            help = "Types of ledger records to return, comma separated. All types if not specified.",
+        )]
+        category: Vec<String>,
+
+        #[arg(long, help = "Only show staking rewards among the returned records.")]
+        staking: bool,
+    },
+    /// Get deposits and withdrawals of current user.
+    Movements {
+        /// Currency to filter the movements by. All currencies if not specified.
+        ccy: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            default_value="25",
+            value_parser = value_parser!(u16).range(1..=1000),
+            help = "Number of records to return (max: 1000).",
+        )]
+        limit: Option<u16>,
+
+        #[arg(
+            long,
+            help = "Start time for the movements in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        start: Option<DateTime<Utc>>,
+
+        #[arg(
+            long,
+            help = "End time for the movements in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        category: Option<String>,
+        end: Option<DateTime<Utc>>,
     },
     /// Get wallet addresses for deposit
     DepositAddress {
@@ -297,14 +589,152 @@ enum AuthAction {
             short,
             long,
             default_value = "tetherusl",
-            value_parser = PossibleValuesParser::new([
-                "bitcoin", "litecoin", "ethereum", "tetheruso", "tetherusl", "tetherusx", "tetheruss",
-                "ethereumc", "zcash", "monero", "iota"]),
-            help = "Deposit method"
-
+            help = "Deposit method, see `public deposit-methods`."
         )]
         method: String,
     },
+    /// Generate a Lightning Network invoice to deposit BTC to the exchange wallet.
+    DepositLn {
+        #[arg(short, long, help = "Amount of BTC to deposit (e.g., 0.01).")]
+        amount: String,
+    },
+}
+
+/// Bitfinex Pay invoices for merchants
+#[derive(Subcommand)]
+enum PayAction {
+    /// Create an invoice.
+    Create {
+        /// Amount of the invoice.
+        amount: f64,
+
+        #[arg(long, default_value = "USD", help = "Currency of the amount.")]
+        currency: String,
+
+        #[arg(
+            long,
+            required = true,
+            value_delimiter = ',',
+            help = "Comma separated currencies the customer can pay with (e.g., BTC,ETH,UST-ETH,LNX)."
+        )]
+        pay_currencies: Vec<String>,
+
+        #[arg(long, required = true, help = "Order ID of the merchant.")]
+        order_id: String,
+
+        #[arg(
+            long,
+            value_parser = value_parser!(u32).range(300..=86400),
+            help = "Seconds the invoice stays open (300-86400)."
+        )]
+        duration: Option<u32>,
+
+        #[arg(long, help = "URL notified on status changes of the invoice.")]
+        webhook: Option<String>,
+
+        #[arg(long, help = "URL the customer is redirected to after paying.")]
+        redirect_url: Option<String>,
+
+        #[arg(
+            long,
+            required = true,
+            value_parser = parse_json,
+            help = "Customer details as a JSON object, see the Bitfinex Pay docs."
+        )]
+        customer_info: serde_json::Value,
+    },
+    /// List invoices.
+    Invoices {
+        #[arg(long, help = "Only get the invoice of this ID.")]
+        id: Option<String>,
+
+        #[arg(
+            long,
+            default_value = "20",
+            value_parser = value_parser!(u16).range(1..=100),
+            help = "Number of invoices to return (max 100).",
+        )]
+        limit: Option<u16>,
+
+        #[arg(
+            long,
+            help = "Start time for the invoices in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        start: Option<DateTime<Utc>>,
+
+        #[arg(
+            long,
+            help = "End time for the invoices in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        end: Option<DateTime<Utc>>,
+    },
+    /// Manually complete an invoice paid by an unmatched deposit.
+    Complete {
+        /// ID of the invoice.
+        id: String,
+
+        #[arg(long, required = true, help = "Currency the invoice was paid with.")]
+        pay_ccy: String,
+
+        #[arg(long, help = "ID of the deposit.")]
+        deposit_id: Option<u64>,
+
+        #[arg(long, help = "ID of the ledger entry of the deposit.")]
+        ledger_id: Option<u64>,
+    },
+    /// Expire an open invoice.
+    Expire {
+        /// ID of the invoice.
+        id: String,
+    },
+}
+
+/// Bitfinex Pulse social posts
+#[derive(Subcommand)]
+enum PulseAction {
+    /// Get the latest public Pulse messages.
+    Hist {
+        #[arg(
+            long,
+            default_value = "20",
+            value_parser = value_parser!(u16).range(1..=100),
+            help = "Number of messages to return (max 100).",
+        )]
+        limit: Option<u16>,
+
+        #[arg(
+            long,
+            help = "End time for the messages in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        end: Option<DateTime<Utc>>,
+    },
+    /// Get the profile of a Pulse user.
+    Profile {
+        /// Nickname of the user.
+        nickname: String,
+    },
+    /// Post a Pulse message.
+    Add {
+        #[arg(long, required = true, help = "Title of the message.")]
+        title: String,
+
+        #[arg(long, required = true, help = "Content of the message.")]
+        content: String,
+
+        #[arg(long, help = "ID of the message to comment on.")]
+        parent: Option<String>,
+
+        #[arg(long, help = "Only visible to you.")]
+        private: bool,
+
+        #[arg(long, help = "Pin the message to your profile.")]
+        pin: bool,
+    },
+    /// Delete one of your Pulse messages.
+    Del {
+        /// ID of the message.
+        id: String,
+    },
 }
 
 /// Public endpoints that does not related to trading nor funding
@@ -349,13 +779,13 @@ enum PublicAction {
             long,
             help = "Start time for the stats in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the stats in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
     /// Get exchange rate for a specified currency pair.
     ExRate {
@@ -366,11 +796,35 @@ enum PublicAction {
     }, // Exchange Rate
 
     /// All available pairs on Bitfinex.
-    AvailPairs,
+    AvailPairs {
+        #[arg(
+            long,
+            default_value = "exchange",
+            value_parser = PossibleValuesParser::new(["exchange", "margin", "futures"]),
+            help = "Market of the pairs.",
+        )]
+        market: String,
+    },
 
     /// All available currencies on Bitfinex.
     AvailCurrencies,
 
+    /// Deposit methods and the currencies they move.
+    DepositMethods,
+
+    /// Deposit and withdrawal status of deposit methods.
+    TxStatus {
+        #[arg(long, help = "Only show methods with deposits or withdrawals paused.")]
+        paused: bool,
+    },
+
+    /// Full name, symbol, unit and block explorer of currencies.
+    Currencies {
+        /// Comma separated currencies to show (e.g. BTC,UST). All by default.
+        #[arg(value_delimiter = ',')]
+        currencies: Vec<String>,
+    },
+
     /// Get the current status of the platform, “Operative” or “Maintenance”.
     PlatformStatus,
 
@@ -394,16 +848,81 @@ enum PublicAction {
             long,
             help = "Start time for the stats in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the stats in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
+    },
+
+    /// Rank trading pairs of a quote currency by 24h change or volume.
+    Movers {
+        #[arg(long, default_value = "USD", help = "Quote currency of the pairs.")]
+        quote: String,
+
+        #[arg(long, default_value = "20", help = "Number of pairs to show.")]
+        top: usize,
+
+        #[arg(
+            short,
+            long,
+            default_value = "change",
+            value_parser = PossibleValuesParser::new(["change", "volume"]),
+            help = "Rank by absolute 24h change or by 24h volume in quote currency.",
+        )]
+        sort: String,
+    },
+
+    /// Screen trading pairs by top-of-book spread and visible book depth.
+    Screen {
+        #[arg(long, help = "Maximum top-of-book spread in basis points.")]
+        max_spread_bps: Option<f64>,
+
+        #[arg(long, help = "Minimum visible depth in USD on both sides of the book.")]
+        min_depth_usd: Option<f64>,
     },
 }
 
+/// Order flags of `trading submit` and `trading update`.
+///
+/// Ref: <https://docs.bitfinex.com/docs/flag-values>
+#[derive(Args)]
+struct OrderFlagArgs {
+    #[arg(long, help = "Hide the order from the order book.")]
+    hidden: bool,
+
+    #[arg(long, help = "Close the position if present.")]
+    close: bool,
+
+    #[arg(long, help = "Only reduce the current position.")]
+    reduce_only: bool,
+
+    #[arg(long, help = "Cancel the order if it would match immediately.")]
+    post_only: bool,
+
+    #[arg(long, help = "One-Cancels-Other, requires the OCO stop price.")]
+    oco: bool,
+
+    #[arg(long, help = "Exclude variable rate funding offers from matching.")]
+    no_var_rates: bool,
+}
+
+impl OrderFlagArgs {
+    /// `None` if no flag is set.
+    fn flags(&self) -> Option<OrderFlags> {
+        let flags = OrderFlags::empty()
+            .hidden(self.hidden)
+            .close(self.close)
+            .reduce_only(self.reduce_only)
+            .post_only(self.post_only)
+            .oco(self.oco)
+            .no_var_rates(self.no_var_rates);
+        (!flags.is_empty()).then_some(flags)
+    }
+}
+
 /// Trading/exchange related utilities
 #[derive(Subcommand)]
 enum TradingAction {
@@ -426,6 +945,60 @@ enum TradingAction {
     RawBook { symbol: String },
     /// Get current tick of symbol
     Ticker { symbol: String },
+    /// Get hourly bid/ask snapshots of symbols
+    TickersHist {
+        /// Comma separated trading pairs (e.g. tBTCUSD,tETHUSD), or ALL.
+        #[arg(value_delimiter = ',', required = true)]
+        symbols: Vec<String>,
+
+        #[arg(
+            long,
+            default_value = "100",
+            value_parser = value_parser!(u16).range(1..=250),
+            help = "Number of snapshots to return (max 250).",
+        )]
+        limit: Option<u16>,
+
+        #[arg(
+            long,
+            help = "Start time for the snapshots in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        start: Option<DateTime<Utc>>,
+
+        #[arg(
+            long,
+            help = "End time for the snapshots in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        end: Option<DateTime<Utc>>,
+    },
+    /// Order size limits and margins of pairs.
+    PairInfo {
+        /// Comma separated trading pairs (e.g. tBTCUSD,tETHUSD). All by default.
+        #[arg(value_delimiter = ',')]
+        symbols: Vec<String>,
+    },
+    /// Get historical liquidations of margin and derivatives positions.
+    Liquidations {
+        #[arg(
+            long,
+            default_value = "100",
+            value_parser = value_parser!(u16).range(1..=500),
+            help = "Number of liquidations to return (max 500).",
+        )]
+        limit: Option<u16>,
+
+        #[arg(
+            long,
+            help = "Start time for the liquidations in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        start: Option<DateTime<Utc>>,
+
+        #[arg(
+            long,
+            help = "End time for the liquidations in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        end: Option<DateTime<Utc>>,
+    },
     /// Get candles of symbol
     Candles {
         symbol: String,
@@ -434,7 +1007,7 @@ enum TradingAction {
             short,
             long,
             default_value = "30m",
-            value_parser = PossibleValuesParser::new(["1m", "5m", "15m", "30m", "1h", "3h", "4h", "6h", "12h", "1d", "1w", "2w", "1M"]),
+            value_parser = PossibleValuesParser::new(["1m", "5m", "15m", "30m", "1h", "3h", "6h", "12h", "1d", "1w", "2w", "1M"]),
             help = "Time frame for the candles. Default is 30 minutes.",
         )]
         time_frame: Option<String>,
@@ -451,13 +1024,13 @@ enum TradingAction {
             long,
             help = "Start time for the candles in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the candles in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
     /// Get public trades records
     Trades {
@@ -477,13 +1050,13 @@ enum TradingAction {
             long,
             help = "Start time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
     // --- Authenticated Actions --- //
     /// Gets all the current user's active orders.
@@ -509,6 +1082,30 @@ enum TradingAction {
         )]
         client_id_date: Option<String>,
     },
+    /// Get all active margin and derivatives positions.
+    Positions,
+    /// Margin balances, and the buy/sell availability of a pair if given.
+    Margin {
+        /// Trading symbol (e.g. tBTCUSD).
+        symbol: Option<String>,
+    },
+    /// Realized PnL and fees grouped by the strategy tag of orders.
+    Attribution {
+        #[arg(short, long, help = "Symbol to filter trades")]
+        symbol: Option<String>,
+
+        #[arg(
+            long,
+            help = "Start time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        start: Option<DateTime<Utc>>,
+
+        #[arg(
+            long,
+            help = "End time for the trades in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
+        )]
+        end: Option<DateTime<Utc>>,
+    },
     /// Retrieves all user's closed/cancelled orders up to 2 weeks in the past.
     HistOrders {
         #[arg(short, long, help = "Symbol to filter orders")]
@@ -527,13 +1124,13 @@ enum TradingAction {
             long,
             help = "Start time for the orders in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        start: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
 
         #[arg(
             long,
             help = "End time for the orders in ISO 8601 format (e.g., 2025-01-01T00:00:00Z)."
         )]
-        end: Option<DateTime<Local>>,
+        end: Option<DateTime<Utc>>,
     },
     /// Submits an order on a trading pair (e.g. tBTCUSD, tLTCBTC, ...).
     Submit {
@@ -590,11 +1187,8 @@ enum TradingAction {
         )]
         cid: Option<u32>,
 
-        #[arg(
-            long,
-            help = "The sum of all order flags. See: https://docs.bitfinex.com/docs/flag-values"
-        )]
-        flags: Option<u32>,
+        #[command(flatten)]
+        flags: OrderFlagArgs,
 
         #[arg(
             long,
@@ -602,6 +1196,15 @@ enum TradingAction {
             help = "Datetime for automatic order cancellation"
         )]
         time_in_force: Option<String>,
+
+        #[arg(
+            long,
+            help = "Strategy label stored in the order meta, used by attribution."
+        )]
+        tag: Option<String>,
+
+        #[arg(long, help = "Affiliate code stored in the order meta.")]
+        aff_code: Option<String>,
     },
     /// Updates an existing order, can be used to update margin, exchange, and derivative orders.
     Update {
@@ -649,11 +1252,8 @@ enum TradingAction {
         #[arg(long, value_name = "YYYY-MM-DD", help = "Date of Client Order ID.")]
         cid_date: Option<String>,
 
-        #[arg(
-            long,
-            help = "The sum of all order flags. See: https://docs.bitfinex.com/docs/flag-values"
-        )]
-        flags: Option<u32>,
+        #[command(flatten)]
+        flags: OrderFlagArgs,
 
         #[arg(
             long,
@@ -662,7 +1262,12 @@ enum TradingAction {
         )]
         time_in_force: Option<String>,
     },
-    /// Cancels one of the current user's orders.
+    /// Pick an open order interactively and update its amount and price.
+    Amend {
+        #[arg(short, long, help = "Symbol to filter orders")]
+        symbol: Option<String>,
+    },
+    /// Cancels one of the current user's orders, or a group of them.
     Cancel {
         #[arg(short, long, help = "ID of the order.")]
         id: Option<u64>,
@@ -676,17 +1281,349 @@ enum TradingAction {
 
         #[arg(long, value_name = "YYYY-MM-DD", help = "Date of Client Order ID.")]
         cid_date: Option<String>,
-    },
-    /// Cancels all of the current user's orders, including derivative.
-    CancelAll,
-}
 
-fn load_key() -> (String, String) {
+        #[arg(
+            short,
+            long,
+            conflicts_with_all = ["id", "cid"],
+            help = "Group ID; cancels all orders of the group."
+        )]
+        gid: Option<u64>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["id", "cid", "gid"],
+            value_parser = parse_order_filter,
+            help = "Cancel all active orders matching conditions joined by 'and', \
+                    e.g. \"price<25000 and symbol=tBTCUSD\". Fields: id, gid, symbol, type, price, amount."
+        )]
+        filter: Option<OrderFilter>,
+
+        #[arg(
+            long,
+            requires = "filter",
+            help = "Only print the orders matching the filter."
+        )]
+        preview: bool,
+    },
+    /// Cancels all of the current user's orders, including derivative.
+    CancelAll,
+    /// Delta-hedge the balance and margin positions of a currency with a
+    /// market order on a derivative pair.
+    Hedge {
+        /// Currency to hedge (e.g. BTC).
+        ccy: String,
+
+        #[arg(
+            short,
+            long,
+            help = "Derivative pair of the hedge (e.g. tBTCF0:USTF0)."
+        )]
+        symbol: String,
+
+        #[arg(long, help = "Only print the hedge order without submitting it.")]
+        dry_run: bool,
+    },
+    /// Claims a margin position, converting it to exchange holdings.
+    Claim {
+        /// ID of the position.
+        id: u64,
+
+        #[arg(
+            short,
+            long,
+            help = "Amount to claim. The whole position if not specified."
+        )]
+        amount: Option<String>,
+    },
+    /// Increases an existing derivative position.
+    Increase {
+        /// Symbol of the position (e.g. tBTCF0:USTF0).
+        symbol: String,
+
+        #[arg(
+            short,
+            long,
+            required = true,
+            help = "Amount to increase the position by."
+        )]
+        amount: String,
+
+        #[arg(long, help = "Only preview the limits and required collateral.")]
+        preview: bool,
+    },
+    /// Sets the collateral of a derivative position, or shows its limits.
+    Collateral {
+        /// Symbol of the position (e.g. tBTCF0:USTF0).
+        symbol: String,
+
+        /// New collateral of the position. Shows the collateral limits if not given.
+        collateral: Option<f64>,
+    },
+}
+
+/// Integrity checks of market data
+#[derive(Subcommand)]
+enum VerifyAction {
+    /// Check trading candles for gaps, duplicates and non-monotonic timestamps.
+    Candles {
+        /// Trading symbol (e.g. tBTCUSD).
+        symbol: String,
+
+        #[arg(
+            short,
+            long,
+            default_value = "1h",
+            value_parser = PossibleValuesParser::new(["1m", "5m", "15m", "30m", "1h", "3h", "6h", "12h", "1d", "1w", "2w", "1M"]),
+            help = "Time frame for the candles.",
+        )]
+        timeframe: String,
+
+        #[arg(
+            long,
+            default_value = "90d",
+            value_parser = parse_lookback,
+            help = "How far back to check, as a number with unit m, h, d or w (e.g., 90d)."
+        )]
+        last: chrono::Duration,
+    },
+}
+
+/// Diagnostics built on public market data
+#[derive(Subcommand)]
+enum AnalyzeAction {
+    /// Round-trip rates across the pairs of three currencies, including
+    /// taker fees, to spot arbitrage and inconsistent cross rates.
+    Triangle {
+        /// Currencies of the triangle (e.g. BTC USD UST).
+        #[arg(num_args = 3, required = true)]
+        ccys: Vec<String>,
+
+        #[arg(
+            long,
+            default_value_t = report::FEE_TIERS[0].taker_fee,
+            help = "Taker fee in percent paid on every conversion."
+        )]
+        fee: f64,
+    },
+}
+
+/// Reports on the account history
+#[derive(Subcommand)]
+enum ReportAction {
+    /// Fill rate and time to fill of past funding offers.
+    OfferStats {
+        /// Symbol of the funding offers (e.g., "fUSD", "fBTC").
+        symbol: String,
+
+        #[arg(
+            long,
+            default_value = "30d",
+            value_parser = parse_lookback,
+            help = "How far back to look, as a number with unit m, h, d or w (e.g., 30d)."
+        )]
+        last: chrono::Duration,
+    },
+    /// Daily account equity valued in a quote currency, replayed from the
+    /// ledgers.
+    Equity {
+        #[arg(
+            long,
+            default_value = "180d",
+            value_parser = parse_lookback,
+            help = "How far back to look, as a number with unit m, h, d or w (e.g., 180d)."
+        )]
+        last: chrono::Duration,
+
+        #[arg(
+            short,
+            long,
+            default_value = "USD",
+            help = "Currency to value the equity in."
+        )]
+        quote: String,
+
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Also write the series to this CSV file."
+        )]
+        csv: Option<PathBuf>,
+
+        #[arg(long, help = "Print the series as a bar chart instead of a table.")]
+        chart: bool,
+    },
+    /// Average slippage of trades against their order price.
+    Slippage {
+        #[arg(short, long, help = "Symbol to filter trades")]
+        symbol: Option<String>,
+
+        #[arg(
+            long,
+            default_value = "30d",
+            value_parser = parse_lookback,
+            help = "How far back to look, as a number with unit m, h, d or w (e.g., 30d)."
+        )]
+        last: chrono::Duration,
+    },
+}
+
+/// Parse a lookback such as 30m, 12h, 90d or 2w.
+fn parse_lookback(value: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("Invalid duration: {value}");
+    let split = value.char_indices().last().map_or(0, |(idx, _)| idx);
+    let (num, unit) = value.split_at(split);
+    let num: i64 = num.parse().map_err(|_| invalid())?;
+    match unit {
+        "m" => Ok(chrono::Duration::minutes(num)),
+        "h" => Ok(chrono::Duration::hours(num)),
+        "d" => Ok(chrono::Duration::days(num)),
+        "w" => Ok(chrono::Duration::weeks(num)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_json(value: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(value).map_err(|e| format!("Invalid JSON: {e}"))
+}
+
+/// Conditions of `trading cancel --filter`, all of which must match.
+#[derive(Clone)]
+struct OrderFilter(Vec<OrderCondition>);
+
+#[derive(Clone)]
+struct OrderCondition {
+    field: String,
+    op: &'static str,
+    value: String,
+}
+
+impl OrderFilter {
+    fn matches(&self, order: &TradingOrder) -> bool {
+        self.0.iter().all(|c| c.matches(order))
+    }
+}
+
+impl OrderCondition {
+    fn matches(&self, order: &TradingOrder) -> bool {
+        let number = match self.field.as_str() {
+            "symbol" => return self.compare_text(&order.symbol),
+            "type" => return self.compare_text(&order.order_type.to_string()),
+            "id" => order.id as f64,
+            "gid" => order.group_id.unwrap_or(0) as f64,
+            "price" => order.price,
+            "amount" => order.amount,
+            _ => return false,
+        };
+        // Checked to be a number when parsed
+        let value: f64 = self.value.parse().unwrap_or(f64::NAN);
+        match self.op {
+            "<=" => number <= value,
+            ">=" => number >= value,
+            "!=" => number != value,
+            "<" => number < value,
+            ">" => number > value,
+            _ => number == value,
+        }
+    }
+
+    fn compare_text(&self, text: &str) -> bool {
+        let equal = text.eq_ignore_ascii_case(&self.value);
+        if self.op == "!=" { !equal } else { equal }
+    }
+}
+
+/// Parse a filter such as `price<25000 and symbol=tBTCUSD`. Conditions are
+/// joined by `and` in any case, surrounded by whitespace.
+fn parse_order_filter(value: &str) -> Result<OrderFilter, String> {
+    let mut clauses = vec![Vec::new()];
+    for word in value.split_whitespace() {
+        if word.eq_ignore_ascii_case("and") {
+            clauses.push(Vec::new());
+        } else if let Some(clause) = clauses.last_mut() {
+            clause.push(word);
+        }
+    }
+
+    let mut conditions = Vec::new();
+    for clause in clauses {
+        let clause = clause.join(" ");
+        if clause.is_empty() {
+            return Err(String::from("Empty condition, expected e.g. price<25000"));
+        }
+        let Some((idx, op)) = ["<=", ">=", "!=", "<", ">", "="]
+            .iter()
+            .filter_map(|op| clause.find(op).map(|idx| (idx, *op)))
+            .min_by_key(|(idx, op)| (*idx, std::cmp::Reverse(op.len())))
+        else {
+            return Err(format!("Missing operator in condition: {clause}"));
+        };
+        let field = clause[..idx].trim().to_lowercase();
+        let value = clause[idx + op.len()..]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string();
+        if value.is_empty() {
+            return Err(format!("Missing value in condition: {clause}"));
+        }
+        match field.as_str() {
+            "symbol" | "type" if op == "=" || op == "!=" => {}
+            "symbol" | "type" => return Err(format!("Only = and != apply to {field}")),
+            "id" | "gid" | "price" | "amount" if value.parse::<f64>().is_ok() => {}
+            "id" | "gid" | "price" | "amount" => {
+                return Err(format!("Not a number in condition: {clause}"));
+            }
+            _ => return Err(format!("Unknown field: {field}")),
+        }
+        conditions.push(OrderCondition { field, op, value });
+    }
+    Ok(OrderFilter(conditions))
+}
+
+/// Accept either a full ISO 8601 time or a plain date, which means the end of
+/// that day in local time.
+fn parse_datetime_or_date(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = value.parse::<DateTime<Utc>>() {
+        return Ok(time);
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_milli_opt(23, 59, 59, 999))
+        .and_then(|time| time.and_local_timezone(Local).single())
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("Invalid time: {value}"))
+}
+
+/// Ask for a value on stdin. Returns `None` when the input is empty.
+fn prompt(label: &str) -> Option<String> {
+    print!("{label}: ");
+    std::io::stdout().flush().expect("Failed to flush stdout");
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read from stdin");
+    let input = input.trim();
+    (!input.is_empty()).then(|| input.to_string())
+}
+
+/// Ask for a number on stdin until the input parses. Returns `None` when the
+/// input is empty.
+fn prompt_number(label: &str) -> Option<f64> {
+    loop {
+        let input = prompt(label)?;
+        match input.parse::<f64>() {
+            Ok(number) if number.is_finite() => return Some(number),
+            _ => println!("Not a number: {input}"),
+        }
+    }
+}
+
+fn load_key() -> (String, String) {
     // Try to load from env var
     let api_key = std::env::var("API_KEY").ok();
     let api_secret = std::env::var("API_SECRET").ok();
-    if api_key.is_some() && api_secret.is_some() {
-        return (api_key.unwrap(), api_secret.unwrap());
+    if let (Some(api_key), Some(api_secret)) = (api_key, api_secret) {
+        return (api_key, api_secret);
     }
 
     // Load from .env file
@@ -698,17 +1635,53 @@ fn load_key() -> (String, String) {
     (api_key, api_secret)
 }
 
+/// Apply the request settings, from the flags or else the environment
+/// (including the .env file once the key is loaded).
+fn configure(mut client: Client) -> Client {
+    let config = REQUEST_CONFIG.get();
+    let timeout = config
+        .and_then(|c| c.timeout)
+        .or_else(|| std::env::var("BFX_TIMEOUT").ok()?.parse().ok());
+    let retries = config
+        .and_then(|c| c.retries)
+        .or_else(|| std::env::var("BFX_RETRIES").ok()?.parse().ok());
+    if let Some(timeout) = timeout {
+        client = client.with_timeout(Duration::from_secs(timeout));
+    }
+    if let Some(retries) = retries {
+        client = client.with_retries(retries);
+    }
+    client
+}
+
 fn get_client_with_key() -> Client {
     let (api_key, api_secret) = load_key();
-    Client::new(api_key, api_secret)
+    configure(Client::new(api_key, api_secret))
 }
 
 fn get_client() -> Client {
-    Client::new(String::new(), String::new())
+    configure(Client::new(String::new(), String::new()))
 }
 
 pub async fn main() {
     let cli = Cli::parse();
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+    let _ = REQUEST_CONFIG.set(RequestConfig {
+        timeout: cli.timeout,
+        retries: cli.retries,
+    });
+    if let Some(history) = &cli.history {
+        let _ = HISTORY_FILE.set(history.clone());
+    }
+    if let Some(locale) = cli.locale {
+        let _ = DISPLAY_LOCALE.set(locale);
+    }
+    if let Some(round) = cli.round {
+        let _ = ROUNDING.set(round);
+    }
 
     match &cli.command {
         Commands::Public { action } => {
@@ -723,6 +1696,35 @@ pub async fn main() {
         Commands::Trading { action } => {
             process_trading_action(action).await;
         }
+        Commands::Verify { action } => {
+            process_verify_action(action).await;
+        }
+        Commands::Report { action } => {
+            process_report_action(action).await;
+        }
+        Commands::Analyze { action } => {
+            process_analyze_action(action).await;
+        }
+        Commands::Pay { action } => {
+            process_pay_action(action).await;
+        }
+        Commands::Pulse { action } => {
+            process_pulse_action(action).await;
+        }
+        Commands::Doctor => {
+            let checks = doctor().await;
+            pretty_print::print_doctor(&checks);
+            if checks.iter().any(|c| c.status == "fail") {
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "self-update")]
+        Commands::SelfUpdate { check } => {
+            if let Err(e) = self_update(*check).await {
+                eprintln!("Self-update failed: {e}");
+                std::process::exit(1);
+            }
+        }
     }
 }
 
@@ -746,8 +1748,8 @@ async fn process_public_action(action: &PublicAction) {
                     side_pair.clone(),
                     *use_short,
                     *limit,
-                    start.clone(),
-                    end.clone(),
+                    *start,
+                    *end,
                 )
                 .await
                 .unwrap();
@@ -760,14 +1762,36 @@ async fn process_public_action(action: &PublicAction) {
                 .unwrap();
             pretty_print_json(&rate);
         }
-        PublicAction::AvailPairs => {
-            let pairs = client.request_avail_exchange_pairs().await.unwrap();
+        PublicAction::AvailPairs { market } => {
+            let pairs = match market.as_str() {
+                "margin" => client.request_avail_margin_pairs().await.unwrap(),
+                "futures" => client.request_avail_futures_pairs().await.unwrap(),
+                _ => client.request_avail_exchange_pairs().await.unwrap(),
+            };
             pretty_print::print_vec_string("Available Pairs", &pairs);
         }
         PublicAction::AvailCurrencies => {
             let currencies = client.request_avail_ccy_list().await.unwrap();
             pretty_print::print_vec_string("Available Currencies", &currencies);
         }
+        PublicAction::DepositMethods => {
+            let methods = client.request_deposit_methods().await.unwrap();
+            pretty_print::print_deposit_methods(&methods);
+        }
+        PublicAction::TxStatus { paused } => {
+            let mut status = client.request_tx_status().await.unwrap();
+            if *paused {
+                status.retain(TxStatus::is_paused);
+            }
+            pretty_print::print_tx_status(&status);
+        }
+        PublicAction::Currencies { currencies } => {
+            let mut info = client.request_currency_info().await.unwrap();
+            if !currencies.is_empty() {
+                info.retain(|ccy, _| currencies.iter().any(|c| c.eq_ignore_ascii_case(ccy)));
+            }
+            pretty_print::print_currency_info(&info);
+        }
         PublicAction::PlatformStatus => {
             let status = client.request_platform_status().await.unwrap();
             pretty_print::print_platform_status(&status);
@@ -783,11 +1807,44 @@ async fn process_public_action(action: &PublicAction) {
             end,
         } => {
             let stats = client
-                .request_funding_stats(symbol, limit.clone(), start.clone(), end.clone())
+                .request_funding_stats(symbol, *limit, *start, *end)
                 .await
                 .unwrap();
             pretty_print::print_funding_stats(&stats);
         }
+        PublicAction::Movers { quote, top, sort } => {
+            let mut tickers = client.request_trading_tickers(&["ALL"]).await.unwrap();
+            // Pairs are either tBTCUSD or tDOGE:USD
+            tickers.retain(|(symbol, _)| {
+                symbol.ends_with(quote.as_str()) && symbol.len() > quote.len() + 1
+            });
+            let key = |t: &TradingTicker| match sort.as_str() {
+                "volume" => t.volume * t.last_price,
+                _ => t.daily_change_relative.abs(),
+            };
+            tickers.sort_by(|(_, a), (_, b)| key(b).total_cmp(&key(a)));
+            tickers.truncate(*top);
+            pretty_print::print_trading_movers(&tickers);
+        }
+        PublicAction::Screen {
+            max_spread_bps,
+            min_depth_usd,
+        } => {
+            let mut markets = client.screen_trading_market(*max_spread_bps).await.unwrap();
+            let depth = |m: &TradingMarket| {
+                m.bid_depth_usd
+                    .zip(m.ask_depth_usd)
+                    .map_or(0.0, |(bid, ask)| bid.min(ask))
+            };
+            markets.retain(|m| min_depth_usd.is_none_or(|d| depth(m) >= d));
+            // Tightest spread first, deeper book on ties
+            markets.sort_by(|a, b| {
+                a.spread_bps
+                    .total_cmp(&b.spread_bps)
+                    .then(depth(b).total_cmp(&depth(a)))
+            });
+            pretty_print::print_trading_market(&markets);
+        }
     }
 }
 
@@ -798,36 +1855,67 @@ async fn process_auth_action(action: &AuthAction) {
             let result = client.request_user_info().await.unwrap();
             pretty_print::print_user_info(&result);
         }
-        AuthAction::Wallets => {
+        AuthAction::Wallets { at: None, .. } => {
             let wallets = client.request_wallets().await.unwrap();
             pretty_print::print_wallet(&wallets);
         }
+        AuthAction::Wallets { at, ccy } => {
+            let wallets = client
+                .request_wallets_hist(*at, ccy.as_deref())
+                .await
+                .unwrap();
+            pretty_print::print_wallet_hist(&wallets);
+        }
         AuthAction::KeyPermission => {
             let perm = client.request_key_permission().await.unwrap();
             pretty_print::print_key_permission(&perm);
         }
+        AuthAction::FeeTier => {
+            let summary = client.request_account_summary().await.unwrap();
+            pretty_print::print_fee_tier(&report::fee_tier_progress(&summary));
+        }
         AuthAction::Ledger {
             ccy,
             limit,
             category,
+            staking,
         } => {
-            let cat = category.clone().unwrap();
-            let result = client
-                .request_ledger(ccy, *limit, Some(cat.as_str().into()))
+            let categories = category.iter().map(|c| c.as_str().into()).collect();
+            let mut result = client
+                .request_ledger(ccy, *limit, categories)
                 .await
                 .unwrap();
+            if *staking {
+                result.retain(Ledger::is_staking_payment);
+            }
             pretty_print::print_ledger(&result);
         }
+        AuthAction::Movements {
+            ccy,
+            limit,
+            start,
+            end,
+        } => {
+            let movements = client
+                .request_movements(ccy.as_deref(), *start, *end, *limit)
+                .await
+                .unwrap();
+            pretty_print::print_movements(&movements);
+        }
         AuthAction::DepositAddress {
             wallet_type,
             method,
         } => {
             let addresses = get_client_with_key()
-                .request_deposit_address(wallet_type.as_str().into(), method.as_str().into())
+                .request_deposit_address(wallet_type.as_str().into(), method)
                 .await
                 .unwrap();
             pretty_print_json(&addresses);
         }
+        AuthAction::DepositLn { amount } => {
+            let invoice = client.request_lightning_invoice(amount).await.unwrap();
+            pretty_print_json(&invoice);
+        }
     }
 }
 
@@ -866,10 +1954,10 @@ async fn process_funding_action(action: &FundingAction) {
                     symbol,
                     (*period).unwrap(),
                     agg_period.into(),
-                    time_frame.as_str().into(),
+                    time_frame.as_str().try_into().unwrap(),
                     *limit,
-                    start.clone(),
-                    end.clone(),
+                    *start,
+                    *end,
                 )
                 .await
                 .unwrap();
@@ -882,11 +1970,35 @@ async fn process_funding_action(action: &FundingAction) {
             end,
         } => {
             let trades = get_client()
-                .request_funding_trades(symbol, Some(*limit), start.clone(), end.clone())
+                .request_funding_trades(symbol, Some(*limit), *start, *end)
                 .await
                 .unwrap();
             pretty_print::print_funding_trade(&trades);
         }
+        FundingAction::Scan {
+            min_rate,
+            min_depth,
+            sort,
+        } => {
+            let mut markets = get_client().scan_funding_market().await.unwrap();
+            markets.retain(|m| {
+                min_rate.is_none_or(|r| m.frr >= r)
+                    && min_depth.is_none_or(|d| m.depth_usd.is_some_and(|v| v >= d))
+            });
+            let key = |m: &FundingMarket| match sort.as_str() {
+                "depth" => m.depth_usd.unwrap_or_default(),
+                "volume" => m.volume_usd.unwrap_or_default(),
+                // Lower volatility first
+                "volatility" => -m.volatility.unwrap_or(f64::MAX),
+                _ => m.frr,
+            };
+            markets.sort_by(|a, b| key(b).total_cmp(&key(a)));
+            pretty_print::print_funding_market(&markets);
+        }
+        #[cfg(feature = "ws")]
+        FundingAction::Tape { symbol, window } => {
+            stream_funding_tape(symbol, Duration::from_secs(window * 60)).await;
+        }
         // --- Authenticated actions --- //
         FundingAction::Submit {
             symbol,
@@ -910,8 +2022,22 @@ async fn process_funding_action(action: &FundingAction) {
             pretty_print::print_funding_offer(&vec![result]);
         }
         FundingAction::CancelAll { symbol } => {
-            get_client_with_key().cancel_funding_offer_all(symbol).await;
-            println!("Canceled all funding offers");
+            let result = get_client_with_key()
+                .cancel_funding_offer_all(symbol)
+                .await
+                .unwrap();
+            if !result.is_success() {
+                eprintln!(
+                    "Failed to cancel funding offers: {}",
+                    result.message.as_deref().unwrap_or(&result.status)
+                );
+                return;
+            }
+            if result.offers.is_empty() {
+                println!("Canceled all funding offers");
+            } else {
+                pretty_print::print_funding_offer(&result.offers);
+            }
         }
         FundingAction::Offers { symbol } => {
             let offers = get_client_with_key()
@@ -920,12 +2046,42 @@ async fn process_funding_action(action: &FundingAction) {
                 .unwrap();
             pretty_print::print_funding_offer(&offers);
         }
-        FundingAction::Credits { symbol } => {
+        FundingAction::Forecast { symbols } => {
+            let client = get_client_with_key();
+            let mut credits = Vec::new();
+            for symbol in symbols {
+                credits.extend(client.request_funding_credits(symbol).await.unwrap());
+            }
+            let forecast = report::interest_forecast(&credits, Utc::now());
+            pretty_print::print_interest_forecast(&forecast);
+        }
+        FundingAction::Info { symbol } => {
+            let info = get_client_with_key()
+                .request_funding_info(symbol)
+                .await
+                .unwrap();
+            pretty_print_json(&info);
+        }
+        FundingAction::Close { id } => {
+            let result = get_client_with_key().close_funding(*id).await.unwrap();
+            pretty_print_json(&result);
+        }
+        FundingAction::Keep { ids, typ } => {
+            let result = get_client_with_key()
+                .keep_funding(typ.as_str().into(), ids)
+                .await
+                .unwrap();
+            pretty_print_json(&result);
+        }
+        FundingAction::Credits { symbol, by_pair } => {
             let credits = get_client_with_key()
                 .request_funding_credits(symbol)
                 .await
                 .unwrap();
-            pretty_print::print_funding_credits(&credits);
+            match by_pair {
+                true => pretty_print::print_credit_exposure(&risk::credit_exposure(&credits)),
+                false => pretty_print::print_funding_credits(&credits),
+            }
         }
         FundingAction::HistOffers {
             symbol,
@@ -934,7 +2090,7 @@ async fn process_funding_action(action: &FundingAction) {
             end,
         } => {
             let offers = get_client_with_key()
-                .request_funding_offers_hist(symbol, *limit, start.clone(), end.clone())
+                .request_funding_offers_hist(symbol, *limit, *start, *end)
                 .await
                 .unwrap();
             pretty_print::print_funding_offer(&offers);
@@ -946,7 +2102,7 @@ async fn process_funding_action(action: &FundingAction) {
             end,
         } => {
             let credits = get_client_with_key()
-                .request_funding_credits_hist(symbol, *limit, start.clone(), end.clone())
+                .request_funding_credits_hist(symbol, *limit, *start, *end)
                 .await
                 .unwrap();
             pretty_print::print_funding_credits(&credits);
@@ -972,6 +2128,33 @@ async fn process_trading_action(action: &TradingAction) {
             let ticker = get_client().request_trading_ticker(symbol).await.unwrap();
             pretty_print::print_trading_ticker(&ticker);
         }
+        TradingAction::TickersHist {
+            symbols,
+            limit,
+            start,
+            end,
+        } => {
+            let symbols: Vec<&str> = symbols.iter().map(String::as_str).collect();
+            let tickers = get_client()
+                .request_tickers_hist(&symbols, *limit, *start, *end)
+                .await
+                .unwrap();
+            pretty_print::print_tickers_hist(&tickers);
+        }
+        TradingAction::PairInfo { symbols } => {
+            let mut info = get_client().request_pair_info().await.unwrap();
+            if !symbols.is_empty() {
+                info.retain(|p| symbols.iter().any(|s| s.eq_ignore_ascii_case(&p.symbol)));
+            }
+            pretty_print::print_pair_info(&info);
+        }
+        TradingAction::Liquidations { limit, start, end } => {
+            let liquidations = get_client()
+                .request_liquidations(*limit, *start, *end)
+                .await
+                .unwrap();
+            pretty_print::print_liquidations(&liquidations);
+        }
         TradingAction::Candles {
             symbol,
             time_frame,
@@ -983,10 +2166,10 @@ async fn process_trading_action(action: &TradingAction) {
             let candles = get_client()
                 .request_trading_candles(
                     symbol,
-                    time_frame.as_str().into(),
+                    time_frame.as_str().try_into().unwrap(),
                     *limit,
-                    start.clone(),
-                    end.clone(),
+                    *start,
+                    *end,
                 )
                 .await
                 .unwrap();
@@ -999,7 +2182,7 @@ async fn process_trading_action(action: &TradingAction) {
             end,
         } => {
             let trades = get_client()
-                .request_trading_trades(symbol, Some(*limit), start.clone(), end.clone())
+                .request_trading_trades(symbol, Some(*limit), *start, *end)
                 .await
                 .unwrap();
             pretty_print::print_trading_trade(&trades);
@@ -1021,6 +2204,50 @@ async fn process_trading_action(action: &TradingAction) {
                 .unwrap();
             pretty_print::print_trading_order(&orders);
         }
+        TradingAction::Positions => {
+            let client = get_client_with_key();
+            let positions = client.request_positions().await.unwrap();
+            let symbols: Vec<&str> = positions.iter().map(|p| p.symbol.as_str()).collect();
+            let tickers = match symbols.is_empty() {
+                true => Vec::new(),
+                false => client.request_trading_tickers(&symbols).await.unwrap(),
+            };
+            let pnls = risk::unrealized_pnl(&positions, &tickers);
+            pretty_print::print_positions(&positions, &pnls);
+        }
+        TradingAction::Margin { symbol: None } => {
+            let base = get_client_with_key().request_margin_base().await.unwrap();
+            pretty_print_json(&base);
+        }
+        TradingAction::Margin {
+            symbol: Some(symbol),
+        } => {
+            let info = get_client_with_key()
+                .request_margin_symbol(symbol)
+                .await
+                .unwrap();
+            pretty_print_json(&info);
+        }
+        TradingAction::Attribution { symbol, start, end } => {
+            let client = get_client_with_key();
+            let mut orders = client
+                .request_trading_orders_hist(symbol.clone(), Some(2500), *start, *end)
+                .await
+                .unwrap();
+            // Partially filled orders are still active
+            orders.extend(
+                client
+                    .request_trading_orders(symbol.clone(), None, None, None)
+                    .await
+                    .unwrap(),
+            );
+            let trades = client
+                .request_user_trades(symbol.clone(), Some(2500), *start, *end, None)
+                .await
+                .unwrap();
+            let result = report::attribute_by_tag(&orders, &trades);
+            pretty_print::print_tag_performance(&result);
+        }
         TradingAction::HistOrders {
             symbol,
             limit,
@@ -1030,9 +2257,9 @@ async fn process_trading_action(action: &TradingAction) {
             let orders = get_client_with_key()
                 .request_trading_orders_hist(
                     symbol.clone(),
-                    limit.clone(),
-                    start.clone(),
-                    end.clone(),
+                    *limit,
+                    *start,
+                    *end,
                 )
                 .await
                 .unwrap();
@@ -1051,21 +2278,29 @@ async fn process_trading_action(action: &TradingAction) {
             cid,
             flags,
             time_in_force,
+            tag,
+            aff_code,
         } => {
+            let meta = (tag.is_some() || aff_code.is_some()).then(|| OrderMeta {
+                tag: tag.clone(),
+                aff_code: aff_code.clone(),
+                ..Default::default()
+            });
             let orders = get_client_with_key()
                 .submit_trading_order(
                     symbol,
                     order_type.as_str().into(),
                     amount,
                     price,
-                    lev.clone(),
+                    *lev,
                     price_trailing.clone(),
                     price_aux_limit.clone(),
                     price_oco_stop.clone(),
-                    gid.clone(),
-                    cid.clone(),
-                    flags.clone(),
+                    *gid,
+                    *cid,
+                    flags.flags(),
                     time_in_force.clone(),
+                    meta,
                 )
                 .await
                 .unwrap();
@@ -1091,13 +2326,13 @@ async fn process_trading_action(action: &TradingAction) {
                     amount.clone(),
                     price.clone(),
                     delta.clone(),
-                    lev.clone(),
+                    *lev,
                     price_trailing.clone(),
                     price_aux_limit.clone(),
-                    gid.clone(),
-                    cid.clone(),
+                    *gid,
+                    *cid,
                     cid_date.clone(),
-                    flags.clone(),
+                    flags.flags(),
                     time_in_force.clone(),
                 )
                 .await
@@ -1105,9 +2340,99 @@ async fn process_trading_action(action: &TradingAction) {
 
             pretty_print::print_trading_order(&vec![order]);
         }
-        TradingAction::Cancel { id, cid, cid_date } => {
+        TradingAction::Amend { symbol } => {
+            let client = get_client_with_key();
+            let orders = client
+                .request_trading_orders(symbol.clone(), None, None, None)
+                .await
+                .unwrap();
+            if orders.is_empty() {
+                println!("No open orders");
+                return;
+            }
+            pretty_print::print_trading_order(&orders);
+
+            let order = loop {
+                let Some(input) = prompt("Order ID") else {
+                    return;
+                };
+                match orders.iter().find(|o| o.id.to_string() == input) {
+                    Some(order) => break order,
+                    None => println!("No open order with ID {input}"),
+                }
+            };
+            // Keep the current value on empty input, and only send changes
+            let amount = prompt_number(&format!("Amount [{}]", order.amount))
+                .filter(|a| *a != order.amount)
+                .map(|a| a.to_string());
+            let price = prompt_number(&format!("Price [{}]", order.price))
+                .filter(|p| *p != order.price)
+                .map(|p| p.to_string());
+            if amount.is_none() && price.is_none() {
+                println!("Nothing to update");
+                return;
+            }
+            let confirm = prompt("Submit the update? [y/N]").unwrap_or_default();
+            if !confirm.eq_ignore_ascii_case("y") {
+                return;
+            }
+
+            match client
+                .update_trading_order(
+                    order.id, amount, price, None, None, None, None, None, None, None, None, None,
+                )
+                .await
+            {
+                Ok(order) => pretty_print::print_trading_order(&vec![order]),
+                Err(e) => eprintln!("Failed to update order {}: {e:?}", order.id),
+            }
+        }
+        TradingAction::Cancel {
+            filter: Some(filter),
+            preview,
+            ..
+        } => {
+            let client = get_client_with_key();
+            let orders: Vec<TradingOrder> = client
+                .request_trading_orders(None, None, None, None)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|o| filter.matches(o))
+                .collect();
+            if orders.is_empty() {
+                println!("No active orders match the filter");
+                return;
+            }
+            pretty_print::print_trading_order(&orders);
+            if *preview {
+                return;
+            }
+            let confirm =
+                prompt(&format!("Cancel {} orders? [y/N]", orders.len())).unwrap_or_default();
+            if !confirm.eq_ignore_ascii_case("y") {
+                return;
+            }
+
+            let ids: Vec<u64> = orders.iter().map(|o| o.id).collect();
+            let orders = client
+                .cancel_trading_orders(Some(ids), None, None)
+                .await
+                .unwrap();
+            pretty_print::print_trading_order(&orders);
+        }
+        TradingAction::Cancel { gid: Some(gid), .. } => {
+            let orders = get_client_with_key()
+                .cancel_trading_orders(None, Some(*gid), None)
+                .await
+                .unwrap();
+            pretty_print::print_trading_order(&orders);
+        }
+        TradingAction::Cancel {
+            id, cid, cid_date, ..
+        } => {
             let order = get_client_with_key()
-                .cancel_trading_order(id.clone(), cid.clone(), cid_date.clone())
+                .cancel_trading_order(*id, *cid, cid_date.clone())
                 .await
                 .unwrap();
 
@@ -1120,42 +2445,841 @@ async fn process_trading_action(action: &TradingAction) {
                 .unwrap();
             pretty_print::print_trading_order(&orders);
         }
-    }
-}
-
-fn pretty_print_json<T: serde::Serialize>(data: &T) {
-    match serde_json::to_string_pretty(data) {
-        Ok(json) => println!("{}", json),
-        Err(e) => eprintln!("Error serializing to JSON: {}", e),
-    }
-}
-
-mod pretty_print {
-    use crate::client::{
-        FundingStats, KeyPermission, Ledger, Permission, PlatformStatus, Stat, User, Wallet
-    };
-    use crate::funding::{
-        Candle, FundingBook, FundingBookRaw, FundingCredit, FundingOffer, FundingTicker, FundingTrade
-    };
-    use crate::trading::{TradingBook, TradingBookRaw, TradingOrder, TradingTicker, TradingTrade};
-    use tabled::{builder::Builder, settings::Style};
-
-    fn build_and_print(builder: Builder) {
-        let mut table = builder.build();
-        table.with(Style::rounded());
-        println!("{}", table);
-    }
-
-    pub fn print_vec_string(title: &str, vs: &Vec<String>) {
-        let mut builder = Builder::default();
-        builder.push_record([title]);
+        TradingAction::Hedge {
+            ccy,
+            symbol,
+            dry_run,
+        } => {
+            let client = get_client_with_key();
+            let hedge = client.request_hedge_order(ccy, symbol).await.unwrap();
+            pretty_print_json(&hedge);
+            if *dry_run || hedge.amount == 0.0 {
+                return;
+            }
+            let confirm = prompt(&format!("Submit {} {}? [y/N]", hedge.amount, hedge.symbol))
+                .unwrap_or_default();
+            if !confirm.eq_ignore_ascii_case("y") {
+                return;
+            }
+            let orders = client.submit_hedge_order(&hedge).await.unwrap();
+            if orders.is_empty() {
+                println!("Hedge amount is below the minimum order size, nothing submitted");
+                return;
+            }
+            pretty_print::print_trading_order(&orders);
+        }
+        TradingAction::Claim { id, amount } => {
+            let result = get_client_with_key()
+                .claim_position(*id, amount.as_deref())
+                .await
+                .unwrap();
+            pretty_print::print_positions(&vec![result.position], &[]);
+        }
+        TradingAction::Increase {
+            symbol,
+            amount,
+            preview: true,
+        } => {
+            let info = get_client_with_key()
+                .request_position_increase_info(symbol, amount)
+                .await
+                .unwrap();
+            pretty_print_json(&info);
+        }
+        TradingAction::Increase { symbol, amount, .. } => {
+            let result = get_client_with_key()
+                .increase_position(symbol, amount)
+                .await
+                .unwrap();
+            pretty_print_json(&result);
+        }
+        TradingAction::Collateral {
+            symbol,
+            collateral: None,
+        } => {
+            let limits = get_client_with_key()
+                .calc_deriv_collateral_limits(symbol)
+                .await
+                .unwrap();
+            pretty_print_json(&limits);
+        }
+        TradingAction::Collateral {
+            symbol,
+            collateral: Some(collateral),
+        } => {
+            let updated = get_client_with_key()
+                .set_deriv_collateral(symbol, *collateral)
+                .await
+                .unwrap();
+            record_result(&updated);
+            println!("Collateral updated: {updated}");
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+async fn stream_funding_tape(symbol: &str, window: Duration) {
+    use crate::ws::{Channel, RollingRate, WsClient, WsEvent};
+
+    let mut ws = WsClient::connect().await.unwrap();
+    ws.subscribe(Channel::Trades {
+        symbol: symbol.to_string(),
+    })
+    .unwrap();
+
+    let mut rolling = RollingRate::new(window);
+    println!(
+        "{:<26} {:>12} {:>16} {:>6} {:>12}",
+        "time", "rate", "amount", "period", "avg-rate"
+    );
+    while let Some(event) = ws.next_event().await {
+        let WsEvent::FundingTrades { mut trades, .. } = event else {
+            continue;
+        };
+        // Snapshots are sent from the newest trade
+        trades.sort_by_key(|t| (t.created, t.id));
+        for t in trades {
+            rolling.push(&t);
+            println!(
+                "{:<26} {:>12.8} {:>16.2} {:>6} {:>12.8}",
+                t.created.local().format("%Y-%m-%d %H:%M:%S%.3f"),
+                t.rate,
+                t.amount,
+                t.period,
+                rolling.rate().unwrap_or(t.rate),
+            );
+        }
+    }
+}
+
+/// Result of one `bfx doctor` check.
+#[derive(serde::Serialize)]
+struct DoctorCheck {
+    check: &'static str,
+    /// ok, warn or fail
+    status: &'static str,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl DoctorCheck {
+    fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        DoctorCheck {
+            check,
+            status: "ok",
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        DoctorCheck {
+            check,
+            status: "warn",
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        DoctorCheck {
+            check,
+            status: "fail",
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Parse the KEY=VALUE lines of an env file, skipping blank lines and comments.
+fn parse_env_file(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut entries = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: missing '='", idx + 1));
+        };
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        entries.push((key.trim().to_string(), value.to_string()));
+    }
+    Ok(entries)
+}
+
+async fn doctor() -> Vec<DoctorCheck> {
+    use crate::client::{BITFINEX_AUTH_HOST, BITFINEX_PUB_HOST};
+    use crate::utils::home_dir;
+
+    let mut checks = Vec::new();
+
+    // Config file, looked up like `resolve_env_path_or_create` without creating it
+    let env_path = [
+        Some(PathBuf::from(".bfx_cli.env")),
+        home_dir().map(|h| h.join(".bfx_cli.env")),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|p| p.exists());
+    let mut api_key = std::env::var("API_KEY").ok();
+    let mut api_secret = std::env::var("API_SECRET").ok();
+    match &env_path {
+        None if api_key.is_some() && api_secret.is_some() => {
+            checks.push(DoctorCheck::ok("config", "API key from the environment"));
+        }
+        None => checks.push(DoctorCheck::warn(
+            "config",
+            "No .bfx_cli.env file found",
+            "Run any auth command to create it, or set API_KEY and API_SECRET",
+        )),
+        Some(path) => {
+            let entries = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| parse_env_file(&content));
+            match entries {
+                Err(e) => checks.push(DoctorCheck::fail(
+                    "config",
+                    format!("{}: {e}", path.display()),
+                    "Fix the file, each line must be KEY=VALUE",
+                )),
+                Ok(entries) => {
+                    for (key, value) in entries {
+                        match key.as_str() {
+                            "API_KEY" => api_key = api_key.or(Some(value)),
+                            "API_SECRET" => api_secret = api_secret.or(Some(value)),
+                            _ => {}
+                        }
+                    }
+                    checks.push(DoctorCheck::ok("config", path.display().to_string()));
+                }
+            }
+        }
+    }
+
+    // Reachability of both hosts, also giving the server time
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to build HTTP client");
+    let mut server_time = None;
+    for (check, host) in [
+        ("pub-host", BITFINEX_PUB_HOST),
+        ("auth-host", BITFINEX_AUTH_HOST),
+    ] {
+        match http.get(format!("{host}/platform/status")).send().await {
+            Ok(resp) => {
+                server_time = server_time.or_else(|| {
+                    let date = resp.headers().get(reqwest::header::DATE)?.to_str().ok()?;
+                    DateTime::parse_from_rfc2822(date).ok()
+                });
+                checks.push(DoctorCheck::ok(
+                    check,
+                    format!("{host}: HTTP {}", resp.status()),
+                ));
+            }
+            Err(e) => {
+                // The cause tells DNS, connection and TLS failures apart
+                let mut detail = format!("{host}: {e}");
+                let mut source = std::error::Error::source(&e);
+                while let Some(cause) = source {
+                    detail = format!("{detail}: {cause}");
+                    source = cause.source();
+                }
+                checks.push(DoctorCheck::fail(
+                    check,
+                    detail,
+                    "Check DNS, proxy and firewall settings, and that TLS is not intercepted",
+                ));
+            }
+        }
+    }
+
+    match server_time {
+        Some(server_time) => {
+            let skew = (Utc::now() - server_time.with_timezone(&Utc)).num_seconds();
+            if skew.abs() > 5 {
+                checks.push(DoctorCheck::warn(
+                    "clock",
+                    format!("Local clock is {skew}s off the server"),
+                    "Enable time synchronization (NTP)",
+                ));
+            } else {
+                checks.push(DoctorCheck::ok("clock", format!("{skew}s off the server")));
+            }
+        }
+        None => checks.push(DoctorCheck::warn(
+            "clock",
+            "Server time unavailable",
+            "Fix the host checks first",
+        )),
+    }
+
+    match (api_key, api_secret) {
+        (Some(key), Some(secret)) if !key.is_empty() && !secret.is_empty() => {
+            match configure(Client::new(key, secret)).request_key_permission().await {
+                Ok(perm) => {
+                    let missing: Vec<&str> = [
+                        &perm.account,
+                        &perm.orders,
+                        &perm.funding,
+                        &perm.wallets,
+                        &perm.history,
+                        &perm.positions,
+                    ]
+                    .into_iter()
+                    .filter(|p| !p.read)
+                    .map(|p| p.name.as_str())
+                    .collect();
+                    if missing.is_empty() {
+                        checks.push(DoctorCheck::ok("api-key", "Read access granted"));
+                    } else {
+                        checks.push(DoctorCheck::warn(
+                            "api-key",
+                            format!("No read access to {}", missing.join(", ")),
+                            "Edit the key permissions on bitfinex.com",
+                        ));
+                    }
+                }
+                Err(e) => checks.push(DoctorCheck::fail(
+                    "api-key",
+                    format!("{e:?}"),
+                    "Check API_KEY and API_SECRET, and that the key is not revoked or IP restricted",
+                )),
+            }
+        }
+        _ => checks.push(DoctorCheck::warn(
+            "api-key",
+            "API_KEY or API_SECRET not set",
+            "Set them in .bfx_cli.env or the environment",
+        )),
+    }
+
+    #[cfg(feature = "ws")]
+    {
+        use crate::ws::WsClient;
+
+        let connect = tokio::time::timeout(Duration::from_secs(10), WsClient::connect()).await;
+        match connect {
+            Ok(Ok(ws)) => {
+                let _ = ws.shutdown(false, Duration::from_secs(5)).await;
+                checks.push(DoctorCheck::ok("websocket", "Connected"));
+            }
+            Ok(Err(e)) => checks.push(DoctorCheck::fail(
+                "websocket",
+                format!("{e:?}"),
+                "Check that outgoing WebSocket (wss) connections are allowed",
+            )),
+            Err(_) => checks.push(DoctorCheck::fail(
+                "websocket",
+                "Timed out",
+                "Check that outgoing WebSocket (wss) connections are allowed",
+            )),
+        }
+    }
+
+    checks
+}
+
+#[cfg(feature = "self-update")]
+async fn self_update(check: bool) -> Result<(), String> {
+    const RELEASES_URL: &str = "https://api.github.com/repos/BreezeWhite/bfx-rs/releases/latest";
+    // Hex of the raw Ed25519 public key signing the release binaries, set by
+    // .github/workflows/build.yaml. The checksums are published next to the
+    // binaries and only detect corrupted downloads, not a tampered release.
+    const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("BFX_RELEASE_PUBLIC_KEY");
+
+    let public_key = RELEASE_PUBLIC_KEY
+        .and_then(|key| hex::decode(key.trim()).ok())
+        .ok_or("This build has no release signing key, so updates cannot be verified")?;
+
+    // Release assets built by .github/workflows/build.yaml
+    let asset = match std::env::consts::OS {
+        "linux" => "bfx-linux",
+        "macos" => "bfx-macos",
+        "windows" => "bfx-windows.exe",
+        os => return Err(format!("No release binary for {os}")),
+    };
+    let version = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split('.')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    };
+
+    let http = reqwest::Client::builder()
+        .user_agent(concat!("bfx/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let release: serde_json::Value = http
+        .get(RELEASES_URL)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to get the latest release: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid release info: {e}"))?;
+    let latest = release["tag_name"]
+        .as_str()
+        .ok_or("Missing tag of the latest release")?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    if version(latest) <= version(current) {
+        println!("Already up to date: {current}");
+        return Ok(());
+    }
+    println!("New release available: {current} -> {latest}");
+    if check {
+        return Ok(());
+    }
+
+    let download_url = |name: &str| {
+        release["assets"]
+            .as_array()
+            .and_then(|assets| assets.iter().find(|a| a["name"] == name))
+            .and_then(|a| a["browser_download_url"].as_str())
+            .map(String::from)
+            .ok_or(format!("Release {latest} has no asset {name}"))
+    };
+    let download = async |name: &str| -> Result<Vec<u8>, String> {
+        let url = download_url(name)?;
+        let bytes = http
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to download {name}: {e}"))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to download {name}: {e}"))?;
+        Ok(bytes.to_vec())
+    };
+
+    // "<sha256 hex>  <file name>" as written by sha256sum
+    let checksum = download(&format!("{asset}.sha256")).await?;
+    let expected = String::from_utf8_lossy(&checksum)
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .ok_or("Empty checksum file")?;
+    let binary = download(asset).await?;
+    let actual = hex::encode(ring::digest::digest(&ring::digest::SHA256, &binary));
+    if actual != expected {
+        return Err(format!(
+            "Checksum mismatch of {asset}: expected {expected}, got {actual}"
+        ));
+    }
+    let signature = download(&format!("{asset}.sig")).await?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, &public_key)
+        .verify(&binary, &signature)
+        .map_err(|_| format!("Invalid signature of {asset}, the release is not installed"))?;
+
+    // A running binary can be renamed but not overwritten on Windows
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let new = exe.with_extension("new");
+    let old = exe.with_extension("old");
+    std::fs::write(&new, &binary).map_err(|e| format!("Failed to write {}: {e}", new.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&new, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(&exe, &old).map_err(|e| format!("Failed to replace {}: {e}", exe.display()))?;
+    if let Err(e) = std::fs::rename(&new, &exe) {
+        let _ = std::fs::rename(&old, &exe);
+        return Err(format!("Failed to replace {}: {e}", exe.display()));
+    }
+    let _ = std::fs::remove_file(&old);
+    println!("Updated to {latest}");
+    Ok(())
+}
+
+async fn process_verify_action(action: &VerifyAction) {
+    match action {
+        VerifyAction::Candles {
+            symbol,
+            timeframe,
+            last,
+        } => {
+            let time_frame = timeframe.as_str().try_into().unwrap();
+            let end = Utc::now();
+            let candles = get_client()
+                .request_trading_candles_between(symbol, time_frame, end - *last, end)
+                .await
+                .unwrap();
+            let result = report::verify_candles(&candles, time_frame);
+            pretty_print::print_candle_integrity(&result);
+            if !result.is_ok() {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Append `result` with the time and command line to the history file, if
+/// set with `--history` or BFX_HISTORY.
+fn record_result<T: serde::Serialize + ?Sized>(result: &T) {
+    let Some(path) = HISTORY_FILE
+        .get()
+        .cloned()
+        .or_else(|| std::env::var_os("BFX_HISTORY").map(PathBuf::from))
+    else {
+        return;
+    };
+    let result = match rounding().to_value(result) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error serializing to JSON: {}", e);
+            return;
+        }
+    };
+    let entry = serde_json::json!({
+        "time": Utc::now().to_rfc3339(),
+        "command": std::env::args().collect::<Vec<_>>(),
+        "result": result,
+    });
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{entry}"));
+    if let Err(e) = written {
+        eprintln!("Failed to write history to {}: {e}", path.display());
+    }
+}
+
+fn pretty_print_json<T: serde::Serialize>(data: &T) {
+    record_result(data);
+    match rounding()
+        .to_value(data)
+        .and_then(|json| serde_json::to_string_pretty(&json))
+    {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Error serializing to JSON: {}", e),
+    }
+}
+
+async fn process_analyze_action(action: &AnalyzeAction) {
+    match action {
+        AnalyzeAction::Triangle { ccys, fee } => {
+            let ccys: [&str; 3] = [&ccys[0], &ccys[1], &ccys[2]];
+            let symbols = report::triangle_symbols(ccys);
+            let symbols: Vec<&str> = symbols.iter().map(String::as_str).collect();
+            let tickers = get_client()
+                .request_trading_tickers(&symbols)
+                .await
+                .unwrap();
+            let routes = report::triangle_routes(ccys, &tickers, *fee);
+            if routes.is_empty() {
+                println!("No pairs found between {}", ccys.join(", "));
+                return;
+            }
+            pretty_print::print_triangle_routes(&routes);
+        }
+    }
+}
+
+async fn process_report_action(action: &ReportAction) {
+    match action {
+        ReportAction::OfferStats { symbol, last } => {
+            let end = Utc::now();
+            let offers = get_client_with_key()
+                .request_funding_offers_hist(symbol, Some(500), Some(end - *last), Some(end))
+                .await
+                .unwrap();
+            let stats = report::offer_stats(&offers);
+            pretty_print::print_offer_stats(&stats);
+        }
+        ReportAction::Slippage { symbol, last } => {
+            let end = Utc::now();
+            let trades = get_client_with_key()
+                .request_user_trades(
+                    symbol.clone(),
+                    Some(2500),
+                    Some(end - *last),
+                    Some(end),
+                    None,
+                )
+                .await
+                .unwrap();
+            let result = report::slippage_by_symbol(&trades);
+            pretty_print::print_slippage(&result);
+        }
+        ReportAction::Equity {
+            last,
+            quote,
+            csv,
+            chart,
+        } => {
+            let client = get_client_with_key();
+            let end = Utc::now();
+            let start = end - *last;
+            let wallets = client.request_wallets().await.unwrap();
+            let ledgers = client.request_ledger_since(start).await.unwrap();
+            // Currencies held during the period but no longer in a wallet too
+            let mut ccys: Vec<&str> = wallets
+                .iter()
+                .map(|w| w.ccy.as_str())
+                .chain(ledgers.iter().map(|l| l.ccy.as_str()))
+                .collect();
+            ccys.sort_unstable();
+            ccys.dedup();
+
+            let mut prices = std::collections::HashMap::new();
+            for ccy in ccys {
+                if ccy == quote {
+                    continue;
+                }
+                let symbol = report::pair_symbol(ccy, quote);
+                // One extra day to price the first day
+                let from = start - chrono::Duration::days(1);
+                match client
+                    .request_trading_candles(
+                        &symbol,
+                        CandleTimeFrame::Day1,
+                        Some(10000),
+                        Some(from),
+                        Some(end),
+                    )
+                    .await
+                {
+                    Ok(candles) => {
+                        prices.insert(ccy.to_string(), candles);
+                    }
+                    Err(e) => eprintln!("No prices of {symbol}, {ccy} is left out: {e:?}"),
+                }
+            }
+
+            let points = report::equity_curve(&wallets, &ledgers, &prices, quote, start, end);
+            if *chart {
+                pretty_print::print_equity_chart(&points);
+            } else {
+                pretty_print::print_equity_curve(&points);
+            }
+            if let Some(path) = csv
+                && let Err(e) = write_equity_csv(path, &points)
+            {
+                eprintln!("Failed to write {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Write the equity series as `date,equity,staking,unpriced` rows, with the unpriced
+/// currencies separated by `;`.
+fn write_equity_csv(path: &PathBuf, points: &[report::EquityPoint]) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "date,equity,staking,unpriced")?;
+    for p in points {
+        writeln!(
+            file,
+            "{},{},{},{}",
+            p.date,
+            p.equity,
+            p.staking_rewards,
+            p.unpriced.join(";")
+        )?;
+    }
+    Ok(())
+}
+
+async fn process_pay_action(action: &PayAction) {
+    let client = get_client_with_key();
+    match action {
+        PayAction::Create {
+            amount,
+            currency,
+            pay_currencies,
+            order_id,
+            duration,
+            webhook,
+            redirect_url,
+            customer_info,
+        } => {
+            let pay_currencies: Vec<&str> = pay_currencies.iter().map(String::as_str).collect();
+            let invoice = client
+                .create_pay_invoice(
+                    *amount,
+                    currency,
+                    &pay_currencies,
+                    order_id,
+                    *duration,
+                    webhook.as_deref(),
+                    redirect_url.as_deref(),
+                    customer_info.clone(),
+                )
+                .await
+                .unwrap();
+            pretty_print_json(&invoice);
+        }
+        PayAction::Invoices {
+            id,
+            limit,
+            start,
+            end,
+        } => {
+            let invoices = client
+                .request_pay_invoices(id.as_deref(), *limit, *start, *end)
+                .await
+                .unwrap();
+            pretty_print::print_pay_invoices(&invoices);
+        }
+        PayAction::Complete {
+            id,
+            pay_ccy,
+            deposit_id,
+            ledger_id,
+        } => {
+            let invoice = client
+                .complete_pay_invoice(id, pay_ccy, *deposit_id, *ledger_id)
+                .await
+                .unwrap();
+            pretty_print_json(&invoice);
+        }
+        PayAction::Expire { id } => {
+            let invoice = client.expire_pay_invoice(id).await.unwrap();
+            pretty_print_json(&invoice);
+        }
+    }
+}
+
+async fn process_pulse_action(action: &PulseAction) {
+    match action {
+        PulseAction::Hist { limit, end } => {
+            let messages = get_client().request_pulse_hist(*limit, *end).await.unwrap();
+            pretty_print::print_pulse_messages(&messages);
+        }
+        PulseAction::Profile { nickname } => {
+            let profile = get_client().request_pulse_profile(nickname).await.unwrap();
+            pretty_print_json(&profile);
+        }
+        PulseAction::Add {
+            title,
+            content,
+            parent,
+            private,
+            pin,
+        } => {
+            let message = get_client_with_key()
+                .add_pulse(title, content, parent.as_deref(), !*private, *pin)
+                .await
+                .unwrap();
+            pretty_print::print_pulse_messages(&[message]);
+        }
+        PulseAction::Del { id } => {
+            let deleted = get_client_with_key().delete_pulse(id).await.unwrap();
+            record_result(&deleted);
+            println!("Deleted: {deleted}");
+        }
+    }
+}
+
+mod pretty_print {
+    use std::collections::HashMap;
+
+    use crate::client::{
+        CurrencyInfo, FundingStats, KeyPermission, Ledger, Movement, Permission, PlatformStatus,
+        Stat, TxStatus, User, Wallet, WalletHist,
+    };
+    use crate::funding::{
+        Candle, FundingBook, FundingBookRaw, FundingCredit, FundingMarket, FundingOffer,
+        FundingTicker, FundingTrade,
+    };
+    use crate::pay::PayInvoice;
+    use crate::pulse::PulseMessage;
+    use crate::report::{
+        CandleIntegrity, CandleSeries, EquityPoint, FeeTierProgress, InterestForecast, OfferStats,
+        Slippage, TagPerformance, TriangleRoute,
+    };
+    use crate::risk::{PairExposure, PositionPnl};
+    use crate::trading::{
+        Liquidation, PairInfo, Position, TradingBook, TradingBookRaw, TradingMarket, TradingOrder,
+        TradingTicker, TradingTickerHist, TradingTrade,
+    };
+    use tabled::{
+        Table,
+        builder::Builder,
+        settings::{Format, Style, object::Segment},
+    };
+
+    /// Apply the number and date format of [`super::display_locale`].
+    fn localize(table: &mut Table) {
+        let locale = super::display_locale();
+        if locale.decimal_comma || locale.date_format.is_some() {
+            table.modify(
+                Segment::all(),
+                Format::content(move |cell| locale.format_cell(cell)),
+            );
+        }
+    }
+
+    fn build_and_print(builder: Builder) {
+        let mut table = builder.build();
+        localize(&mut table);
+        table.with(Style::rounded());
+        println!("{}", table);
+    }
+
+    pub fn print_vec_string(title: &str, vs: &Vec<String>) {
+        super::record_result(vs);
+        let mut builder = Builder::default();
+        builder.push_record([title]);
         for s in vs {
             builder.push_record([s]);
         }
         build_and_print(builder);
     }
 
+    pub fn print_deposit_methods(methods: &HashMap<String, Vec<String>>) {
+        super::record_result(methods);
+        let mut names: Vec<&String> = methods.keys().collect();
+        names.sort();
+        let mut builder = Builder::default();
+        builder.push_record(["method", "currencies"]);
+        for name in names {
+            builder.push_record([name.clone(), methods[name].join(",")]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_tx_status(status: &[TxStatus]) {
+        super::record_result(status);
+        let state = |active: bool| if active { "active" } else { "paused" };
+        let mut builder = Builder::default();
+        builder.push_record(["method", "deposit", "withdrawal"]);
+        for s in status {
+            builder.push_record([
+                s.method.as_str(),
+                state(s.deposit_active),
+                state(s.withdrawal_active),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_currency_info(info: &HashMap<String, CurrencyInfo>) {
+        super::record_result(info);
+        let mut ccys: Vec<&String> = info.keys().collect();
+        ccys.sort();
+        let mut builder = Builder::default();
+        builder.push_record(["currency", "label", "symbol", "unit", "explorer"]);
+        for ccy in ccys {
+            let c = &info[ccy];
+            builder.push_record([
+                ccy.clone(),
+                c.label.clone().unwrap_or_default(),
+                c.symbol.clone().unwrap_or_default(),
+                c.unit.clone().unwrap_or_default(),
+                c.explorer.first().cloned().unwrap_or_default(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
     pub fn print_platform_status(status: &PlatformStatus) {
+        super::record_result(status);
         let mut builder = Builder::default();
         builder.push_record(["status"]);
         builder.push_record([status.status.to_string()]);
@@ -1163,19 +3287,20 @@ mod pretty_print {
     }
 
     pub fn print_user_info(user: &User) {
+        super::record_result(user);
         let mut builder = Builder::default();
         builder.push_record(["id".to_string(), user.id.to_string()]);
         builder.push_record(["email".to_string(), user.email.clone()]);
         builder.push_record(["email-verified".to_string(), user.email_verified.to_string()]);
         builder.push_record(["name".to_string(), user.name.clone()]);
-        builder.push_record(["created".to_string(), user.created.to_rfc3339()]);
+        builder.push_record(["created".to_string(), user.created.local().to_rfc3339()]);
         builder.push_record(["verified".to_string(), user.verified.to_string()]);
         builder.push_record(["verification-level".to_string(), user.verification_level.to_string()]);
         builder.push_record(["timezone".to_string(), user.timezone.clone()]);
         builder.push_record(["locale".to_string(), user.locale.clone()]);
         builder.push_record(["company".to_string(), user.company.clone()]);
         builder.push_record(["subaccount-type", &user.subaccount_type.clone().map_or(String::new(), |v| v)]);
-        builder.push_record(["master-account-created", &user.master_account_created.map_or(String::new(), |v| v.to_string())]);
+        builder.push_record(["master-account-created", &user.master_account_created.map_or(String::new(), |v| v.local().to_rfc3339())]);
         builder.push_record(["group-id", &user.group_id.map_or(String::new(), |v| v.to_string())]);
         builder.push_record(["master-account-id", &user.master_account_id.map_or(String::new(), |v| v.to_string())]);
         builder.push_record(["inherit-master-account-verification", &user.inherit_master_account_verification.map_or(String::new(), |v| v.to_string())]);
@@ -1191,7 +3316,7 @@ mod pretty_print {
         builder.push_record(["is-securities-el-salvador", &user.is_securities_el_salvador.map_or(String::new(), |v| v.to_string())]);
         builder.push_record(["allow-disabled-ctxswitch", &user.allow_disable_ctxswitch.map_or(String::new(), |v| v.to_string())]);
         builder.push_record(["ctxswitch-disabled", &user.ctxswitch_disabled.to_string()]);
-        builder.push_record(["last-login", &user.last_login.to_rfc3339()]);
+        builder.push_record(["last-login", &user.last_login.local().to_rfc3339()]);
         builder.push_record(["verification-level-submitted", &user.verification_level_submitted.to_string()]);
         builder.push_record(["comp-countries", &serde_json::to_string_pretty(&user.comp_countries).unwrap()]);
         builder.push_record(["comp-countries-resid", &serde_json::to_string_pretty(&user.comp_countries_resid).unwrap()]);
@@ -1199,11 +3324,13 @@ mod pretty_print {
         builder.push_record(["is-merchant-enterprise", &user.is_merchant_enterprise.to_string()]);
 
         let mut table = builder.build();
+        localize(&mut table);
         table.with(Style::modern());
         println!("{table}");
     }
 
     pub fn print_key_permission(perm: &KeyPermission) {
+        super::record_result(perm);
         let mut builder = Builder::default();
         let format_p = |p: &Permission| {
             format!("Read: {} / Write: {}", p.read, p.write)
@@ -1220,41 +3347,85 @@ mod pretty_print {
         builder.push_record(["bfxpay".to_string(), format_p(&perm.bfxpay)]);
 
         let mut table = builder.build();
+        localize(&mut table);
         table.with(Style::modern());
         println!("{table}");
     }
 
     pub fn print_wallet(wallets: &Vec<Wallet>) {
+        super::record_result(wallets);
         let mut builder = Builder::default();
-        builder.push_record(["ccy", "type", "free", "balance", "unsettled"]);
+        builder.push_record(["ccy", "type", "free", "locked", "balance", "unsettled"]);
         for w in wallets {
             builder.push_record([
                 w.ccy.clone(),
                 w.typ.clone(),
                 w.free.to_string(),
+                w.locked().to_string(),
+                w.balance.to_string(),
+                w.unsettled_amount.to_string(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_wallet_hist(wallets: &Vec<WalletHist>) {
+        super::record_result(wallets);
+        let mut builder = Builder::default();
+        builder.push_record(["ccy", "type", "free", "balance", "unsettled", "updated"]);
+        for w in wallets {
+            builder.push_record([
+                w.ccy.clone(),
+                w.typ.clone(),
+                w.free.map_or(String::new(), |f| f.to_string()),
                 w.balance.to_string(),
                 w.unsettled_amount.to_string(),
+                w.time.local().to_rfc3339(),
             ]);
         }
         build_and_print(builder);
     }
 
     pub fn print_ledger(ledgers: &Vec<Ledger>) {
+        super::record_result(ledgers);
         let mut builder = Builder::default();
-        builder.push_record(["id", "amount", "balance", "ccy", "time"]);
+        builder.push_record(["id", "amount", "balance", "ccy", "description", "time"]);
         for l in ledgers {
             builder.push_record([
                 l.id.to_string(),
                 l.amount.to_string(),
                 l.balance.to_string(),
                 l.ccy.clone(),
-                l.time.to_rfc3339(),
+                l.description.clone().unwrap_or_default(),
+                l.time.local().to_rfc3339(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_movements(movements: &Vec<Movement>) {
+        super::record_result(movements);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "id", "ccy", "method", "amount", "fee", "status", "tx-id", "updated",
+        ]);
+        for m in movements {
+            builder.push_record([
+                m.id.to_string(),
+                m.ccy.clone(),
+                m.method.clone(),
+                m.amount.to_string(),
+                m.fee.to_string(),
+                m.status.clone(),
+                m.tx_id.clone().unwrap_or_default(),
+                m.updated.local().to_rfc3339(),
             ]);
         }
         build_and_print(builder);
     }
 
     pub fn print_trading_order(orders: &Vec<TradingOrder>) {
+        super::record_result(orders);
         let mut builder = Builder::default();
         builder.push_record([
             "id",
@@ -1274,14 +3445,307 @@ mod pretty_print {
                 o.amount_orig.to_string(),
                 o.order_type.to_string(),
                 o.status.clone(),
-                o.created.to_rfc3339(),
-                o.updated.to_rfc3339(),
+                o.created.local().to_rfc3339(),
+                o.updated.local().to_rfc3339(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_candle_integrity(result: &CandleIntegrity) {
+        super::record_result(result);
+        let mut builder = Builder::default();
+        builder.push_record(["candles", &result.count.to_string()]);
+        builder.push_record(["gaps", &result.gaps.len().to_string()]);
+        builder.push_record(["duplicates", &result.duplicates.len().to_string()]);
+        builder.push_record(["non-monotonic", &result.non_monotonic.len().to_string()]);
+        build_and_print(builder);
+
+        if !result.gaps.is_empty() {
+            let mut builder = Builder::default();
+            builder.push_record(["missing-from", "missing-to", "candles"]);
+            for g in &result.gaps {
+                builder.push_record([
+                    g.from.local().to_rfc3339(),
+                    g.to.local().to_rfc3339(),
+                    g.missing.to_string(),
+                ]);
+            }
+            build_and_print(builder);
+        }
+        for time in &result.duplicates {
+            println!("duplicate: {}", time.local().to_rfc3339());
+        }
+        for time in &result.non_monotonic {
+            println!("non-monotonic: {}", time.local().to_rfc3339());
+        }
+    }
+
+    pub fn print_credit_exposure(exposures: &[PairExposure]) {
+        super::record_result(exposures);
+        let mut builder = Builder::default();
+        builder.push_record(["pair", "credits", "amount", "share-%", "avg-rate"]);
+        for e in exposures {
+            builder.push_record([
+                e.pair.clone(),
+                e.credits.to_string(),
+                format!("{:.2}", e.amount),
+                format!("{:.1}", e.share),
+                format!("{:.6}", e.avg_rate),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_fee_tier(progress: &FeeTierProgress) {
+        super::record_result(progress);
+        let mut builder = Builder::default();
+        builder.push_record(["maker-fee-%", &format!("{:.3}", progress.maker_fee)]);
+        builder.push_record(["taker-fee-%", &format!("{:.3}", progress.taker_fee)]);
+        builder.push_record(["30d-volume-usd", &format!("{:.2}", progress.volume_30d_usd)]);
+        match (&progress.next_tier, progress.volume_needed_usd) {
+            (Some(tier), Some(needed)) => {
+                builder.push_record([
+                    "next-tier",
+                    &format!("{:.2}% / {:.2}%", tier.maker_fee, tier.taker_fee),
+                ]);
+                builder.push_record(["volume-needed-usd", &format!("{needed:.2}")]);
+            }
+            _ => builder.push_record(["next-tier", "top tier reached"]),
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_triangle_routes(routes: &[TriangleRoute]) {
+        super::record_result(routes);
+        let mut builder = Builder::default();
+        builder.push_record(["route", "pairs", "mid-gap-%", "net-return-%", "arbitrage"]);
+        for r in routes {
+            let pairs: Vec<&str> = r.legs.iter().map(|l| l.symbol.as_str()).collect();
+            builder.push_record([
+                r.route.clone(),
+                pairs.join(" "),
+                format!("{:.4}", r.mid_gap),
+                format!("{:.4}", r.net_return),
+                (r.net_return > 0.0).to_string(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_offer_stats(stats: &OfferStats) {
+        super::record_result(stats);
+        let fmt_rate = |rate: Option<f64>| rate.map_or("-".into(), |r| format!("{r:.8}"));
+        let mut builder = Builder::default();
+        builder.push_record(["offers", &stats.offers.to_string()]);
+        builder.push_record(["filled", &stats.filled.to_string()]);
+        builder.push_record(["partial", &stats.partial.to_string()]);
+        builder.push_record(["cancelled", &stats.cancelled.to_string()]);
+        builder.push_record(["fill-rate-%", &format!("{:.2}", stats.fill_rate() * 100.0)]);
+        builder.push_record(["offered-amount", &format!("{:.2}", stats.offered_amount)]);
+        builder.push_record(["filled-amount", &format!("{:.2}", stats.filled_amount)]);
+        builder.push_record([
+            "avg-time-to-fill",
+            &stats
+                .avg_fill_secs
+                .map_or("-".into(), |secs| format!("{:.1}m", secs / 60.0)),
+        ]);
+        builder.push_record(["filled-avg-rate", &fmt_rate(stats.filled_avg_rate)]);
+        builder.push_record(["cancelled-avg-rate", &fmt_rate(stats.cancelled_avg_rate)]);
+        build_and_print(builder);
+    }
+
+    pub fn print_interest_forecast(forecast: &[InterestForecast]) {
+        super::record_result(forecast);
+        let mut builder = Builder::default();
+        builder.push_record(["symbol", "credits", "amount", "avg-rate", "1d", "7d", "30d"]);
+        for f in forecast {
+            builder.push_record([
+                f.symbol.clone(),
+                f.credits.to_string(),
+                format!("{:.2}", f.amount),
+                format!("{:.8}", f.avg_rate),
+                format!("{:.4}", f.next_1d),
+                format!("{:.4}", f.next_7d),
+                format!("{:.4}", f.next_30d),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_pay_invoices(invoices: &[PayInvoice]) {
+        super::record_result(invoices);
+        let mut builder = Builder::default();
+        builder.push_record(["id", "order-id", "amount", "currency", "status", "created"]);
+        for i in invoices {
+            builder.push_record([
+                i.id.clone(),
+                i.order_id.clone(),
+                i.amount.to_string(),
+                i.currency.clone(),
+                i.status.clone(),
+                i.created.local().to_rfc3339(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_doctor(checks: &[super::DoctorCheck]) {
+        super::record_result(checks);
+        let mut builder = Builder::default();
+        builder.push_record(["check", "status", "detail", "fix"]);
+        for c in checks {
+            builder.push_record([
+                c.check.to_string(),
+                c.status.to_string(),
+                c.detail.clone(),
+                c.fix.clone().unwrap_or_default(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_pulse_messages(messages: &[PulseMessage]) {
+        super::record_result(messages);
+        let mut builder = Builder::default();
+        builder.push_record(["id", "created", "title", "content", "likes"]);
+        for m in messages {
+            let content: String = m.content.chars().take(80).collect();
+            builder.push_record([
+                m.id.clone(),
+                m.created.local().to_rfc3339(),
+                m.title.clone().unwrap_or_default(),
+                content,
+                m.likes.unwrap_or(0).to_string(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_equity_curve(points: &[EquityPoint]) {
+        super::record_result(points);
+        let mut builder = Builder::default();
+        builder.push_record(["date", "equity", "change-%", "staking", "unpriced"]);
+        let mut prev: Option<f64> = None;
+        for p in points {
+            let change = match prev {
+                Some(prev) if prev != 0.0 => format!("{:.2}", (p.equity - prev) / prev * 100.0),
+                _ => String::new(),
+            };
+            builder.push_record([
+                p.date.to_string(),
+                format!("{:.2}", p.equity),
+                change,
+                format!("{:.2}", p.staking_rewards),
+                p.unpriced.join(", "),
+            ]);
+            prev = Some(p.equity);
+        }
+        build_and_print(builder);
+    }
+
+    /// One bar per day, scaled between the lowest and highest equity.
+    pub fn print_equity_chart(points: &[EquityPoint]) {
+        super::record_result(points);
+        const WIDTH: f64 = 50.0;
+        let min = points.iter().map(|p| p.equity).fold(f64::INFINITY, f64::min);
+        let max = points.iter().map(|p| p.equity).fold(f64::NEG_INFINITY, f64::max);
+        let range = max - min;
+        for p in points {
+            let len = match range > 0.0 {
+                true => ((p.equity - min) / range * WIDTH).round() as usize,
+                false => WIDTH as usize,
+            };
+            println!("{} {:>14.2} {}", p.date, p.equity, "█".repeat(len.max(1)));
+        }
+    }
+
+    pub fn print_slippage(result: &[Slippage]) {
+        super::record_result(result);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "symbol",
+            "order-type",
+            "trades",
+            "volume",
+            "avg-bps",
+            "max-bps",
+        ]);
+        for r in result {
+            builder.push_record([
+                r.symbol.clone(),
+                r.order_type.clone(),
+                r.trades.to_string(),
+                format!("{:.2}", r.volume),
+                format!("{:.2}", r.avg_slippage * 10000.0),
+                format!("{:.2}", r.max_slippage * 10000.0),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_tag_performance(result: &[TagPerformance]) {
+        super::record_result(result);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "tag",
+            "symbol",
+            "trades",
+            "volume",
+            "realized-pnl",
+            "fees",
+            "net",
+        ]);
+        for r in result {
+            builder.push_record([
+                r.tag.clone(),
+                r.symbol.clone(),
+                r.trades.to_string(),
+                format!("{:.2}", r.volume),
+                format!("{:.2}", r.realized_pnl),
+                format!("{:.2}", r.fees),
+                format!("{:.2}", r.realized_pnl + r.fees),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_positions(positions: &Vec<Position>, pnls: &[PositionPnl]) {
+        super::record_result(positions);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "symbol",
+            "amount",
+            "base-price",
+            "mark-price",
+            "upnl",
+            "upnl-%",
+            "contrib-%",
+            "liq-price",
+            "leverage",
+        ]);
+        let opt = |v: Option<f64>| v.map_or(String::new(), |v| v.to_string());
+        for p in positions {
+            let pnl = pnls.iter().find(|pnl| pnl.symbol == p.symbol);
+            builder.push_record([
+                p.symbol.clone(),
+                p.amount.to_string(),
+                p.base_price.to_string(),
+                opt(pnl.map(|pnl| pnl.mark_price)),
+                pnl.map_or(String::new(), |pnl| format!("{:.2}", pnl.pnl)),
+                pnl.map_or(String::new(), |pnl| format!("{:.2}", pnl.pnl_perc)),
+                pnl.map_or(String::new(), |pnl| format!("{:.1}", pnl.contribution)),
+                opt(p.liquidation_price),
+                opt(p.leverage),
             ]);
         }
+        let total: f64 = pnls.iter().map(|pnl| pnl.pnl).sum();
+        builder.push_record(["total", "", "", "", &format!("{total:.2}"), "", "", "", ""]);
         build_and_print(builder);
     }
 
     pub fn print_trading_ticker(ticker: &TradingTicker) {
+        super::record_result(ticker);
         let mut builder = Builder::default();
         builder.push_record(["last-price", &ticker.last_price.to_string()]);
         builder.push_record(["high", &ticker.high.to_string()]);
@@ -1294,17 +3758,123 @@ mod pretty_print {
         builder.push_record(["daily-change", &ticker.daily_change.to_string()]);
         builder.push_record(["daily-change-relative", &ticker.daily_change_relative.to_string()]);
         let mut table = builder.build();
+        localize(&mut table);
         table.with(Style::modern());
         println!("{table}");
     }
 
+    pub fn print_trading_movers(tickers: &Vec<(String, TradingTicker)>) {
+        super::record_result(tickers);
+        let mut builder = Builder::default();
+        builder.push_record(["symbol", "last-price", "change-%", "volume", "quote-volume"]);
+        for (symbol, t) in tickers {
+            builder.push_record([
+                symbol.clone(),
+                t.last_price.to_string(),
+                format!("{:.2}", t.daily_change_relative * 100.0),
+                t.volume.to_string(),
+                format!("{:.0}", t.volume * t.last_price),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_trading_market(markets: &Vec<TradingMarket>) {
+        super::record_result(markets);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "symbol",
+            "bid",
+            "ask",
+            "spread-bps",
+            "bid-depth-usd",
+            "ask-depth-usd",
+        ]);
+        for m in markets {
+            builder.push_record([
+                m.symbol.clone(),
+                m.bid.to_string(),
+                m.ask.to_string(),
+                format!("{:.2}", m.spread_bps),
+                m.bid_depth_usd.map_or(String::new(), |v| format!("{v:.0}")),
+                m.ask_depth_usd.map_or(String::new(), |v| format!("{v:.0}")),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_tickers_hist(tickers: &[TradingTickerHist]) {
+        super::record_result(tickers);
+        let mut builder = Builder::default();
+        builder.push_record(["symbol", "time", "bid", "ask"]);
+        for t in tickers {
+            builder.push_record([
+                t.symbol.clone(),
+                t.time.local().to_rfc3339(),
+                t.bid.to_string(),
+                t.ask.to_string(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_pair_info(info: &[PairInfo]) {
+        super::record_result(info);
+        let opt = |v: Option<f64>| v.map_or("-".into(), |v| v.to_string());
+        let mut builder = Builder::default();
+        builder.push_record([
+            "symbol",
+            "min-size",
+            "max-size",
+            "initial-margin",
+            "maint-margin",
+        ]);
+        for p in info {
+            builder.push_record([
+                p.symbol.clone(),
+                p.min_order_size.to_string(),
+                p.max_order_size.to_string(),
+                opt(p.initial_margin),
+                opt(p.maintenance_margin),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_liquidations(liquidations: &[Liquidation]) {
+        super::record_result(liquidations);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "position",
+            "time",
+            "symbol",
+            "amount",
+            "base-price",
+            "liq-price",
+            "match",
+        ]);
+        for l in liquidations {
+            builder.push_record([
+                l.position_id.to_string(),
+                l.time.local().to_rfc3339(),
+                l.symbol.clone(),
+                l.amount.to_string(),
+                l.base_price.to_string(),
+                l.liquidation_price.to_string(),
+                (l.is_match == 1).to_string(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
     pub fn print_trading_trade(trades: &Vec<TradingTrade>) {
+        super::record_result(trades);
         let mut builder = Builder::default();
         builder.push_record(["id", "time", "amount", "price"]);
         for t in trades {
             builder.push_record([
                 t.id.to_string(),
-                t.time.to_rfc3339(),
+                t.time.local().to_rfc3339(),
                 t.amount.to_string(),
                 t.price.to_string(),
             ]);
@@ -1313,6 +3883,7 @@ mod pretty_print {
     }
 
     pub fn print_trading_book(books: &Vec<TradingBook>) {
+        super::record_result(books);
         let mut builder = Builder::default();
         builder.push_record(["price", "count", "amount"]);
         for b in books {
@@ -1326,6 +3897,7 @@ mod pretty_print {
     }
 
     pub fn print_trading_book_raw(books: &Vec<TradingBookRaw>) {
+        super::record_result(books);
         let mut builder = Builder::default();
         builder.push_record(["order-id", "price", "amount"]);
         for b in books {
@@ -1339,6 +3911,7 @@ mod pretty_print {
     }
 
     pub fn print_funding_credits(orders: &Vec<FundingCredit>) {
+        super::record_result(orders);
         let mut builder = Builder::default();
         builder.push_record(["id", "amount", "rate", "period", "pair", "created"]);
         for o in orders {
@@ -1348,13 +3921,14 @@ mod pretty_print {
                 o.rate.to_string(),
                 o.period.to_string(),
                 o.pair.clone(),
-                o.created.to_rfc3339(),
+                o.created.local().to_rfc3339(),
             ]);
         }
         build_and_print(builder);
     }
 
     pub fn print_funding_offer(orders: &Vec<FundingOffer>) {
+        super::record_result(orders);
         let mut builder = Builder::default();
         builder.push_record(["id", "amount", "rate", "period", "status", "created"]);
         for o in orders {
@@ -1364,29 +3938,58 @@ mod pretty_print {
                 o.rate.to_string(),
                 o.period.to_string(),
                 o.status.to_string(),
-                o.created.to_rfc3339(),
+                o.created.local().to_rfc3339(),
             ]);
         }
         build_and_print(builder);
     }
 
     pub fn print_candle(candles: &Vec<Candle>) {
+        super::record_result(candles);
         let mut builder = Builder::default();
-        builder.push_record(["time", "open", "close", "high", "low", "volume"]);
+        builder.push_record([
+            "time", "open", "close", "high", "low", "volume", "change-%", "range",
+        ]);
         for c in candles {
+            let change = if c.open == 0.0 {
+                String::from("-")
+            } else {
+                format!("{:.2}", (c.close - c.open) / c.open * 100.0)
+            };
             builder.push_record([
-                c.time.to_rfc3339(),
+                c.time.local().to_rfc3339(),
                 c.open.to_string(),
                 c.close.to_string(),
                 c.high.to_string(),
                 c.low.to_string(),
                 c.volume.to_string(),
+                change,
+                (c.high - c.low).to_string(),
             ]);
         }
         build_and_print(builder);
+
+        let (Some((high, low)), Some(avg_volume)) = (candles.high_low(), candles.avg_volume())
+        else {
+            return;
+        };
+        let mut builder = Builder::default();
+        builder.push_record(["candles", &candles.len().to_string()]);
+        builder.push_record([
+            "change-%",
+            &candles
+                .pct_change()
+                .map_or(String::from("-"), |p| format!("{p:.2}")),
+        ]);
+        builder.push_record(["high", &high.to_string()]);
+        builder.push_record(["low", &low.to_string()]);
+        builder.push_record(["range", &(high - low).to_string()]);
+        builder.push_record(["avg-volume", &format!("{avg_volume:.4}")]);
+        build_and_print(builder);
     }
 
     pub fn print_funding_ticker(ticker: &FundingTicker) {
+        super::record_result(ticker);
         let mut builder = Builder::default();
         builder.push_record(["frr", &ticker.frr.to_string()]);
         builder.push_record(["frr-available-amount", &ticker.frr_amount_available.to_string()]);
@@ -1403,11 +4006,13 @@ mod pretty_print {
         builder.push_record(["daily-change", &ticker.daily_change.to_string()]);
         builder.push_record(["daily-change-perc", &ticker.daily_change_perc.to_string()]);
         let mut table = builder.build();
+        localize(&mut table);
         table.with(Style::modern());
         println!("{table}");
     }
 
     pub fn print_funding_trade(trades: &Vec<FundingTrade>) {
+        super::record_result(trades);
         let mut builder = Builder::default();
         builder.push_record(["id", "amount", "rate", "period", "created"]);
         for t in trades {
@@ -1416,18 +4021,58 @@ mod pretty_print {
                 t.amount.to_string(),
                 t.rate.to_string(),
                 t.period.to_string(),
-                t.created.to_rfc3339(),
+                t.created.local().to_rfc3339(),
+            ]);
+        }
+        build_and_print(builder);
+    }
+
+    pub fn print_funding_market(markets: &Vec<FundingMarket>) {
+        super::record_result(markets);
+        let mut builder = Builder::default();
+        builder.push_record([
+            "symbol",
+            "frr",
+            "last-rate",
+            "volume",
+            "volume-usd",
+            "depth-usd",
+            "volatility",
+        ]);
+        for m in markets {
+            // Empty when not computable, "unknown" when the stats failed
+            let unknown = || {
+                if m.stats_error.is_some() {
+                    String::from("unknown")
+                } else {
+                    String::new()
+                }
+            };
+            builder.push_record([
+                m.symbol.clone(),
+                m.frr.to_string(),
+                m.last_rate.to_string(),
+                m.volume.to_string(),
+                m.volume_usd.map_or(String::new(), |v| format!("{v:.0}")),
+                m.depth_usd.map_or_else(unknown, |v| format!("{v:.0}")),
+                m.volatility.map_or_else(unknown, |v| format!("{v:.4}")),
             ]);
         }
         build_and_print(builder);
     }
 
     pub fn print_funding_book(books: &Vec<FundingBook>) {
+        super::record_result(books);
         let mut builder = Builder::default();
         builder.push_record(["rate", "amount", "period", "count"]);
         for b in books {
+            let rate = if b.is_frr {
+                String::from("FRR")
+            } else {
+                b.rate.to_string()
+            };
             builder.push_record([
-                b.rate.to_string(),
+                rate,
                 b.amount.to_string(),
                 b.period.to_string(),
                 b.count.to_string(),
@@ -1437,6 +4082,7 @@ mod pretty_print {
     }
 
     pub fn print_funding_book_raw(books: &Vec<FundingBookRaw>) {
+        super::record_result(books);
         let mut builder = Builder::default();
         builder.push_record(["id", "rate", "amount", "period"]);
         for b in books {
@@ -1451,15 +4097,17 @@ mod pretty_print {
     }
 
     pub fn print_public_stat(stat: &Vec<Stat>) {
+        super::record_result(stat);
         let mut builder = Builder::default();
         builder.push_record(["time", "value"]);
         for s in stat {
-            builder.push_record([s.time.to_rfc3339(), s.value.to_string()]);
+            builder.push_record([s.time.local().to_rfc3339(), s.value.to_string()]);
         }
         build_and_print(builder);
     }
 
     pub fn print_funding_stats(stats: &Vec<FundingStats>) {
+        super::record_result(stats);
         let mut builder = Builder::default();
         builder.push_record([
             "time",
@@ -1471,7 +4119,7 @@ mod pretty_print {
         ]);
         for s in stats {
             builder.push_record([
-                s.time.to_rfc3339(),
+                s.time.local().to_rfc3339(),
                 s.frr.to_string(),
                 s.avg_period.to_string(),
                 s.funding_amount.to_string(),
@@ -1482,3 +4130,129 @@ mod pretty_print {
         build_and_print(builder);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(filter: &str) -> Vec<(String, &'static str, String)> {
+        parse_order_filter(filter)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|c| (c.field, c.op, c.value))
+            .collect()
+    }
+
+    fn locale(value: &str) -> (bool, &'static str) {
+        let locale = DisplayLocale::parse(value).unwrap();
+        (locale.decimal_comma, locale.date_format.unwrap_or_default())
+    }
+
+    #[test]
+    fn locale_parse() {
+        assert_eq!(DisplayLocale::parse("iso"), Ok(DisplayLocale::default()));
+        assert_eq!(locale("en"), (false, "%m/%d/%Y %H:%M:%S"));
+        assert_eq!(locale("en_US.UTF-8"), (false, "%m/%d/%Y %H:%M:%S"));
+        assert_eq!(locale("en-GB"), (false, "%d/%m/%Y %H:%M:%S"));
+        assert_eq!(locale("en-ZA"), (true, "%d/%m/%Y %H:%M:%S"));
+        assert_eq!(locale("de"), (true, "%d.%m.%Y %H:%M:%S"));
+        assert_eq!(locale("DE_de"), (true, "%d.%m.%Y %H:%M:%S"));
+        assert_eq!(locale("fr-CA"), (true, "%Y-%m-%d %H:%M:%S"));
+        assert_eq!(locale("ja-JP"), (false, "%Y-%m-%d %H:%M:%S"));
+        // Regions overriding the decimal separator of the language
+        assert_eq!(locale("de-CH"), (false, "%d.%m.%Y %H:%M:%S"));
+        assert_eq!(locale("es-ES"), (true, "%d/%m/%Y %H:%M:%S"));
+        assert_eq!(locale("es-MX"), (false, "%d/%m/%Y %H:%M:%S"));
+        assert_eq!(locale("es_US"), (false, "%m/%d/%Y %H:%M:%S"));
+
+        for invalid in ["", "x", "english", "d3-DE"] {
+            assert!(
+                DisplayLocale::parse(invalid).is_err(),
+                "{invalid:?} should fail"
+            );
+        }
+    }
+
+    #[test]
+    fn locale_format_cell() {
+        let de = DisplayLocale::parse("de-DE").unwrap();
+        assert_eq!(de.format_cell("1234.5"), "1234,5");
+        assert_eq!(de.format_cell("-0.00012"), "-0,00012");
+        assert_eq!(de.format_cell("42"), "42");
+        assert_eq!(de.format_cell("tBTCUSD"), "tBTCUSD");
+        assert_eq!(de.format_cell("v1.2.3"), "v1.2.3");
+        assert_eq!(
+            de.format_cell("2024-03-05T14:07:09+00:00"),
+            "05.03.2024 14:07:09"
+        );
+
+        let us = DisplayLocale::parse("en-US").unwrap();
+        assert_eq!(us.format_cell("1234.5"), "1234.5");
+        assert_eq!(
+            us.format_cell("2024-03-05T14:07:09+00:00"),
+            "03/05/2024 14:07:09"
+        );
+
+        let iso = DisplayLocale::default();
+        assert_eq!(iso.format_cell("1234.5"), "1234.5");
+        assert_eq!(
+            iso.format_cell("2024-03-05T14:07:09+00:00"),
+            "2024-03-05T14:07:09+00:00"
+        );
+    }
+
+    #[test]
+    fn order_filter_operators() {
+        // Two-character operators win over their one-character prefix
+        assert_eq!(conditions("price<=25000")[0].1, "<=");
+        assert_eq!(conditions("price>=25000")[0].1, ">=");
+        assert_eq!(conditions("price!=25000")[0].1, "!=");
+        assert_eq!(conditions("price<25000")[0].1, "<");
+        assert_eq!(
+            conditions("amount>-1")[0],
+            (String::from("amount"), ">", String::from("-1"))
+        );
+        assert_eq!(
+            conditions("id = 42")[0],
+            (String::from("id"), "=", String::from("42"))
+        );
+    }
+
+    #[test]
+    fn order_filter_and() {
+        let expected = vec![
+            (String::from("price"), "<", String::from("25000")),
+            (String::from("symbol"), "=", String::from("tBTCUSD")),
+        ];
+        assert_eq!(conditions("price<25000 and symbol=tBTCUSD"), expected);
+        assert_eq!(conditions("price<25000 AND symbol=tBTCUSD"), expected);
+        assert_eq!(conditions("PRICE<25000  And\tsymbol=tBTCUSD"), expected);
+        assert_eq!(
+            conditions("price < 25000 and symbol = \"tBTCUSD\""),
+            expected
+        );
+        // Only whole words join conditions
+        assert_eq!(conditions("symbol=tANDUSD")[0].2, "tANDUSD");
+    }
+
+    #[test]
+    fn order_filter_invalid() {
+        for filter in [
+            "",
+            "price",
+            "price<",
+            "price<abc",
+            "symbol<tBTCUSD",
+            "side=buy",
+            "price<1 and",
+            "and price<1",
+            "price<1 and and amount>0",
+        ] {
+            assert!(
+                parse_order_filter(filter).is_err(),
+                "{filter:?} should fail"
+            );
+        }
+    }
+}
@@ -1,26 +1,34 @@
-use core::fmt;
 use std::{
+    collections::{HashMap, HashSet},
     convert::{From, Into},
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
 use hex::encode;
 use reqwest::{
     self,
     header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, USER_AGENT},
 };
-use ring::hmac;
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
 use serde::{Deserialize, Serialize};
-use serde_json::{Value, from_str, json};
+use serde_json::{Value, json};
 
 use crate::{
-    deserializer::{from_mts, int_to_bool},
+    deserializer::{int_to_bool, parse_notification, parse_one, parse_vec},
+    utils::Mts,
     error::BitfinexError,
 };
 
-static BITFINEX_PUB_HOST: &str = "https://api-pub.bitfinex.com/v2";
-static BITFINEX_AUTH_HOST: &str = "https://api.bitfinex.com/v2";
+pub(crate) static BITFINEX_PUB_HOST: &str = "https://api-pub.bitfinex.com/v2";
+pub(crate) static BITFINEX_AUTH_HOST: &str = "https://api.bitfinex.com/v2";
 
 fn parse_error(body: &str) -> Option<(String, String)> {
     // Looks for: "error",<code>,"<message>"
@@ -42,6 +50,108 @@ fn parse_error(body: &str) -> Option<(String, String)> {
     }
 }
 
+/// Random UUID v4 for the X-Request-Id header.
+fn generate_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        // Fall back to the time, still unique enough to find a request
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        bytes = nanos.to_be_bytes();
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Log a retried request as a warning, at most once per second. Retries in
+/// between are only counted, so high-frequency callers don't flood the output.
+fn log_retry(reason: &str, request_id: &str, attempt: u16, backoff: Duration) {
+    static LAST_LOG_MS: AtomicI64 = AtomicI64::new(0);
+    static SUPPRESSED: AtomicU64 = AtomicU64::new(0);
+
+    let now = Utc::now().timestamp_millis();
+    let last = LAST_LOG_MS.load(Ordering::Relaxed);
+    if now - last < 1000
+        || LAST_LOG_MS
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+    {
+        SUPPRESSED.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    let suppressed = SUPPRESSED.swap(0, Ordering::Relaxed);
+    tracing::warn!(
+        request_id,
+        attempt,
+        backoff_ms = backoff.as_millis() as u64,
+        suppressed,
+        "{reason}, retrying"
+    );
+}
+
+/// Reason of a retry in the log.
+fn retry_reason(err: &BitfinexError) -> String {
+    match err {
+        BitfinexError::NonceSmall => String::from("nonce too small"),
+        err => format!("{err:?}"),
+    }
+}
+
+/// Add the request ID to the message of generic errors.
+fn tag_error(err: BitfinexError, request_id: &str) -> BitfinexError {
+    match err {
+        BitfinexError::BitfinexGenericError(msg) => {
+            BitfinexError::BitfinexGenericError(format!("{msg} (request id: {request_id})"))
+        }
+        err => err,
+    }
+}
+
+/// Up to 3 of the `known` symbols closest to `symbol` by edit distance,
+/// ignoring case.
+fn closest_symbols<'a>(symbol: &str, known: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let target: Vec<char> = symbol.to_uppercase().chars().collect();
+    let max_distance = (target.len() / 4).max(2);
+    let mut candidates: Vec<(usize, &String)> = known
+        .into_iter()
+        .filter_map(|k| {
+            let other: Vec<char> = k.to_uppercase().chars().collect();
+            let distance = edit_distance(&target, &other);
+            (distance <= max_distance).then_some((distance, k))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(3)
+        .map(|(_, k)| k.clone())
+        .collect()
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
 // --- Data Models --- //
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Wallet {
@@ -57,13 +167,33 @@ pub struct Wallet {
     _placeholder_2: Option<String>,
 }
 
+impl Wallet {
+    /// Balance that can not be used, e.g. staked or held by open orders and offers.
+    pub fn locked(&self) -> f64 {
+        self.balance - self.free
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WalletHist {
+    pub typ: String,
+    pub ccy: String,
+    pub balance: f64,
+    pub unsettled_amount: f64,
+    pub free: Option<f64>,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub time: Mts,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Ledger {
     pub id: u64,
     pub ccy: String,
     pub wallet: String,
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
 
     #[serde(skip_serializing)]
     _placeholder_1: Option<String>,
@@ -77,14 +207,150 @@ pub struct Ledger {
     pub description: Option<String>,
 }
 
+impl Ledger {
+    /// Staking reward, identified by its description since Bitfinex
+    /// documents no ledger category for it.
+    pub fn is_staking_payment(&self) -> bool {
+        self.description
+            .as_deref()
+            .is_some_and(|d| d.to_lowercase().contains("staking"))
+    }
+}
+
+/// Deposit or withdrawal of the user.
+#[derive(Serialize, Deserialize)]
+pub struct Movement {
+    pub id: u64,
+    pub ccy: String,
+    /// Deposit method or network, e.g. BITCOIN.
+    pub method: String,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+
+    pub started: Mts,
+    pub updated: Mts,
+
+    #[serde(skip_serializing)]
+    _placeholder_3: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_4: Option<String>,
+
+    /// e.g. PROCESSING, COMPLETED, CANCELED.
+    pub status: String,
+
+    #[serde(skip_serializing)]
+    _placeholder_5: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_6: Option<String>,
+
+    /// Positive for deposits, negative for withdrawals.
+    pub amount: f64,
+    pub fee: f64,
+
+    #[serde(skip_serializing)]
+    _placeholder_7: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_8: Option<String>,
+
+    pub address: Option<String>,
+    pub payment_id: Option<String>,
+
+    #[serde(skip_serializing)]
+    _placeholder_9: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_10: Option<String>,
+
+    pub tx_id: Option<String>,
+    pub note: Option<String>,
+}
+
+/// 30-day trading volume in one currency.
+#[derive(Serialize, Debug)]
+pub struct Volume30d {
+    pub ccy: String,
+    pub vol: f64,
+    pub vol_maker: f64,
+}
+
+/// Fee tier, 30-day volume and fees of the account.
+#[derive(Serialize, Debug)]
+pub struct AccountSummary {
+    pub maker_fee: f64,
+    pub taker_fee_crypto: f64,
+    pub taker_fee_stable: f64,
+    pub taker_fee_fiat: f64,
+    /// Negative when derivatives makers receive a rebate.
+    pub deriv_maker_fee: f64,
+    pub deriv_taker_fee: f64,
+    /// Per currency, without the USD total.
+    pub volume_30d: Vec<Volume30d>,
+    pub total_volume_30d_usd: f64,
+    /// Fees paid on funding earnings in the last 30 days, in USD.
+    pub funding_fees_30d_usd: f64,
+    /// Trading fees paid in the last 30 days, in USD.
+    pub trading_fees_30d_usd: f64,
+    pub leo_level: Option<u8>,
+    pub leo_amount_avg: Option<f64>,
+}
+
+impl AccountSummary {
+    fn from_value(value: &Value) -> Self {
+        let num = |v: &Value| v.as_f64().unwrap_or_default();
+        // [4]: [[MAKER_FEE, .., .., _, _, DERIV_REBATE], [TAKER_CRYPTO, TAKER_STABLE, TAKER_FIAT, _, _, DERIV_TAKER]]
+        let maker = &value[4][0];
+        let taker = &value[4][1];
+
+        // [5]: [{"curr": "BTC", "vol": .., "vol_maker": ..}, .., {"curr": "Total (USD)", ..}]
+        let mut volume_30d = Vec::new();
+        let mut total_volume_30d_usd = 0.0;
+        for v in value[5].as_array().into_iter().flatten() {
+            let ccy = v["curr"].as_str().unwrap_or_default();
+            if ccy == "Total (USD)" {
+                total_volume_30d_usd = num(&v["vol"]);
+                continue;
+            }
+            volume_30d.push(Volume30d {
+                ccy: ccy.to_string(),
+                vol: num(&v["vol"]),
+                vol_maker: num(&v["vol_maker"]),
+            });
+        }
+
+        // LEO info is the object holding "leo_lev"
+        let leo = value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|v| v.get("leo_lev").is_some());
+
+        AccountSummary {
+            maker_fee: num(&maker[0]),
+            taker_fee_crypto: num(&taker[0]),
+            taker_fee_stable: num(&taker[1]),
+            taker_fee_fiat: num(&taker[2]),
+            deriv_maker_fee: num(&maker[5]),
+            deriv_taker_fee: num(&taker[5]),
+            volume_30d,
+            total_volume_30d_usd,
+            // [7]: FEES_FUNDING_TOTAL_30D, [9]: FEES_TRADING_TOTAL_30D
+            funding_fees_30d_usd: num(&value[7]),
+            trading_fees_30d_usd: num(&value[9]),
+            leo_level: leo.and_then(|l| l["leo_lev"].as_u64()).map(|l| l as u8),
+            leo_amount_avg: leo.and_then(|l| l["leo_amount_avg"].as_f64()),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct User {
     pub id: u32,
     pub email: String,
     pub name: String,
 
-    #[serde(deserialize_with = "from_mts")]
-    pub created: DateTime<Local>,
+    pub created: Mts,
     #[serde(deserialize_with = "int_to_bool")]
     pub verified: bool,
     pub verification_level: u8,
@@ -107,7 +373,7 @@ pub struct User {
     #[serde(skip_serializing)]
     _placeholder_3: Option<String>,
 
-    pub master_account_created: Option<u64>,
+    pub master_account_created: Option<Mts>,
     pub group_id: Option<u16>,
     pub master_account_id: Option<u32>,
 
@@ -166,7 +432,7 @@ pub struct User {
     #[serde(skip_serializing)]
     _placeholder_17: Option<String>,
 
-    pub last_login: DateTime<Local>,
+    pub last_login: Mts,
 
     #[serde(skip_serializing)]
     _placeholder_18: Option<String>,
@@ -220,8 +486,7 @@ pub struct KeyPermission {
 
 #[derive(Serialize, Deserialize)]
 pub struct Stat {
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
     pub value: f64,
 }
 
@@ -240,10 +505,24 @@ pub struct DepositAddress {
     pub pool_address: Option<String>,
 }
 
+/// Lightning Network deposit invoice.
+#[derive(Serialize, Deserialize)]
+pub struct LightningInvoice {
+    pub invoice_hash: String,
+    /// BOLT11 payment request to pay from a Lightning wallet.
+    pub invoice: String,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+
+    pub amount: String,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DepositAddressResult {
-    #[serde(deserialize_with = "from_mts")]
-    pub created: DateTime<Local>,
+    pub created: Mts,
     pub noti_type: String,
     pub message_id: Option<String>,
 
@@ -265,8 +544,7 @@ pub struct PlatformStatus {
 
 #[derive(Serialize, Deserialize)]
 pub struct FundingStats {
-    #[serde[deserialize_with = "from_mts"]]
-    pub time: DateTime<Local>,
+    pub time: Mts,
 
     #[serde(skip_serializing)]
     _placeholder_1: Option<String>,
@@ -295,8 +573,7 @@ pub struct FundingStats {
 #[derive(Serialize, Deserialize)]
 pub struct DerivativesStatus {
     pub key: String,
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
 
     #[serde(skip_serializing)]
     _placeholder_1: Option<String>,
@@ -312,8 +589,7 @@ pub struct DerivativesStatus {
     #[serde(skip_serializing)]
     _placeholder_3: Option<String>,
 
-    #[serde(deserialize_with = "from_mts")]
-    pub next_funding_evt_time: DateTime<Local>,
+    pub next_funding_evt_time: Mts,
     pub next_funding_accrued: f64,
     pub next_funding_step: u64,
     
@@ -347,6 +623,66 @@ pub struct DerivativesStatus {
     pub clamp_max: f64,
 }
 
+/// Display details of a currency, see [`Client::request_currency_info`].
+#[derive(Serialize, Default, Debug)]
+pub struct CurrencyInfo {
+    /// Full name, e.g. `Bitcoin` for BTC.
+    pub label: Option<String>,
+    /// Symbol shown instead of the code, e.g. `USDt` for UST.
+    pub symbol: Option<String>,
+    /// Unit of the amounts, e.g. `GRAM` for XAUT.
+    pub unit: Option<String>,
+    /// Block explorer home page, followed by the address and transaction URL
+    /// templates, where `VAL` stands for the address or transaction ID.
+    pub explorer: Vec<String>,
+}
+
+impl CurrencyInfo {
+    /// Explorer URL of a transaction, if the currency has an explorer.
+    pub fn tx_url(&self, tx_id: &str) -> Option<String> {
+        self.explorer.get(2).map(|url| url.replace("VAL", tx_id))
+    }
+
+    /// Explorer URL of an address, if the currency has an explorer.
+    pub fn address_url(&self, address: &str) -> Option<String> {
+        self.explorer.get(1).map(|url| url.replace("VAL", address))
+    }
+}
+
+/// Whether deposits and withdrawals of a method are open, see
+/// [`Client::request_tx_status`].
+#[derive(Serialize)]
+pub struct TxStatus {
+    /// Deposit method, e.g. `BITCOIN` or `TETHERUSE`.
+    pub method: String,
+    pub deposit_active: bool,
+    pub withdrawal_active: bool,
+}
+
+impl TxStatus {
+    /// Deposits or withdrawals are in maintenance.
+    pub fn is_paused(&self) -> bool {
+        !self.deposit_active || !self.withdrawal_active
+    }
+}
+
+impl<'de> Deserialize<'de> for TxStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // [METHOD, DEPOSIT_STATUS, WITHDRAWAL_STATUS, ...], 1 when active
+        let row: Vec<Value> = Deserialize::deserialize(deserializer)?;
+        let method = row
+            .first()
+            .and_then(Value::as_str)
+            .ok_or_else(|| serde::de::Error::custom("missing method of tx status"))?;
+        let active = |i: usize| row.get(i).and_then(Value::as_i64) == Some(1);
+        Ok(TxStatus {
+            method: method.to_string(),
+            deposit_active: active(1),
+            withdrawal_active: active(2),
+        })
+    }
+}
+
 // --- Enums --- //
 pub enum LedgerType {
     Exchange = 5,
@@ -448,88 +784,583 @@ impl WalletType {
     }
 }
 
-pub enum DepositMethod {
-    Bitcoin,
-    Litecoin,
-    Ethereum,
-    Tetheruso,
-    Tetherusl,
-    Tetherusx,
-    Tetheruss,
-    Ethereumc,
-    Zcash,
-    Monero,
-    Iota,
+/// Kind of order or offer for [`Client::calc_available_balance`].
+pub enum AvailBalanceType {
+    Exchange,
+    Margin,
+    Deriv,
+    Funding,
 }
 
-impl From<&str> for DepositMethod {
+impl From<&str> for AvailBalanceType {
     fn from(value: &str) -> Self {
-        match value {
-            "bitcoin" => DepositMethod::Bitcoin,
-            "litecoin" => DepositMethod::Litecoin,
-            "ethereum" => DepositMethod::Ethereum,
-            "tetheruso" => DepositMethod::Tetheruso,
-            "tetherusl" => DepositMethod::Tetherusl,
-            "tetherusx" => DepositMethod::Tetherusx,
-            "tetheruss" => DepositMethod::Tetheruss,
-            "ethereumc" => DepositMethod::Ethereumc,
-            "zcash" => DepositMethod::Zcash,
-            "monero" => DepositMethod::Monero,
-            "iota" => DepositMethod::Iota,
-            _ => panic!("Unknown deposit method: {}", value),
-        }
-    }
-}
-
-impl fmt::Display for DepositMethod {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let method_str = match self {
-            DepositMethod::Bitcoin => "bitcoin",
-            DepositMethod::Litecoin => "litecoin",
-            DepositMethod::Ethereum => "ethereum",
-            DepositMethod::Tetheruso => "tetheruso",
-            DepositMethod::Tetherusl => "tetherusl",
-            DepositMethod::Tetherusx => "tetherusx",
-            DepositMethod::Tetheruss => "tetheruss",
-            DepositMethod::Ethereumc => "ethereumc",
-            DepositMethod::Zcash => "zcash",
-            DepositMethod::Monero => "monero",
-            DepositMethod::Iota => "iota",
+        match value.to_uppercase().as_str() {
+            "EXCHANGE" => AvailBalanceType::Exchange,
+            "MARGIN" => AvailBalanceType::Margin,
+            "DERIV" => AvailBalanceType::Deriv,
+            "FUNDING" => AvailBalanceType::Funding,
+            _ => AvailBalanceType::Exchange,
+        }
+    }
+}
+
+impl AvailBalanceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AvailBalanceType::Exchange => "EXCHANGE",
+            AvailBalanceType::Margin => "MARGIN",
+            AvailBalanceType::Deriv => "DERIV",
+            AvailBalanceType::Funding => "FUNDING",
+        }
+    }
+}
+
+/// Cumulative counters of the requests of a [`Client`], see [`Client::stats`].
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ClientStats {
+    /// Requests sent per endpoint path, retries included.
+    pub requests: HashMap<String, u64>,
+    /// Failed requests per Bitfinex error code, or `transport` when no
+    /// response was received.
+    pub errors: HashMap<String, u64>,
+    pub retries: u64,
+    /// Bytes of request payloads.
+    pub bytes_sent: u64,
+    /// Bytes of response bodies.
+    pub bytes_received: u64,
+    pub responses: u64,
+    /// Time spent waiting for all responses.
+    pub total_latency: Duration,
+}
+
+impl ClientStats {
+    /// Average time to get a response, zero before the first one.
+    pub fn average_latency(&self) -> Duration {
+        if self.responses == 0 {
+            return Duration::ZERO;
+        }
+        self.total_latency / self.responses as u32
+    }
+}
+
+/// Decides whether and when a failed request is sent again, see
+/// [`Client::with_retry_policy`].
+pub trait RetryPolicy: Send + Sync {
+    /// Retries after the first attempt.
+    fn max_retries(&self) -> u16;
+
+    /// Wait before the given retry, starting at 1.
+    fn backoff(&self, retry: u16) -> Duration;
+
+    /// Whether to retry a request to `endpoint` (e.g. `auth/w/order/submit`)
+    /// after `error`, or after no response was received if `None`.
+    ///
+    /// Retries "Nonce: small" errors, and transport failures except of writes
+    /// (`auth/w/*`) by default, since a write may have been executed without
+    /// its response arriving.
+    fn is_retryable(&self, endpoint: &str, error: Option<&BitfinexError>) -> bool {
+        match error {
+            None => !endpoint.starts_with("auth/w/"),
+            Some(err) => matches!(err, BitfinexError::NonceSmall),
+        }
+    }
+}
+
+/// Backoff growing by `multiplier` per retry up to `max_backoff`, shortened
+/// by a random fraction of up to `jitter` (0-1) so that many clients do not
+/// retry in lockstep.
+///
+/// The default retries 5 times, starting 1 second apart and doubling up to
+/// a minute, each wait shortened by up to half.
+#[derive(Clone, Debug)]
+pub struct ExponentialBackoff {
+    pub max_retries: u16,
+    pub initial: Duration,
+    pub multiplier: f64,
+    pub max_backoff: Duration,
+    pub jitter: f64,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        ExponentialBackoff {
+            max_retries: 5,
+            initial: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_backoff: Duration::from_secs(60),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn max_retries(&self) -> u16 {
+        self.max_retries
+    }
+
+    fn backoff(&self, retry: u16) -> Duration {
+        let exp = i32::from(retry.saturating_sub(1));
+        let secs = self.initial.as_secs_f64() * self.multiplier.max(1.0).powi(exp);
+        let backoff = secs.min(self.max_backoff.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0) * random_fraction();
+        Duration::from_secs_f64(backoff * (1.0 - jitter))
+    }
+}
+
+/// Uniform random number in [0, 1).
+fn random_fraction() -> f64 {
+    let mut bytes = [0u8; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return 0.0;
+    }
+    (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Fast-fail of endpoint classes after consecutive failures, see
+/// [`Client::with_circuit_breaker`].
+struct CircuitBreaker {
+    max_failures: u32,
+    cool_down: Duration,
+    /// Consecutive failures and end of the cool-down, per endpoint class.
+    states: Mutex<HashMap<String, (u32, Option<Instant>)>>,
+}
+
+impl CircuitBreaker {
+    fn check(&self, class: &str) -> Result<(), BitfinexError> {
+        let states = self.states.lock().unwrap();
+        if let Some((_, Some(open_until))) = states.get(class) {
+            let now = Instant::now();
+            if now < *open_until {
+                return Err(BitfinexError::CircuitOpen {
+                    endpoint: class.to_string(),
+                    retry_in: *open_until - now,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&self, class: &str, result: &Result<String, BitfinexError>) {
+        let mut states = self.states.lock().unwrap();
+        let failed = match result {
+            Ok(_) => false,
+            Err(BitfinexError::RateLimited | BitfinexError::BitfinexTempUnavailable) => true,
+            Err(BitfinexError::BitfinexGenericError(msg)) => {
+                msg.starts_with("Exceed max retry count")
+            }
+            Err(_) => false,
         };
-        write!(f, "{}", method_str)
+        if !failed {
+            states.remove(class);
+            return;
+        }
+        let state = states.entry(class.to_string()).or_insert((0, None));
+        state.0 += 1;
+        // Once open, a single failure after the cool-down opens it again.
+        if state.0 >= self.max_failures {
+            state.1 = Some(Instant::now() + self.cool_down);
+            tracing::warn!(endpoint = class, failures = state.0, "circuit breaker open");
+        }
     }
 }
 
+/// Class of an endpoint for the circuit breaker: the first segment of public
+/// endpoints (e.g. `candles`), and the first three of authenticated ones
+/// (e.g. `auth/w/order`).
+fn endpoint_class(url: &str) -> String {
+    let path = url.split('?').next().unwrap_or(url);
+    let segments = if path.starts_with("auth/") { 3 } else { 1 };
+    path.split('/').take(segments).collect::<Vec<_>>().join("/")
+}
+
 // --- Bitfinex Client --- //
 pub struct Client {
     api_key: String,
     api_secret: String,
+    balance_check: Option<f64>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pub_host: String,
+    auth_host: String,
+    retry_policy: Arc<dyn RetryPolicy>,
+    user_agent: String,
+    request_id: Option<String>,
+    symbol_validation: bool,
+    // Shared with the clients of `with_credentials`
+    /// Last nonce sent, so that concurrent requests get increasing nonces.
+    last_nonce: Arc<AtomicU64>,
+    /// Built on first use so that connections are pooled across requests.
+    http: Arc<OnceLock<reqwest::Client>>,
+    known_symbols: Arc<OnceLock<HashSet<String>>>,
+    deposit_methods: Arc<OnceLock<HashMap<String, Vec<String>>>>,
+    stats: Arc<Mutex<ClientStats>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+}
+
+/// Builder of a [`Client`], see [`Client::builder`].
+#[derive(Default)]
+pub struct ClientBuilder {
+    api_key: String,
+    api_secret: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pub_host: Option<String>,
+    auth_host: Option<String>,
+    user_agent: Option<String>,
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
+}
+
+impl ClientBuilder {
+    /// API key and secret of authenticated requests, not needed for public
+    /// ones.
+    pub fn credentials(
+        mut self,
+        api_key: impl Into<String>,
+        api_secret: impl Into<String>,
+    ) -> Self {
+        self.api_key = api_key.into();
+        self.api_secret = api_secret.into();
+        self
+    }
+
+    /// See [`Client::with_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [`Client::with_connect_timeout`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Base URL of public requests, see [`Client::with_hosts`].
+    pub fn pub_host(mut self, host: impl Into<String>) -> Self {
+        self.pub_host = Some(host.into());
+        self
+    }
+
+    /// Base URL of authenticated requests, see [`Client::with_hosts`].
+    pub fn auth_host(mut self, host: impl Into<String>) -> Self {
+        self.auth_host = Some(host.into());
+        self
+    }
+
+    /// See [`Client::with_user_agent`].
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// See [`Client::with_retry_policy`].
+    pub fn retry_policy(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry_policy = Some(Arc::new(policy));
+        self
+    }
+
+    pub fn build(self) -> Client {
+        let mut client = Client::new(self.api_key, self.api_secret);
+        if let Some(timeout) = self.timeout {
+            client = client.with_timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            client = client.with_connect_timeout(timeout);
+        }
+        let pub_host = self.pub_host.unwrap_or(client.pub_host.clone());
+        let auth_host = self.auth_host.unwrap_or(client.auth_host.clone());
+        client = client.with_hosts(pub_host, auth_host);
+        if let Some(user_agent) = self.user_agent {
+            client = client.with_user_agent(user_agent);
+        }
+        if let Some(policy) = self.retry_policy {
+            client.retry_policy = policy;
+        }
+        client
+    }
 }
 
 impl Client {
+    /// Configure a client step by step, e.g.
+    /// `Client::builder().credentials(key, secret).timeout(Duration::from_secs(10)).build()`.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
     pub fn new(api_key: String, api_secret: String) -> Self {
         Client {
             api_key,
             api_secret,
+            balance_check: None,
+            timeout: None,
+            connect_timeout: None,
+            pub_host: String::from(BITFINEX_PUB_HOST),
+            auth_host: String::from(BITFINEX_AUTH_HOST),
+            retry_policy: Arc::new(ExponentialBackoff::default()),
+            user_agent: String::from("bitfinex-api-rs"),
+            request_id: None,
+            symbol_validation: false,
+            last_nonce: Arc::new(AtomicU64::new(0)),
+            http: Arc::new(OnceLock::new()),
+            known_symbols: Arc::new(OnceLock::new()),
+            deposit_methods: Arc::new(OnceLock::new()),
+            stats: Arc::new(Mutex::new(ClientStats::default())),
+            circuit_breaker: None,
+        }
+    }
+
+    /// Client for another API key, e.g. of an end user of a broker, sharing
+    /// the settings, connection pool, symbol and deposit method caches, stats
+    /// and circuit breaker of this one.
+    pub fn with_credentials(&self, api_key: String, api_secret: String) -> Client {
+        Client {
+            api_key,
+            api_secret,
+            balance_check: self.balance_check,
+            timeout: self.timeout,
+            connect_timeout: self.connect_timeout,
+            pub_host: self.pub_host.clone(),
+            auth_host: self.auth_host.clone(),
+            retry_policy: Arc::clone(&self.retry_policy),
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+            symbol_validation: self.symbol_validation,
+            last_nonce: Arc::clone(&self.last_nonce),
+            http: Arc::clone(&self.http),
+            known_symbols: Arc::clone(&self.known_symbols),
+            deposit_methods: Arc::clone(&self.deposit_methods),
+            stats: Arc::clone(&self.stats),
+            circuit_breaker: self.circuit_breaker.clone(),
+        }
+    }
+
+    /// User-Agent header of all requests. `bitfinex-api-rs` by default.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Fixed `X-Request-Id` header for all requests, e.g. to tag the requests
+    /// of one job. A random UUID per request by default.
+    ///
+    /// The ID is printed with failed requests and included in
+    /// [`BitfinexError::BitfinexGenericError`], to correlate them with
+    /// Bitfinex support tickets.
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Timeout of each HTTP request. No timeout by default.
+    ///
+    /// Timed out `auth/w/*` requests are not retried and fail with
+    /// [`BitfinexError::Timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        // Rebuild the pool with the new timeout
+        self.http = Arc::new(OnceLock::new());
+        self
+    }
+
+    /// Timeout of establishing the connection of each HTTP request. No
+    /// timeout by default.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        // Rebuild the pool with the new timeout
+        self.http = Arc::new(OnceLock::new());
+        self
+    }
+
+    /// Base URLs of public and authenticated requests, e.g. of a proxy.
+    /// `https://api-pub.bitfinex.com/v2` and `https://api.bitfinex.com/v2` by
+    /// default.
+    pub fn with_hosts(mut self, pub_host: impl Into<String>, auth_host: impl Into<String>) -> Self {
+        self.pub_host = pub_host.into().trim_end_matches('/').to_string();
+        self.auth_host = auth_host.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Retries of a failed request with the default [`ExponentialBackoff`].
+    /// 5 by default. Replaces the policy of [`Client::with_retry_policy`].
+    pub fn with_retries(mut self, max_retries: u8) -> Self {
+        self.retry_policy = Arc::new(ExponentialBackoff {
+            max_retries: u16::from(max_retries),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// How failed requests are retried, e.g. an [`ExponentialBackoff`] with
+    /// jitter, or a custom [`RetryPolicy`].
+    pub fn with_retry_policy(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry_policy = Arc::new(policy);
+        self
+    }
+
+    /// Check the wallet balance before submitting orders and offers, and
+    /// return [`BitfinexError::InsufficientBalance`] with the shortfall
+    /// without sending the request.
+    ///
+    /// ## Parameters:
+    /// - `fee_headroom`: extra fraction of the notional kept for fees (e.g. 0.002).
+    pub fn with_balance_check(mut self, fee_headroom: f64) -> Self {
+        self.balance_check = Some(fee_headroom);
+        self
+    }
+
+    /// Validate symbols against the pair and currency lists of Bitfinex,
+    /// fetched once on first use, and return [`BitfinexError::UnknownSymbol`]
+    /// with close matches without sending the request.
+    pub fn with_symbol_validation(mut self) -> Self {
+        self.symbol_validation = true;
+        self
+    }
+
+    /// Fail fast with [`BitfinexError::CircuitOpen`] for `cool_down` once
+    /// `max_failures` requests in a row to the same class of endpoints (e.g.
+    /// `candles` or `auth/w/order`) failed, without sending them. Failures are
+    /// rate limits, temporary unavailability and exhausted retries. Disabled
+    /// by default.
+    pub fn with_circuit_breaker(mut self, max_failures: u32, cool_down: Duration) -> Self {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker {
+            max_failures: max_failures.max(1),
+            cool_down,
+            states: Mutex::new(HashMap::new()),
+        }));
+        self
+    }
+
+    /// Snapshot of the request counters since the client was created.
+    pub fn stats(&self) -> ClientStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut ClientStats)) {
+        update(&mut self.stats.lock().unwrap());
+    }
+
+    /// Fee headroom of the balance pre-check, if enabled.
+    pub(crate) fn balance_check(&self) -> Option<f64> {
+        self.balance_check
+    }
+
+    /// Fail when the available balance of `ccy` in the `wallet` (exchange,
+    /// margin or funding) is below `required`.
+    pub(crate) async fn ensure_wallet_balance(
+        &self,
+        wallet: &str,
+        ccy: &str,
+        required: f64,
+    ) -> Result<(), BitfinexError> {
+        let available: f64 = self
+            .request_wallets()
+            .await?
+            .iter()
+            .filter(|w| w.typ == wallet && w.ccy == ccy)
+            .map(|w| w.free)
+            .sum();
+        if available < required {
+            return Err(BitfinexError::InsufficientBalance {
+                message: format!(
+                    "not enough {wallet} balance: {available} {ccy} available, {required} {ccy} required"
+                ),
+                shortfall: Some(required - available),
+            });
+        }
+        Ok(())
+    }
+
+    /// Fail when symbol validation is enabled and `symbol` is not listed.
+    pub(crate) async fn check_symbol(&self, symbol: &str) -> Result<(), BitfinexError> {
+        if !self.symbol_validation {
+            return Ok(());
+        }
+        let known = match self.known_symbols.get() {
+            Some(known) => known,
+            None => {
+                let known = self.request_known_symbols().await?;
+                self.known_symbols.get_or_init(|| known)
+            }
+        };
+        if known.contains(symbol) {
+            return Ok(());
         }
+        Err(BitfinexError::UnknownSymbol {
+            symbol: symbol.to_string(),
+            suggestions: closest_symbols(symbol, known),
+        })
+    }
+
+    /// All trading symbols (exchange, margin and derivatives pairs) and
+    /// funding symbols.
+    async fn request_known_symbols(&self) -> Result<HashSet<String>, BitfinexError> {
+        let url = String::from(
+            "conf/pub:list:pair:exchange,pub:list:pair:margin,pub:list:pair:futures,pub:list:currency",
+        );
+        let body = self.get(&url).await?;
+        let lists: Vec<Vec<String>> = parse_one(&body)?;
+        let mut known = HashSet::new();
+        for (prefix, list) in ["t", "t", "t", "f"].iter().zip(lists) {
+            known.extend(list.into_iter().map(|s| format!("{prefix}{s}")));
+        }
+        Ok(known)
     }
 
     // Inner utility functions
+    fn http_client(&self) -> &reqwest::Client {
+        self.http.get_or_init(|| {
+            let mut builder = reqwest::Client::builder();
+            if let Some(timeout) = self.timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(timeout) = self.connect_timeout {
+                builder = builder.connect_timeout(timeout);
+            }
+            builder.build().expect("Failed to build HTTP client")
+        })
+    }
+
     fn sign_payload(&self, secret: &[u8], payload: &[u8]) -> String {
         let signed_key = hmac::Key::new(hmac::HMAC_SHA384, secret);
-        let signature = encode(hmac::sign(&signed_key, payload).as_ref());
-
-        signature
+        encode(hmac::sign(&signed_key, payload).as_ref())
     }
 
+    /// Current time in microseconds, or one more than the last nonce if
+    /// that is not later, so nonces strictly increase across threads.
     fn generate_nonce(&self) -> String {
-        let start = SystemTime::now();
-        let since_epoch = start.duration_since(UNIX_EPOCH).unwrap();
-        let timestamp = since_epoch.as_secs() * 1_000_000;
-        timestamp.to_string()
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        let last = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+        now.max(last + 1).to_string()
+    }
+
+    fn next_request_id(&self) -> String {
+        match &self.request_id {
+            Some(id) => id.clone(),
+            None => generate_uuid(),
+        }
     }
 
-    fn build_headers(&self, url: &String, payload: Option<String>) -> HeaderMap {
+    /// Headers sent with both public and authenticated requests.
+    fn base_headers(&self, request_id: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let user_agent = HeaderValue::from_str(&self.user_agent)
+            .unwrap_or_else(|_| HeaderValue::from_static("bitfinex-api-rs"));
+        headers.insert(USER_AGENT, user_agent);
+        if let Ok(id) = HeaderValue::from_str(request_id) {
+            headers.insert(HeaderName::from_static("x-request-id"), id);
+        }
+        headers
+    }
+
+    fn build_headers(&self, url: &str, payload: Option<String>, request_id: &str) -> HeaderMap {
         let nonce = self.generate_nonce();
         let payload = match payload {
             Some(p) => p,
@@ -539,8 +1370,7 @@ impl Client {
 
         let signature = self.sign_payload(self.api_secret.as_bytes(), signature_path.as_bytes());
 
-        let mut headers = HeaderMap::new();
-        headers.insert(USER_AGENT, HeaderValue::from_static("bitfinex-api-rs"));
+        let mut headers = self.base_headers(request_id);
         headers.insert(
             HeaderName::from_static("bfx-nonce"),
             HeaderValue::from_str(nonce.as_str()).unwrap(),
@@ -558,8 +1388,9 @@ impl Client {
         headers
     }
 
-    fn handle_error(&self, body: &String) -> Result<(), BitfinexError> {
+    fn handle_error(&self, body: &str) -> Result<(), BitfinexError> {
         if let Some((err_code, err_msg)) = parse_error(body) {
+            self.update_stats(|s| *s.errors.entry(err_code.clone()).or_default() += 1);
             match err_code.as_str() {
                 "10001" => {
                     // Generic error code
@@ -568,7 +1399,7 @@ impl Client {
                     }
                     // "error",10001,"Invalid offer: incorrect amount, minimum is 150.0 dollar or equivalent in UST"
                     // "error",10001,"FRR offset larger than 30% of FRR, aborting."
-                    return Err(BitfinexError::BitfinexGenericError(err_msg));
+                    return Err(BitfinexError::from_message(err_msg));
                 }
                 "10020" => {
                     // "error",10020,"currency: invalid"
@@ -600,83 +1431,177 @@ impl Client {
         Ok(())
     }
 
+    fn record_attempt(&self, url: &str, sent: usize, attempt: u16) {
+        let path = url.split('?').next().unwrap_or(url);
+        self.update_stats(|s| {
+            *s.requests.entry(path.to_string()).or_default() += 1;
+            s.bytes_sent += sent as u64;
+            if attempt > 1 {
+                s.retries += 1;
+            }
+        });
+    }
+
+    fn record_response(&self, received: usize, latency: Duration) {
+        self.update_stats(|s| {
+            s.bytes_received += received as u64;
+            s.responses += 1;
+            s.total_latency += latency;
+        });
+    }
+
+    /// Send `request` unless the circuit of its endpoint class is open.
+    async fn with_circuit(
+        &self,
+        url: &str,
+        request: impl Future<Output = Result<String, BitfinexError>>,
+    ) -> Result<String, BitfinexError> {
+        let Some(breaker) = &self.circuit_breaker else {
+            return request.await;
+        };
+        let class = endpoint_class(url);
+        breaker.check(&class)?;
+        let result = request.await;
+        breaker.record(&class, &result);
+        result
+    }
+
     // General public functions
     pub async fn get(&self, url: &String) -> Result<String, BitfinexError> {
-        let endpoint = format!("{BITFINEX_PUB_HOST}/{url}");
-
-        let retry_cnt: u8 = 5;
-        let retry_interval = 1;
-        for _ in 0..=retry_cnt {
-            let response = reqwest::get(&endpoint).await;
-            if let Ok(resp) = response {
-                let body = resp.text().await.unwrap();
+        self.with_circuit(url, self.send_get(url)).await
+    }
+
+    pub async fn post(
+        &self,
+        url: &String,
+        payload: Option<String>,
+        params: Option<Vec<(&str, String)>>,
+    ) -> Result<String, BitfinexError> {
+        self.with_circuit(url, self.send_post(url, payload, params))
+            .await
+    }
+
+    async fn send_get(&self, url: &String) -> Result<String, BitfinexError> {
+        let endpoint = format!("{}/{url}", self.pub_host);
+
+        let client = self.http_client();
+        let request_id = self.next_request_id();
+        let policy = &self.retry_policy;
+        for attempt in 1..=policy.max_retries().saturating_add(1) {
+            self.record_attempt(url, 0, attempt);
+            let started = Instant::now();
+            let response = match client
+                .get(&endpoint)
+                .headers(self.base_headers(&request_id))
+                .send()
+                .await
+            {
+                Ok(resp) => resp.text().await,
+                Err(e) => Err(e),
+            };
+            if let Ok(body) = response {
+                self.record_response(body.len(), started.elapsed());
                 match self.handle_error(&body) {
-                    Err(BitfinexError::NonceSmall) => {
-                        println!("Catched NonceSmall error. Retrying..");
-                        tokio::time::sleep(Duration::from_secs(retry_interval)).await;
+                    Err(err) if policy.is_retryable(url, Some(&err)) => {
+                        if attempt > policy.max_retries() {
+                            break;
+                        }
+                        let backoff = policy.backoff(attempt);
+                        log_retry(&retry_reason(&err), &request_id, attempt, backoff);
+                        tokio::time::sleep(backoff).await;
                         continue;
                     }
                     Err(err) => {
-                        eprintln!("Error occured: {err:#?}");
-                        return Err(err);
+                        tracing::error!(request_id, error = ?err, "request failed");
+                        return Err(tag_error(err, &request_id));
                     }
                     Ok(_) => return Ok(body),
                 }
             } else {
-                println!("Bad response: {response:#?}");
-                tokio::time::sleep(Duration::from_secs(retry_interval)).await;
+                self.update_stats(|s| *s.errors.entry(String::from("transport")).or_default() += 1);
+                if attempt > policy.max_retries() || !policy.is_retryable(url, None) {
+                    break;
+                }
+                let backoff = policy.backoff(attempt);
+                log_retry("bad response", &request_id, attempt, backoff);
+                tokio::time::sleep(backoff).await;
             }
         }
-        Err(BitfinexError::BitfinexGenericError(
-            "Exceed max retry count".into(),
-        ))
+        Err(BitfinexError::BitfinexGenericError(format!(
+            "Exceed max retry count (request id: {request_id})"
+        )))
     }
 
-    pub async fn post(
+    async fn send_post(
         &self,
         url: &String,
         payload: Option<String>,
         params: Option<Vec<(&str, String)>>,
     ) -> Result<String, BitfinexError> {
-        let endpoint = format!("{BITFINEX_AUTH_HOST}/{url}");
-
-        let client = reqwest::Client::new();
-        let retry_cnt: u8 = 5;
-        let retry_interval = 1;
-        for _ in 0..=retry_cnt {
-            let mut builder = client
-                .post(&endpoint)
-                .headers(self.build_headers(url, payload.clone()));
+        let endpoint = format!("{}/{url}", self.auth_host);
+
+        let client = self.http_client();
+        let request_id = self.next_request_id();
+        let policy = &self.retry_policy;
+        for attempt in 1..=policy.max_retries().saturating_add(1) {
+            let sent = payload.as_ref().map_or(0, String::len);
+            self.record_attempt(url, sent, attempt);
+            let started = Instant::now();
+            let mut builder = client.post(&endpoint).headers(self.build_headers(
+                url,
+                payload.clone(),
+                &request_id,
+            ));
             if let Some(ref payload) = payload {
                 builder = builder.body(payload.clone());
             }
             if let Some(ref params) = params {
                 builder = builder.query(params);
             }
-            let response = builder.send().await;
+            let response = match builder.send().await {
+                Ok(resp) => resp.text().await,
+                Err(e) => Err(e),
+            };
 
-            if let Ok(resp) = response {
-                let body: String = resp.text().await.unwrap();
+            if let Ok(body) = response {
+                self.record_response(body.len(), started.elapsed());
                 match self.handle_error(&body) {
-                    Err(BitfinexError::NonceSmall) => {
-                        println!("Catched NonceSmall error. Retrying..");
-                        tokio::time::sleep(Duration::from_secs(retry_interval)).await;
+                    Err(err) if policy.is_retryable(url, Some(&err)) => {
+                        if attempt > policy.max_retries() {
+                            break;
+                        }
+                        let backoff = policy.backoff(attempt);
+                        log_retry(&retry_reason(&err), &request_id, attempt, backoff);
+                        tokio::time::sleep(backoff).await;
                         continue;
                     }
                     Err(err) => {
-                        eprintln!("Error occured: {err:#?}");
-                        return Err(err);
+                        tracing::error!(request_id, error = ?err, "request failed");
+                        return Err(tag_error(err, &request_id));
                     }
                     Ok(_) => return Ok(body),
                 }
-            } else {
-                eprintln!("Bad response: {response:#?}");
-                tokio::time::sleep(Duration::from_secs(retry_interval)).await;
+            } else if let Err(e) = response {
+                self.update_stats(|s| *s.errors.entry(String::from("transport")).or_default() += 1);
+                // A timed out write may have been executed, and resending it
+                // with a fresh nonce would duplicate orders or offers.
+                if e.is_timeout() && url.starts_with("auth/w/") {
+                    tracing::error!(request_id, error = ?e, "write request timed out");
+                    return Err(BitfinexError::Timeout(format!(
+                        "{url} timed out (request id: {request_id})"
+                    )));
+                }
+                if attempt > policy.max_retries() || !policy.is_retryable(url, None) {
+                    break;
+                }
+                let backoff = policy.backoff(attempt);
+                log_retry("bad response", &request_id, attempt, backoff);
+                tokio::time::sleep(backoff).await;
             }
         }
-        Err(BitfinexError::BitfinexGenericError(
-            "Exceed max retry count".into(),
-        ))
+        Err(BitfinexError::BitfinexGenericError(format!(
+            "Exceed max retry count (request id: {request_id})"
+        )))
     }
 
     pub async fn post_url(&self, url: &String) -> Result<String, BitfinexError> {
@@ -709,7 +1634,7 @@ impl Client {
         let url = String::from("calc/fx");
         let payload = json!({"ccy1": ccy, "ccy2": to_ccy}).to_string();
         let res = self.post_with_payload(&url, payload).await?;
-        let res: Vec<f64> = from_str(&res).unwrap();
+        let res: Vec<f64> = parse_one(&res)?;
         Ok(res[0])
     }
 
@@ -718,14 +1643,128 @@ impl Client {
         let body = self
             .get(&String::from("conf/pub:list:pair:exchange"))
             .await?;
-        let res: Vec<Vec<String>> = from_str(&body).unwrap();
+        let res: Vec<Vec<String>> = parse_one(&body)?;
+        Ok(res[0].to_owned())
+    }
+
+    /// Pairs available for margin trading.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_avail_margin_pairs(&self) -> Result<Vec<String>, BitfinexError> {
+        let body = self
+            .get(&String::from("conf/pub:list:pair:margin"))
+            .await?;
+        let res: Vec<Vec<String>> = parse_one(&body)?;
         Ok(res[0].to_owned())
     }
 
+    /// Derivatives pairs, e.g. `BTCF0:USTF0`.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_avail_futures_pairs(&self) -> Result<Vec<String>, BitfinexError> {
+        let body = self
+            .get(&String::from("conf/pub:list:pair:futures"))
+            .await?;
+        let res: Vec<Vec<String>> = parse_one(&body)?;
+        Ok(res[0].to_owned())
+    }
+
+    /// Deposit and withdrawal methods, in lowercase as expected by
+    /// [`Client::request_deposit_address`], with the currencies they move,
+    /// e.g. `tetherusl` -> `[UST]`.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_deposit_methods(
+        &self,
+    ) -> Result<HashMap<String, Vec<String>>, BitfinexError> {
+        let body = self.get(&String::from("conf/pub:map:tx:method")).await?;
+        let [methods]: [Vec<(String, Vec<String>)>; 1] = parse_one(&body)?;
+        Ok(methods
+            .into_iter()
+            .map(|(method, ccys)| (method.to_lowercase(), ccys))
+            .collect())
+    }
+
+    /// Deposit and withdrawal status of all methods, to check that they are
+    /// not in maintenance before moving funds.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_tx_status(&self) -> Result<Vec<TxStatus>, BitfinexError> {
+        let body = self.get(&String::from("conf/pub:info:tx:status")).await?;
+        let [status]: [Vec<TxStatus>; 1] = parse_one(&body)?;
+        Ok(status)
+    }
+
+    /// Full names of currencies, e.g. `BTC` -> `Bitcoin`.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_currency_labels(&self) -> Result<HashMap<String, String>, BitfinexError> {
+        self.request_currency_map("label").await
+    }
+
+    /// Symbols shown instead of the currency codes, e.g. `UST` -> `USDt`.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_currency_syms(&self) -> Result<HashMap<String, String>, BitfinexError> {
+        self.request_currency_map("sym").await
+    }
+
+    /// Units of the currency amounts, e.g. `XAUT` -> `GRAM`.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_currency_units(&self) -> Result<HashMap<String, String>, BitfinexError> {
+        self.request_currency_map("unit").await
+    }
+
+    async fn request_currency_map(
+        &self,
+        key: &str,
+    ) -> Result<HashMap<String, String>, BitfinexError> {
+        let url = format!("conf/pub:map:currency:{key}");
+        let body = self.get(&url).await?;
+        let [map]: [Vec<(String, String)>; 1] = parse_one(&body)?;
+        Ok(map.into_iter().collect())
+    }
+
+    /// Label, symbol, unit and block explorer of all currencies, by currency
+    /// code. Currencies without any of them are not included.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_currency_info(
+        &self,
+    ) -> Result<HashMap<String, CurrencyInfo>, BitfinexError> {
+        let url = String::from(
+            "conf/pub:map:currency:label,pub:map:currency:sym,pub:map:currency:unit,pub:map:currency:explorer",
+        );
+        let body = self.get(&url).await?;
+        type Maps = (
+            Vec<(String, String)>,
+            Vec<(String, String)>,
+            Vec<(String, String)>,
+            Vec<(String, Vec<String>)>,
+        );
+        let (labels, syms, units, explorers): Maps = parse_one(&body)?;
+
+        let mut info: HashMap<String, CurrencyInfo> = HashMap::new();
+        for (ccy, label) in labels {
+            info.entry(ccy).or_default().label = Some(label);
+        }
+        for (ccy, sym) in syms {
+            info.entry(ccy).or_default().symbol = Some(sym);
+        }
+        for (ccy, unit) in units {
+            info.entry(ccy).or_default().unit = Some(unit);
+        }
+        for (ccy, explorer) in explorers {
+            info.entry(ccy).or_default().explorer = explorer;
+        }
+        Ok(info)
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
     pub async fn request_avail_ccy_list(&self) -> Result<Vec<String>, BitfinexError> {
         let body = self.get(&String::from("conf/pub:list:currency")).await?;
-        let res: Vec<Vec<String>> = from_str(&body).unwrap();
+        let res: Vec<Vec<String>> = parse_one(&body)?;
         Ok(res[0].to_owned())
     }
 
@@ -740,6 +1779,7 @@ impl Client {
     /// pos.size
     /// 
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-stats>
+    #[allow(clippy::too_many_arguments)]
     pub async fn request_stat(
         &self,
         symbol: &str,
@@ -747,9 +1787,10 @@ impl Client {
         side_pair: Option<String>, // Only for credits.size.sym. Default to tBTCUSD
         use_short: Option<bool>,   // Only for pos.size
         limit: Option<u16>, // Max 10000
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<Stat>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         let k = key.as_str();
         let mut url = format!("stats1/{k}");
 
@@ -766,7 +1807,7 @@ impl Client {
             let side_pair = match side_pair {
                 Some(v) => v,
                 None => {
-                    println!(
+                    tracing::warn!(
                         "Querying credits.size.sym without specifying side_pair. Defaulting to tBTCUSD"
                     );
                     String::from("tBTCUSD")
@@ -803,14 +1844,14 @@ impl Client {
         }
 
         let body = self.get(&url).await?;
-        let stats: Vec<Stat> = from_str(&body).unwrap();
+        let stats: Vec<Stat> = parse_vec(&body)?;
         Ok(stats)
     }
 
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-platform-status>
     pub async fn request_platform_status(&self) -> Result<PlatformStatus, BitfinexError> {
         let body = self.get(&String::from("platform/status")).await?;
-        let res: PlatformStatus = from_str(&body).unwrap();
+        let res: PlatformStatus = parse_one(&body)?;
         Ok(res)
     }
 
@@ -822,9 +1863,10 @@ impl Client {
         &self,
         symbol: &str,
         limit: Option<u16>, // Max 250
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<FundingStats>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         let mut url = format!("funding/stats/{symbol}/hist?");
 
         if let Some(limit) = limit {
@@ -839,7 +1881,7 @@ impl Client {
         }
 
         let body = self.get(&url).await?;
-        let stats: Vec<FundingStats> = from_str(&body).unwrap();
+        let stats: Vec<FundingStats> = parse_vec(&body)?;
         Ok(stats)
     }
 
@@ -850,7 +1892,7 @@ impl Client {
     pub async fn request_deriv_status(&self, keys: &str) -> Result<Vec<DerivativesStatus>, BitfinexError> {
         let url = format!("status/deriv?keys={keys}");
         let body = self.get(&url).await?;
-        let sts: Vec<DerivativesStatus> = from_str(&body).unwrap();
+        let sts: Vec<DerivativesStatus> = parse_vec(&body)?;
         Ok(sts)
     }
 
@@ -859,30 +1901,166 @@ impl Client {
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-info-user>
     pub async fn request_user_info(&self) -> Result<User, BitfinexError> {
         let body = self.post_url(&String::from("auth/r/info/user")).await?;
-        let user: User = from_str(&body).unwrap();
+        let user: User = parse_one(&body)?;
         Ok(user)
     }
 
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-summary>
+    pub async fn request_account_summary(&self) -> Result<AccountSummary, BitfinexError> {
+        let body = self.post_url(&String::from("auth/r/summary")).await?;
+        let value: Value = parse_one(&body)?;
+        Ok(AccountSummary::from_value(&value))
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-wallets>
     pub async fn request_wallets(&self) -> Result<Vec<Wallet>, BitfinexError> {
         let body = self.post_url(&String::from("auth/r/wallets")).await?;
-        let wallets: Vec<Wallet> = from_str(&body).unwrap();
+        let wallets: Vec<Wallet> = parse_vec(&body)?;
         Ok(wallets)
     }
 
+    /// Wallet balances at a past point in time.
+    ///
+    /// ## Parameters:
+    /// - `end`: point in time of the balances. Now if not specified.
+    /// - `ccy`: only return wallets of this currency (e.g. USD).
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-wallets-hist>
+    pub async fn request_wallets_hist(
+        &self,
+        end: Option<DateTime<Utc>>,
+        ccy: Option<&str>,
+    ) -> Result<Vec<WalletHist>, BitfinexError> {
+        let mut payload = json!({});
+        if let Some(end) = end {
+            payload["end"] = Value::from(end.timestamp_millis());
+        }
+        if let Some(ccy) = ccy {
+            payload["currency"] = Value::from(ccy);
+        }
+        let url = String::from("auth/r/wallets/hist");
+        let body = self.post(&url, Some(payload.to_string()), None).await?;
+        let wallets: Vec<WalletHist> = parse_vec(&body)?;
+        Ok(wallets)
+    }
+
+    /// ## Parameters:
+    /// - `categories`: ledger types to return. Empty for all types.
+    ///
+    /// Bitfinex filters by a single category per request, so multiple categories
+    /// are fetched concurrently and merged from the newest record. `limit` applies
+    /// to the merged result.
+    ///
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-ledgers>
     pub async fn request_ledger(
+        &self,
+        ccy: &str,
+        limit: Option<u16>,
+        categories: Vec<LedgerType>,
+    ) -> Result<Vec<Ledger>, BitfinexError> {
+        if categories.is_empty() {
+            return self.request_ledger_category(ccy, limit, None).await;
+        }
+
+        let results = join_all(
+            categories
+                .into_iter()
+                .map(|category| self.request_ledger_category(ccy, limit, Some(category))),
+        )
+        .await;
+        let mut ledgers = Vec::new();
+        for result in results {
+            ledgers.extend(result?);
+        }
+        ledgers.sort_by(|a, b| b.time.cmp(&a.time).then(b.id.cmp(&a.id)));
+        if let Some(limit) = limit {
+            ledgers.truncate(limit as usize);
+        }
+        Ok(ledgers)
+    }
+
+    /// Ledger entries of all currencies since `start`, from the newest. Pages
+    /// back through the history, unlike [`Client::request_ledger`] which
+    /// returns at most 2500 entries.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-ledgers>
+    pub async fn request_ledger_since(
+        &self,
+        start: DateTime<Utc>,
+    ) -> Result<Vec<Ledger>, BitfinexError> {
+        let url = String::from("auth/r/ledgers/hist");
+        let limit: usize = 2500;
+        let mut end = Utc::now();
+        let mut seen = HashSet::new();
+        let mut ledgers = Vec::new();
+        loop {
+            let payload = json!({
+                "start": start.timestamp_millis(),
+                "end": end.timestamp_millis(),
+                "limit": limit,
+            })
+            .to_string();
+            let body = self.post_with_payload(&url, payload).await?;
+            let page: Vec<Ledger> = parse_vec(&body)?;
+            let full_page = page.len() == limit;
+            let Some(oldest) = page.last() else {
+                break;
+            };
+            // Pages overlap on the boundary millisecond
+            let stalled = *oldest.time == end;
+            end = *oldest.time;
+            ledgers.extend(page.into_iter().filter(|l| seen.insert(l.id)));
+            if !full_page || stalled {
+                break;
+            }
+        }
+        Ok(ledgers)
+    }
+
+    /// Deposits and withdrawals of all currencies, or of `ccy` if given.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-movements>
+    pub async fn request_movements(
+        &self,
+        ccy: Option<&str>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        limit: Option<u16>,
+    ) -> Result<Vec<Movement>, BitfinexError> {
+        let url = match ccy {
+            Some(ccy) => format!("auth/r/movements/{ccy}/hist"),
+            None => String::from("auth/r/movements/hist"),
+        };
+
+        let mut payload = json!({});
+        if let Some(start) = start {
+            payload["start"] = Value::from(start.timestamp_millis());
+        }
+        if let Some(end) = end {
+            payload["end"] = Value::from(end.timestamp_millis());
+        }
+        if let Some(limit) = limit {
+            // Max 1000
+            payload["limit"] = Value::from(limit);
+        }
+
+        let body = self.post_with_payload(&url, payload.to_string()).await?;
+        let movements: Vec<Movement> = parse_vec(&body)?;
+        Ok(movements)
+    }
+
+    async fn request_ledger_category(
         &self,
         ccy: &str,
         limit: Option<u16>,
         category: Option<LedgerType>,
     ) -> Result<Vec<Ledger>, BitfinexError> {
         let url = format!("auth/r/ledgers/{ccy}/hist");
-        let cat: u8 = match category {
-            Some(category) => category.into(),
-            None => LedgerType::Interest.into(),
-        };
-        let payload = json!({"category": cat}).to_string();
+        let payload = match category {
+            Some(category) => json!({"category": u8::from(category)}),
+            None => json!({}),
+        }
+        .to_string();
 
         let mut params = Vec::<(&str, String)>::new();
         if let Some(limit) = limit {
@@ -891,16 +2069,80 @@ impl Client {
         }
 
         let body = self.post(&url, Some(payload), Some(params)).await?;
-        // let ledgers: Vec<Ledger> = from_str(&body).unwrap();
-        let ledgers: Vec<Ledger> = from_str(&body).unwrap();
+        let ledgers: Vec<Ledger> = parse_vec(&body)?;
         Ok(ledgers)
     }
 
+    /// Maximum amount of an order or offer that can be submitted.
+    ///
+    /// ## Parameters:
+    /// - `dir`: 1 to buy, -1 to sell. Only for trading symbols.
+    /// - `rate`: order price. Only for trading symbols.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-calc-order-avail>
+    pub async fn calc_available_balance(
+        &self,
+        symbol: &str,
+        dir: Option<i8>,
+        rate: Option<&str>,
+        typ: AvailBalanceType,
+    ) -> Result<f64, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = String::from("auth/calc/order/avail");
+        let mut payload = json!({
+            "symbol": symbol,
+            "type": typ.as_str(),
+        });
+        if let Some(dir) = dir {
+            payload["dir"] = Value::from(dir);
+        }
+        if let Some(rate) = rate {
+            payload["rate"] = Value::from(rate);
+        }
+
+        let body = self.post_with_payload(&url, payload.to_string()).await?;
+        let res: Vec<f64> = parse_one(&body)?;
+        Ok(res[0])
+    }
+
+    /// Generate a short-lived auth token, so other apps can authenticate
+    /// without the API secret.
+    ///
+    /// ## Parameters:
+    /// - `scope`: scope of the token, e.g. `api`.
+    /// - `ttl`: lifetime of the token in seconds.
+    /// - `write_permission`: allow the token to make write requests.
+    /// - `caps`: permissions of the token, e.g. `["a", "o", "f", "w"]` for account,
+    ///   orders, funding and wallets. The permissions of the API key if empty.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/generate-token>
+    pub async fn generate_token(
+        &self,
+        scope: &str,
+        ttl: u32,
+        write_permission: bool,
+        caps: &[&str],
+    ) -> Result<String, BitfinexError> {
+        let url = String::from("auth/w/token");
+        let mut payload = json!({
+            "scope": scope,
+            "ttl": ttl,
+            "writePermission": write_permission,
+        });
+        if !caps.is_empty() {
+            payload["caps"] = Value::from(caps);
+        }
+
+        let body = self.post_with_payload(&url, payload.to_string()).await?;
+        let res: Vec<String> = parse_one(&body)?;
+        Ok(res[0].to_owned())
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/key-permissions>
     pub async fn request_key_permission(&self) -> Result<KeyPermission, BitfinexError> {
         let body = self.post_url(&String::from("auth/r/permissions")).await?;
 
-        let perm: Vec<Permission> = from_str(&body).unwrap();
+        let perm: Vec<Permission> = parse_vec(&body)?;
         let mut temp_data = serde_json::Map::<String, Value>::new();
         for p in perm {
             let v = json!({
@@ -911,26 +2153,78 @@ impl Client {
             temp_data.insert(p.name.clone(), v);
         }
         let value: Value = Value::Object(temp_data);
-        let permission: KeyPermission = serde_json::from_value(value).unwrap();
+        let permission: KeyPermission =
+            serde_json::from_value(value).map_err(|e| BitfinexError::ParseError(e.to_string()))?;
         Ok(permission)
     }
 
+    /// ## Parameters:
+    /// - `method`: deposit method, e.g. `bitcoin` or `tetherusl`, see
+    ///   [`Client::request_deposit_methods`]. Unknown methods fail with
+    ///   [`BitfinexError::UnknownDepositMethod`] without sending the request.
+    ///
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-deposit-address>
     pub async fn request_deposit_address(
         &self,
         wallet: WalletType,
-        method: DepositMethod,
+        method: &str,
     ) -> Result<Vec<DepositAddress>, BitfinexError> {
+        let method = method.to_lowercase();
+        let methods = match self.deposit_methods.get() {
+            Some(methods) => methods,
+            None => {
+                let methods = self.request_deposit_methods().await?;
+                self.deposit_methods.get_or_init(|| methods)
+            }
+        };
+        if !methods.contains_key(&method) {
+            return Err(BitfinexError::UnknownDepositMethod {
+                suggestions: closest_symbols(&method, methods.keys()),
+                method,
+            });
+        }
+
         let url = String::from("auth/w/deposit/address");
         let payload = json!({
             "wallet": wallet.as_str(),
-            "method": method.to_string(),
+            "method": method,
             "op_renew": 0
         });
 
         let body = self.post_with_payload(&url, payload.to_string()).await?;
 
-        let result: DepositAddressResult = from_str(&body).unwrap();
+        let result: DepositAddressResult = parse_notification(&body)?;
         Ok(result.addresses)
     }
+
+    /// Generate an invoice to deposit `amount` of `ccy`, e.g. LNX for the
+    /// Lightning Network.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-deposit-invoice>
+    pub async fn request_deposit_invoice(
+        &self,
+        wallet: WalletType,
+        ccy: &str,
+        amount: &str,
+    ) -> Result<LightningInvoice, BitfinexError> {
+        let url = String::from("auth/w/deposit/invoice");
+        let payload = json!({
+            "currency": ccy,
+            "wallet": wallet.as_str(),
+            "amount": amount,
+        });
+
+        let body = self.post_with_payload(&url, payload.to_string()).await?;
+        let invoice: LightningInvoice = parse_one(&body)?;
+        Ok(invoice)
+    }
+
+    /// Generate a Lightning Network invoice to deposit `amount` BTC. Only the
+    /// exchange wallet accepts Lightning deposits.
+    pub async fn request_lightning_invoice(
+        &self,
+        amount: &str,
+    ) -> Result<LightningInvoice, BitfinexError> {
+        self.request_deposit_invoice(WalletType::Exchange, "LNX", amount).await
+    }
 }
@@ -1,37 +1,72 @@
 
-use serde::de::{self, Deserializer};
-use chrono::{Local, DateTime};
 use serde::Deserialize;
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde_json::{Value, from_str, from_value};
+
+use crate::error::BitfinexError;
+
+/// Parse a list response. `[]`, `[null]` and `null`, returned when there is no
+/// data or during maintenance, yield an empty vector.
+pub fn parse_vec<T: DeserializeOwned>(body: &str) -> Result<Vec<T>, BitfinexError> {
+    let value: Value = from_str(body).map_err(|e| BitfinexError::ParseError(e.to_string()))?;
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(items) => items
+            .into_iter()
+            .filter(|item| !item.is_null())
+            .map(|item| from_value(item).map_err(|e| BitfinexError::ParseError(e.to_string())))
+            .collect(),
+        _ => Err(BitfinexError::ParseError(format!(
+            "Expected an array: {body}"
+        ))),
+    }
+}
 
-
-pub fn int_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_any(IntOrBoolVisitor)
+/// Parse a single record response. `[]`, `[null]` and `null` yield
+/// [`BitfinexError::NoData`].
+pub fn parse_one<T: DeserializeOwned>(body: &str) -> Result<T, BitfinexError> {
+    let value: Value = from_str(body).map_err(|e| BitfinexError::ParseError(e.to_string()))?;
+    let empty = match &value {
+        Value::Null => true,
+        Value::Array(items) => items.iter().all(Value::is_null),
+        _ => false,
+    };
+    if empty {
+        return Err(BitfinexError::NoData);
+    }
+    from_value(value).map_err(|e| BitfinexError::ParseError(e.to_string()))
 }
 
+/// Parse a notification of a write request, e.g.
+/// `[MTS, TYPE, MESSAGE_ID, null, DATA, CODE, STATUS, TEXT]`.
+///
+/// A notification with status ERROR or FAILURE is returned as a typed error.
+pub fn parse_notification<T: DeserializeOwned>(body: &str) -> Result<T, BitfinexError> {
+    let value: Value = from_str(body).map_err(|e| BitfinexError::ParseError(e.to_string()))?;
+    if let Some(status @ ("ERROR" | "FAILURE")) = value.get(6).and_then(Value::as_str) {
+        let message = match value.get(7).and_then(Value::as_str) {
+            Some(text) => text.to_string(),
+            None => status.to_string(),
+        };
+        return Err(BitfinexError::from_message(message));
+    }
+    parse_one(body)
+}
 
-pub fn from_mts<'de, D>(deserializer: D) -> Result<DateTime<Local>, D::Error>
+/// Deserialize `null` as the default value, e.g. an empty vector.
+pub fn null_to_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
 {
-    let timestamp = i64::deserialize(deserializer)?;
-    if let Some(result) = DateTime::from_timestamp_millis(timestamp) {
-        Ok(result.with_timezone(&Local))
-    } else {
-        Err(de::Error::custom("Failed to parse"))
-    }
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
 }
 
-pub fn to_mts<S>(
-    datetime: &DateTime<Local>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
+pub fn int_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
-    S: serde::ser::Serializer,
+    D: Deserializer<'de>,
 {
-    serializer.serialize_i64(datetime.timestamp_millis())
+    deserializer.deserialize_any(IntOrBoolVisitor)
 }
 
 
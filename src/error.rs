@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 #[derive(Debug)]
 pub enum BitfinexError {
     ExceedMaxOfferCount,
@@ -7,4 +9,70 @@ pub enum BitfinexError {
     RateLimited,
     BitfinexTempUnavailable,
     NonceSmall,
+    WebSocketError(String),
+    /// The endpoint returned no data, e.g. `[]` or `[null]`.
+    NoData,
+    /// The response does not match the expected model.
+    ParseError(String),
+    /// Not enough balance in the wallet for the order or offer.
+    InsufficientBalance {
+        message: String,
+        /// Missing amount, known when detected locally.
+        shortfall: Option<f64>,
+    },
+    InvalidPrice(String),
+    /// Funding period in days outside of the 2-120 range.
+    InvalidPeriod(u8),
+    /// Order or offer amount below the minimum size of the pair.
+    MinimumSizeNotMet(String),
+    /// Symbol not listed by Bitfinex, with close matches of listed symbols.
+    UnknownSymbol {
+        symbol: String,
+        suggestions: Vec<String>,
+    },
+    /// Deposit method not listed by Bitfinex, with close matches of listed
+    /// methods.
+    UnknownDepositMethod {
+        method: String,
+        suggestions: Vec<String>,
+    },
+    /// Invalid argument of a library call, detected before sending the
+    /// request.
+    InvalidArgument(String),
+    /// Candle time frame or aggregation not served for the kind of symbol.
+    UnsupportedCandles(String),
+    /// No response to a write request within the timeout. The request may
+    /// have reached Bitfinex, so it is not sent again; look up the order or
+    /// offer by client ID before resubmitting.
+    Timeout(String),
+    /// Requests to the endpoint class are failing fast after repeated
+    /// failures, see `Client::with_circuit_breaker`.
+    CircuitOpen {
+        endpoint: String,
+        retry_in: Duration,
+    },
+}
+
+impl BitfinexError {
+    /// Map the message of a failed request or an ERROR notification into a
+    /// typed error.
+    pub(crate) fn from_message(message: String) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("not enough") && lower.contains("balance") {
+            // "Invalid order: not enough exchange balance for 0.1 BTCUSD at 50000"
+            BitfinexError::InsufficientBalance {
+                message,
+                shortfall: None,
+            }
+        } else if lower.contains("minimum size") || lower.contains("minimum is") {
+            // "Invalid order: minimum size for BTC/USD is 0.00006"
+            // "Invalid offer: incorrect amount, minimum is 150.0 dollar or equivalent in UST"
+            BitfinexError::MinimumSizeNotMet(message)
+        } else if lower.contains("price") && lower.contains("invalid") {
+            // "Invalid order: price: invalid"
+            BitfinexError::InvalidPrice(message)
+        } else {
+            BitfinexError::BitfinexGenericError(message)
+        }
+    }
 }
@@ -1,20 +1,23 @@
 use std::{
     cmp::max,
     convert::{From, Into},
+    time::Duration,
 };
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
-use serde_json::{from_str, json};
+use serde_json::{Value, json};
 
 use crate::{
     client::Client,
-    deserializer::{from_mts, int_to_bool, to_mts},
+    deserializer::{int_to_bool, null_to_default, parse_notification, parse_one, parse_vec},
     error::BitfinexError,
-    utils::parse_ccy_from_symbol,
+    utils::{FundingPeriod, Mts, Side, parse_ccy_from_symbol},
 };
 
 // --- Enums --- //
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BookPrecision {
     One,
     Two,
@@ -78,7 +81,33 @@ impl std::fmt::Display for FundingOrderType {
     }
 }
 
-#[derive(PartialEq)]
+/// Kind of funding for [`Client::keep_funding`].
+pub enum FundingKeepType {
+    /// Funds lent to and used in positions.
+    Credit,
+    /// Funds borrowed.
+    Loan,
+}
+
+impl From<&str> for FundingKeepType {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "loan" => FundingKeepType::Loan,
+            _ => FundingKeepType::Credit,
+        }
+    }
+}
+
+impl std::fmt::Display for FundingKeepType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FundingKeepType::Credit => write!(f, "credit"),
+            FundingKeepType::Loan => write!(f, "loan"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum CandleAggPeriod {
     A10,
     A30,
@@ -92,7 +121,7 @@ impl From<u8> for CandleAggPeriod {
             10 => Self::A10,
             30 => Self::A30,
             120 => Self::A120,
-            0 | _ => Self::Nil,
+            _ => Self::Nil,
         }
     }
 }
@@ -108,6 +137,7 @@ impl From<CandleAggPeriod> for u8 {
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum CandleTimeFrame {
     Min1,
     Min5,
@@ -124,9 +154,11 @@ pub enum CandleTimeFrame {
     Month1,
 }
 
-impl From<&str> for CandleTimeFrame {
-    fn from(value: &str) -> Self {
-        match value {
+impl TryFrom<&str> for CandleTimeFrame {
+    type Error = BitfinexError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
             "1m" => CandleTimeFrame::Min1,
             "5m" => CandleTimeFrame::Min5,
             "15m" => CandleTimeFrame::Min15,
@@ -136,12 +168,17 @@ impl From<&str> for CandleTimeFrame {
             "4h" => CandleTimeFrame::Hour4,
             "6h" => CandleTimeFrame::Hour6,
             "12h" => CandleTimeFrame::Hour12,
-            "1d" => CandleTimeFrame::Day1,
-            "1w" => CandleTimeFrame::Week1,
-            "2w" => CandleTimeFrame::Week2,
+            "1d" | "1D" => CandleTimeFrame::Day1,
+            "1w" | "1W" => CandleTimeFrame::Week1,
+            "2w" | "14D" => CandleTimeFrame::Week2,
             "1M" => CandleTimeFrame::Month1,
-            _ => CandleTimeFrame::Hour4,
-        }
+            _ => {
+                return Err(BitfinexError::InvalidArgument(format!(
+                    "Unknown candle time frame: {value}, expected one of 1m, 5m, 15m, 30m, \
+                     1h, 3h, 6h, 12h, 1D, 1W, 14D or 1M"
+                )));
+            }
+        })
     }
 }
 
@@ -157,19 +194,75 @@ impl From<CandleTimeFrame> for String {
             CandleTimeFrame::Hour4 => String::from("4h"),
             CandleTimeFrame::Hour6 => String::from("6h"),
             CandleTimeFrame::Hour12 => String::from("12h"),
-            CandleTimeFrame::Day1 => String::from("1d"),
-            CandleTimeFrame::Week1 => String::from("1w"),
-            CandleTimeFrame::Week2 => String::from("2w"),
+            CandleTimeFrame::Day1 => String::from("1D"),
+            CandleTimeFrame::Week1 => String::from("1W"),
+            CandleTimeFrame::Week2 => String::from("14D"),
             CandleTimeFrame::Month1 => String::from("1M"),
         }
     }
 }
 
+impl CandleTimeFrame {
+    /// Length of one candle. `None` for months, which vary in length.
+    pub fn duration(&self) -> Option<Duration> {
+        let secs = match self {
+            CandleTimeFrame::Min1 => 60,
+            CandleTimeFrame::Min5 => 5 * 60,
+            CandleTimeFrame::Min15 => 15 * 60,
+            CandleTimeFrame::Min30 => 30 * 60,
+            CandleTimeFrame::Hour1 => 3600,
+            CandleTimeFrame::Hour3 => 3 * 3600,
+            CandleTimeFrame::Hour4 => 4 * 3600,
+            CandleTimeFrame::Hour6 => 6 * 3600,
+            CandleTimeFrame::Hour12 => 12 * 3600,
+            CandleTimeFrame::Day1 => 86400,
+            CandleTimeFrame::Week1 => 7 * 86400,
+            CandleTimeFrame::Week2 => 14 * 86400,
+            CandleTimeFrame::Month1 => return None,
+        };
+        Some(Duration::from_secs(secs))
+    }
+}
+
+/// Check that Bitfinex serves the requested candles for the kind of `symbol`,
+/// since unsupported combinations come back as an empty result.
+///
+/// `funding` is the aggregation and period in days of funding candles, `None`
+/// for trading pairs and derivatives, which take no aggregation.
+pub(crate) fn check_candles(
+    symbol: &str,
+    time_frame: CandleTimeFrame,
+    funding: Option<(CandleAggPeriod, u8)>,
+) -> Result<(), BitfinexError> {
+    if let CandleTimeFrame::Hour4 = time_frame {
+        return Err(BitfinexError::UnsupportedCandles(String::from(
+            "4h candles are not provided, use 3h or 6h instead",
+        )));
+    }
+
+    match funding {
+        None if !symbol.starts_with('t') => Err(BitfinexError::UnsupportedCandles(format!(
+            "{symbol} is not a trading pair, e.g. tBTCUSD or tBTCF0:USTF0"
+        ))),
+        Some(_) if !symbol.starts_with('f') => Err(BitfinexError::UnsupportedCandles(format!(
+            "{symbol} is not a funding currency, e.g. fUSD"
+        ))),
+        Some((agg_period, period))
+            if agg_period != CandleAggPeriod::Nil && period % u8::from(agg_period) != 0 =>
+        {
+            let agg_p = u8::from(agg_period);
+            Err(BitfinexError::UnsupportedCandles(format!(
+                "period {period} is not a multiple of aggregation period {agg_p}"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
 // --- Data Models --- //
 #[derive(Serialize, Deserialize)]
 pub struct Candle {
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
     pub open: f64,
     pub close: f64,
     pub high: f64,
@@ -179,24 +272,43 @@ pub struct Candle {
 
 #[derive(Serialize, Deserialize)]
 pub struct FundingBook {
+    /// 0 for offers at the Flash Return Rate, see `is_frr`.
     pub rate: f64,
     pub period: u8,
     pub count: u16,
     pub amount: f64,
+
+    /// Offers and bids placed at FRR, whose rate follows the FRR instead of
+    /// being 0.
+    #[serde(skip_deserializing)]
+    pub is_frr: bool,
+}
+
+impl FundingBook {
+    /// `Lend` for offers, `Borrow` for bids.
+    pub fn side(&self) -> Side {
+        Side::from_funding_amount(self.amount)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FundingTrade {
     pub id: u64,
 
-    #[serde(deserialize_with = "from_mts", serialize_with = "to_mts")]
-    pub created: DateTime<Local>,
+    pub created: Mts,
 
     pub amount: f64,
     pub rate: f64,
     pub period: u8,
 }
 
+impl FundingTrade {
+    /// Side of the taker.
+    pub fn side(&self) -> Side {
+        Side::from_funding_trade_amount(self.amount)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct FundingBookRaw {
     pub id: u64,
@@ -205,6 +317,13 @@ pub struct FundingBookRaw {
     pub amount: f64,
 }
 
+impl FundingBookRaw {
+    /// `Lend` for offers, `Borrow` for bids.
+    pub fn side(&self) -> Side {
+        Side::from_funding_amount(self.amount)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct FundingTicker {
     pub frr: f64,
@@ -235,10 +354,8 @@ pub struct FundingCredit {
     pub symbol: String,
     pub side: i8, // 1 lender, 0 lender and borrower, -1 borrower
 
-    #[serde(deserialize_with = "from_mts")]
-    pub created: DateTime<Local>,
-    #[serde(deserialize_with = "from_mts")]
-    pub updated: DateTime<Local>,
+    pub created: Mts,
+    pub updated: Mts,
     pub amount: f64,
 
     #[serde(skip_serializing)]
@@ -255,10 +372,8 @@ pub struct FundingCredit {
     pub rate: f64,
     pub period: u8,
 
-    #[serde(deserialize_with = "from_mts")]
-    pub opened: DateTime<Local>,
-    #[serde(deserialize_with = "from_mts")]
-    pub last_payout: DateTime<Local>,
+    pub opened: Mts,
+    pub last_payout: Mts,
     pub notify: Option<bool>,
     #[serde(deserialize_with = "int_to_bool")]
     pub hidden: bool,
@@ -282,10 +397,8 @@ pub struct FundingOffer {
     pub id: u64,
     pub symbol: String,
 
-    #[serde(deserialize_with = "from_mts")]
-    pub created: DateTime<Local>,
-    #[serde(deserialize_with = "from_mts")]
-    pub updated: DateTime<Local>,
+    pub created: Mts,
+    pub updated: Mts,
 
     pub amount: f64,
     pub amount_ori: f64,
@@ -322,10 +435,32 @@ pub struct FundingOffer {
     _placeholder_7: Option<String>,
 }
 
+impl FundingOffer {
+    /// `Lend` for offers, `Borrow` for bids.
+    pub fn side(&self) -> Side {
+        Side::from_funding_amount(self.amount_ori)
+    }
+}
+
+/// Notification of a funding write request without typed data.
+#[derive(Serialize, Deserialize)]
+pub struct FundingNotification {
+    pub created: Mts,
+    pub event_type: String,
+    pub message_id: Option<u64>,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub data: Option<Value>,
+    pub code: Option<u16>,
+    pub status: String,
+    pub message: Option<String>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct FundingOfferResult {
-    #[serde(deserialize_with = "from_mts")]
-    pub created: DateTime<Local>,
+    pub created: Mts,
     pub event_type: String,
     pub message_id: Option<u64>,
 
@@ -338,11 +473,148 @@ pub struct FundingOfferResult {
     pub message: Option<String>,
 }
 
+/// Result of [`Client::cancel_funding_offer_all`].
+#[derive(Serialize, Deserialize)]
+pub struct FundingOfferCancelAllResult {
+    pub created: Mts,
+    pub event_type: String,
+    pub message_id: Option<u64>,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    /// Canceled offers, empty if Bitfinex does not list them.
+    #[serde(deserialize_with = "null_to_default")]
+    pub offers: Vec<FundingOffer>,
+    pub code: Option<u16>,
+    pub status: String,
+    pub message: Option<String>,
+}
+
+impl FundingOfferCancelAllResult {
+    pub fn is_success(&self) -> bool {
+        self.status == "SUCCESS"
+    }
+}
+
+/// Summary of the lending market of a single currency, see [`Client::scan_funding_market`].
+#[derive(Serialize)]
+pub struct FundingMarket {
+    pub symbol: String,
+    pub frr: f64,
+    pub last_rate: f64,
+    /// 24h volume in the funding currency.
+    pub volume: f64,
+    /// 24h volume in USD. `None` if no USD pair exists for the currency.
+    pub volume_usd: Option<f64>,
+    /// Total funding provided in the funding currency, from the latest stats.
+    pub depth: Option<f64>,
+    /// `depth` in USD. `None` if no USD pair exists for the currency.
+    pub depth_usd: Option<f64>,
+    /// Coefficient of variation (std / mean) of the recent FRR history.
+    pub volatility: Option<f64>,
+    /// Error of the stats request, in which case `depth` and `volatility` are
+    /// unknown.
+    pub stats_error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FundingInfo {
+    pub symbol: String,
+    /// Weighted average rate of the taken funding.
+    pub yield_loan: f64,
+    /// Weighted average rate of the provided funding.
+    pub yield_lend: f64,
+    /// Weighted average duration of the taken funding.
+    pub duration_loan: f64,
+    /// Weighted average duration of the provided funding.
+    pub duration_lend: f64,
+}
+
+impl<'de> Deserialize<'de> for FundingInfo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // ["sym", SYMBOL, [YIELD_LOAN, YIELD_LEND, DURATION_LOAN, DURATION_LEND]]
+        let (_, symbol, info): (String, String, Vec<f64>) = Deserialize::deserialize(deserializer)?;
+        if info.len() < 4 {
+            return Err(serde::de::Error::invalid_length(
+                info.len(),
+                &"4 funding values",
+            ));
+        }
+        Ok(FundingInfo {
+            symbol,
+            yield_loan: info[0],
+            yield_lend: info[1],
+            duration_loan: info[2],
+            duration_lend: info[3],
+        })
+    }
+}
+
+// --- Funding Helpers --- //
+/// Aggregate public funding trades into rate candles of an arbitrary interval
+/// (e.g. 15m or 6h), which the candles endpoint does not provide.
+///
+/// ## Parameters:
+/// - `period`: only use trades of this funding period in days. All periods if not specified.
+///
+/// Candles are aligned to the unix epoch and returned from the oldest. Open,
+/// close, high and low are rates and the volume is the sum of the absolute
+/// amounts. Intervals without trades are skipped. A zero `interval` fails
+/// with [`BitfinexError::InvalidArgument`].
+pub fn aggregate_funding_trades(
+    trades: &[FundingTrade],
+    interval: Duration,
+    period: Option<FundingPeriod>,
+) -> Result<Vec<Candle>, BitfinexError> {
+    if interval.is_zero() {
+        return Err(BitfinexError::InvalidArgument(String::from(
+            "Candle interval must be greater than 0",
+        )));
+    }
+    let interval_ms = interval.as_millis() as i64;
+
+    let mut sorted: Vec<&FundingTrade> = trades
+        .iter()
+        .filter(|t| period.is_none_or(|p| t.period == p.days()))
+        .collect();
+    sorted.sort_by_key(|t| (t.created, t.id));
+
+    let mut candles: Vec<Candle> = Vec::new();
+    for t in sorted {
+        let mts = t.created.timestamp_millis();
+        let start = mts - mts.rem_euclid(interval_ms);
+        let volume = t.amount.abs();
+        if let Some(candle) = candles.last_mut()
+            && candle.time.timestamp_millis() == start
+        {
+            candle.close = t.rate;
+            candle.high = candle.high.max(t.rate);
+            candle.low = candle.low.min(t.rate);
+            candle.volume += volume;
+            continue;
+        }
+        let Some(time) = Mts::from_millis(start) else {
+            continue;
+        };
+        candles.push(Candle {
+            time,
+            open: t.rate,
+            close: t.rate,
+            high: t.rate,
+            low: t.rate,
+            volume,
+        });
+    }
+    Ok(candles)
+}
+
 // --- Funding Functions --- //
 impl Client {
     // --- Public Endpoints --- //
     /// 1. The returned amount > 0 is for ask, amount < 0 is for bid.
     /// 2. For `prec` level, from precise to less precise: 1 -> 4
+    /// 3. Entries at FRR come with a rate of 0 and have `is_frr` set.
     /// 
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-book#for-funding-currency-symbols-ex-fusd>
     pub async fn request_funding_book(
@@ -350,13 +622,17 @@ impl Client {
         symbol: &str,
         prec: BookPrecision,
     ) -> Result<Vec<FundingBook>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("f") {
             panic!("You must specify funding symbol for funding book");
         }
         let prec = u8::from(prec);
         let url = format!("book/{symbol}/P{prec}?len=250");
         let body = self.get(&url).await?;
-        let books: Vec<FundingBook> = from_str(&body).unwrap();
+        let mut books: Vec<FundingBook> = parse_vec(&body)?;
+        for book in books.iter_mut() {
+            book.is_frr = book.rate == 0.0;
+        }
         Ok(books)
     }
 
@@ -365,12 +641,13 @@ impl Client {
         &self,
         symbol: &str,
     ) -> Result<Vec<FundingBookRaw>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("f") {
             panic!("You must specify funding symbol for funding book raw");
         }
         let url = format!("book/{symbol}/R0?len=250");
         let body = self.get(&url).await?;
-        let books: Vec<FundingBookRaw> = from_str(&body).unwrap();
+        let books: Vec<FundingBookRaw> = parse_vec(&body)?;
         Ok(books)
     }
 
@@ -379,9 +656,10 @@ impl Client {
         &self,
         symbol: &str,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<FundingTrade>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("f") {
             panic!("You must specify funding symbol for funding trades");
         }
@@ -397,47 +675,99 @@ impl Client {
             url = format!("{url}&end={}", end.timestamp_millis());
         }
         let body = self.get(&url).await?;
-        let trades: Vec<FundingTrade> = from_str(&body).unwrap();
+        let trades: Vec<FundingTrade> = parse_vec(&body)?;
         Ok(trades)
     }
 
+    /// Rate candles of an arbitrary interval built from the public funding trades
+    /// between `start` and `end`, see [`aggregate_funding_trades`]. Only the
+    /// latest 10000 trades of the range are used.
+    pub async fn request_funding_rate_candles(
+        &self,
+        symbol: &str,
+        interval: Duration,
+        period: Option<FundingPeriod>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Candle>, BitfinexError> {
+        let trades = self
+            .request_funding_trades(symbol, Some(10000), start, end)
+            .await?;
+        aggregate_funding_trades(&trades, interval, period)
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-tickers#for-funding-currency-symbols-ex-fusd>
     pub async fn request_funding_ticker(
         &self,
         symbol: &str,
     ) -> Result<FundingTicker, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("f") {
             panic!("You must specify funding symbol for funding ticker");
         }
         let url = format!("ticker/{symbol}");
         let body = self.get(&url).await?;
-        let ticker: FundingTicker = from_str(&body).unwrap();
+        let ticker: FundingTicker = parse_one(&body)?;
         Ok(ticker)
     }
 
+    /// ## Parameters:
+    /// - `symbols`: funding currencies to query (e.g. fUSD). `["ALL"]` for all currencies.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-tickers>
+    pub async fn request_funding_tickers(
+        &self,
+        symbols: &[&str],
+    ) -> Result<Vec<(String, FundingTicker)>, BitfinexError> {
+        let url = format!("tickers?symbols={}", symbols.join(","));
+        let body = self.get(&url).await?;
+        let rows: Vec<Vec<Value>> = parse_vec(&body)?;
+
+        // Each row is [SYMBOL, ...TICKER_FIELDS]
+        let tickers = rows
+            .into_iter()
+            .filter(|row| {
+                row.first()
+                    .and_then(Value::as_str)
+                    .is_some_and(|s| s.starts_with("f"))
+            })
+            .filter_map(|mut row| {
+                let symbol = row.remove(0).as_str()?.to_string();
+                let ticker: FundingTicker = serde_json::from_value(Value::from(row)).ok()?;
+                Some((symbol, ticker))
+            })
+            .collect();
+        Ok(tickers)
+    }
+
     /// ## Aggregation Rules:
     /// 1. `period` can only be multiply of `agg_period`
-    /// For example, if `agg_period` is A10, then `period` could only be 10, 20, 30, ..., etc.
+    ///    For example, if `agg_period` is A10, then `period` could only be 10, 20, 30, ..., etc.
     /// 
     /// 2. Set `agg_period` to `Nil` to not aggregate.
-    /// 3. Other than the above combinations, Bitfinex returns empty result.
+    /// 3. Other combinations, as well as 4h candles, fail with
+    ///    [`BitfinexError::UnsupportedCandles`] since Bitfinex returns an empty result.
     /// 
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-candles#funding-currency-candles>
+    #[allow(clippy::too_many_arguments)]
     pub async fn request_funding_candles(
         &self,
         symbol: &str,
-        period: u8,
+        period: FundingPeriod,
         agg_period: CandleAggPeriod,
         time_frame: CandleTimeFrame,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<Candle>, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        check_candles(symbol, time_frame, Some((agg_period, period.days())))?;
         let mut sub_query: Vec<String> = Vec::new();
         sub_query.push("trade".into());
         sub_query.push(time_frame.into());
         sub_query.push(symbol.into());
 
+        let period = period.days();
         if agg_period != CandleAggPeriod::Nil {
             // format: a10:p2:p30
             let agg_p = u8::from(agg_period);
@@ -461,7 +791,7 @@ impl Client {
         }
 
         let body = self.get(&url).await?;
-        let candles: Vec<Candle> = from_str(&body).unwrap();
+        let candles: Vec<Candle> = parse_vec(&body)?;
         Ok(candles)
     }
 
@@ -471,30 +801,122 @@ impl Client {
         symbol: &str,
     ) -> Result<Vec<Candle>, BitfinexError> {
         // Wrapper of candles.
-        self.request_funding_candles(symbol, 30, 30.into(), "30m".into(), None, None, None)
+        let period = FundingPeriod::new(30)?;
+        self.request_funding_candles(
+            symbol,
+            period,
+            30.into(),
+            CandleTimeFrame::Min30,
+            None,
+            None,
+            None,
+        )
             .await
     }
 
+    /// Scan the lending market of all funding currencies.
+    ///
+    /// All tickers are fetched with a single request, while the funding stats
+    /// used for depth and volatility are fetched concurrently in small batches
+    /// to stay within the rate limit. Markets whose stats failed are kept, with
+    /// the error in [`FundingMarket::stats_error`].
+    pub async fn scan_funding_market(&self) -> Result<Vec<FundingMarket>, BitfinexError> {
+        const BATCH_SIZE: usize = 5;
+        const HISTORY_LEN: u16 = 24;
+
+        let funding_tickers = self.request_funding_tickers(&["ALL"]).await?;
+        let trading_tickers = self.request_trading_tickers(&["ALL"]).await?;
+        let usd_price = |ccy: &str| -> Option<f64> {
+            if ccy == "USD" {
+                return Some(1.0);
+            }
+            trading_tickers
+                .iter()
+                .find(|(s, _)| *s == format!("t{ccy}USD") || *s == format!("t{ccy}:USD"))
+                .map(|(_, t)| t.last_price)
+        };
+
+        let mut markets = Vec::new();
+        for batch in funding_tickers.chunks(BATCH_SIZE) {
+            let stats = join_all(batch.iter().map(|(symbol, _)| {
+                self.request_funding_stats(symbol, Some(HISTORY_LEN), None, None)
+            }))
+            .await;
+
+            for ((symbol, ticker), stats) in batch.iter().zip(stats) {
+                let (stats, stats_error) = match stats {
+                    Ok(stats) => (stats, None),
+                    Err(e) => {
+                        tracing::warn!(symbol, error = ?e, "failed to fetch funding stats");
+                        (Vec::new(), Some(format!("{e:?}")))
+                    }
+                };
+                let depth = stats
+                    .iter()
+                    .max_by_key(|s| s.time)
+                    .map(|s| s.funding_amount);
+                let volatility = {
+                    let rates: Vec<f64> = stats.iter().map(|s| s.frr).collect();
+                    let n = rates.len() as f64;
+                    let mean = rates.iter().sum::<f64>() / n;
+                    if rates.is_empty() || mean == 0.0 {
+                        None
+                    } else {
+                        let var = rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+                        Some(var.sqrt() / mean)
+                    }
+                };
+                let price = usd_price(parse_ccy_from_symbol(symbol));
+                markets.push(FundingMarket {
+                    symbol: symbol.clone(),
+                    frr: ticker.frr,
+                    last_rate: ticker.last_price,
+                    volume: ticker.volume,
+                    volume_usd: price.map(|p| p * ticker.volume),
+                    depth,
+                    depth_usd: price.zip(depth).map(|(p, d)| p * d),
+                    volatility,
+                    stats_error,
+                });
+            }
+        }
+        Ok(markets)
+    }
+
     // --- Authenticated Endpoints --- //
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-funding-credits>
     pub async fn request_funding_credits(
         &self,
         symbol: &str,
     ) -> Result<Vec<FundingCredit>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         let url = format!("auth/r/funding/credits/{symbol}");
         let body = self.post_url(&url).await?;
-        let orders: Vec<FundingCredit> = from_str(&body).unwrap();
+        let orders: Vec<FundingCredit> = parse_vec(&body)?;
         Ok(orders)
     }
 
+    /// Yield and duration of the user's funding, useful to evaluate the
+    /// performance of the lending portfolio.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-info-funding>
+    pub async fn request_funding_info(&self, symbol: &str) -> Result<FundingInfo, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = format!("auth/r/info/funding/{symbol}");
+        let body = self.post_url(&url).await?;
+        let info: FundingInfo = parse_one(&body)?;
+        Ok(info)
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-funding-credits-hist>
     pub async fn request_funding_credits_hist(
         &self,
         symbol: &str,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<FundingCredit>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         let url = format!("auth/r/funding/credits/{symbol}/hist");
         let mut params = Vec::<(&str, String)>::new();
         if let Some(limit) = limit {
@@ -508,7 +930,7 @@ impl Client {
             params.push(("end", (end.timestamp_millis()).to_string()));
         }
         let body = self.post_with_params(&url, params).await?;
-        let credits: Vec<FundingCredit> = from_str(&body).unwrap();
+        let credits: Vec<FundingCredit> = parse_vec(&body)?;
         Ok(credits)
     }
 
@@ -517,9 +939,10 @@ impl Client {
         &self,
         symbol: &str,
     ) -> Result<Vec<FundingOffer>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         let url = format!("auth/r/funding/offers/{symbol}");
         let body = self.post_url(&url).await?;
-        let orders: Vec<FundingOffer> = from_str(&body).unwrap();
+        let orders: Vec<FundingOffer> = parse_vec(&body)?;
         Ok(orders)
     }
 
@@ -528,9 +951,10 @@ impl Client {
         &self,
         symbol: &str,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<FundingOffer>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         let url = format!("auth/r/funding/offers/{symbol}/hist");
         let mut params = Vec::<(&str, String)>::new();
         if let Some(limit) = limit {
@@ -544,7 +968,7 @@ impl Client {
             params.push(("end", (end.timestamp_millis()).to_string()));
         }
         let body = self.post_with_params(&url, params).await?;
-        let offers: Vec<FundingOffer> = from_str(&body).unwrap();
+        let offers: Vec<FundingOffer> = parse_vec(&body)?;
         Ok(offers)
     }
 
@@ -554,13 +978,16 @@ impl Client {
         symbol: &str,
         amount: f64,
         rate: f64,
-        period: u8,
+        period: FundingPeriod,
         order_type: FundingOrderType,
     ) -> Result<FundingOffer, BitfinexError> {
-        assert!(
-            (2..=120).contains(&period),
-            "Out of available period range: {period}"
-        );
+        self.check_symbol(symbol).await?;
+        if let Some(headroom) = self.balance_check() {
+            let ccy = parse_ccy_from_symbol(symbol);
+            self.ensure_wallet_balance("funding", ccy, amount * (1.0 + headroom))
+                .await?;
+        }
+
         let url = String::from("auth/w/funding/offer/submit");
         let payload = json!({
             "symbol": symbol,
@@ -571,7 +998,7 @@ impl Client {
         });
 
         let body = self.post_with_payload(&url, payload.to_string()).await?;
-        let resp: FundingOfferResult = from_str(&body).unwrap();
+        let resp: FundingOfferResult = parse_notification(&body)?;
         Ok(resp.offer)
     }
 
@@ -580,15 +1007,61 @@ impl Client {
         let url = String::from("auth/w/funding/offer/cancel");
         let payload = json!({"id": offer_id}).to_string();
         let body = self.post_with_payload(&url, payload).await?;
-        let resp: FundingOfferResult = from_str(&body).unwrap();
+        let resp: FundingOfferResult = parse_notification(&body)?;
         Ok(resp.offer)
     }
 
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-cancel-all-funding-offers>
-    pub async fn cancel_funding_offer_all(&self, symbol: &str) {
+    pub async fn cancel_funding_offer_all(
+        &self,
+        symbol: &str,
+    ) -> Result<FundingOfferCancelAllResult, BitfinexError> {
         let url = String::from("auth/w/funding/offer/cancel/all");
         let ccy = parse_ccy_from_symbol(symbol);
         let payload = json!({"currency": ccy}).to_string();
-        let _ = self.post_with_payload(&url, payload).await;
+        let body = self.post_with_payload(&url, payload).await?;
+        parse_notification(&body)
+    }
+
+    /// Return borrowed funding, or funding used in a position, before it
+    /// expires.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-funding-close>
+    pub async fn close_funding(&self, id: u64) -> Result<FundingNotification, BitfinexError> {
+        let url = String::from("auth/w/funding/close");
+        let payload = json!({"id": id}).to_string();
+        let body = self.post_with_payload(&url, payload).await?;
+        let result: FundingNotification = parse_notification(&body)?;
+        Ok(result)
+    }
+
+    /// Keep the given credits or loans open at expiry instead of returning
+    /// them.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-keep-funding>
+    pub async fn keep_funding(
+        &self,
+        typ: FundingKeepType,
+        ids: &[u64],
+    ) -> Result<FundingNotification, BitfinexError> {
+        if ids.is_empty() {
+            return Err(BitfinexError::InvalidArgument(String::from(
+                "At least one funding ID is required",
+            )));
+        }
+        let url = String::from("auth/w/funding/keep");
+        let changes: serde_json::Map<String, Value> = ids
+            .iter()
+            .map(|id| (id.to_string(), Value::from(1)))
+            .collect();
+        let payload = json!({
+            "type": typ.to_string(),
+            "id": ids[0],
+            "changes": changes,
+        });
+
+        let body = self.post_with_payload(&url, payload.to_string()).await?;
+        let result: FundingNotification = parse_notification(&body)?;
+        Ok(result)
     }
 }
@@ -20,11 +20,21 @@
 //!
 //! ## Feature flags
 //! - `cli` - Only used when you want to build and run as CLI.
+//! - `ws` - WebSocket client for real-time public and account channels.
+//! - `self-update` - `bfx self-update` command replacing the CLI binary with the latest release.
 // #[cfg(feature = "cli")]
 pub mod cli;
 pub mod client;
 mod deserializer;
 mod error;
 mod funding;
+mod pay;
+mod pulse;
 mod trading;
+pub mod report;
+pub mod risk;
 pub mod utils;
+pub mod ws;
+
+pub use error::BitfinexError;
+pub use trading::{OrderMeta, OrderOp, OrderOpResult, TradingOrder, TradingOrderType};
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    client::Client,
+    deserializer::{parse_one, parse_vec},
+    error::BitfinexError,
+    utils::Mts,
+};
+
+// --- Data Models --- //
+/// Deposit address of one pay currency of an invoice.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PayInvoiceAddress {
+    pub amount: f64,
+    pub currency: String,
+    pub pay_currency: String,
+    pub pool_currency: String,
+    pub address: String,
+    pub ext: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PayInvoice {
+    pub id: String,
+    #[serde(rename = "t")]
+    pub created: Mts,
+    #[serde(rename = "type")]
+    pub typ: String, // ECOMMERCE, POS
+    /// Seconds the invoice stays open.
+    pub duration: u64,
+    pub amount: f64,
+    pub currency: String,
+    pub order_id: String,
+    pub pay_currencies: Vec<String>,
+    pub webhook: Option<String>,
+    pub redirect_url: Option<String>,
+    pub status: String, // CREATED, PENDING, COMPLETED, EXPIRED
+    pub customer_info: Option<Value>,
+    #[serde(default)]
+    pub invoices: Vec<PayInvoiceAddress>,
+    pub payment: Option<Value>,
+    #[serde(default)]
+    pub additional_payments: Vec<Value>,
+    pub merchant_name: Option<String>,
+}
+
+// --- Pay APIs --- //
+impl Client {
+    /// Create a Bitfinex Pay invoice.
+    ///
+    /// ## Parameters:
+    /// - `pay_currencies`: currencies the customer can pay with (e.g. BTC, ETH, UST-ETH, LNX).
+    /// - `duration`: seconds the invoice stays open, between 300 and 86400.
+    /// - `customer_info`: customer details required by Bitfinex, e.g.
+    ///   `{"nationality": "DE", "residCountry": "DE", "fullName": "...", "email": "...", ...}`.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-invoice-create>
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_pay_invoice(
+        &self,
+        amount: f64,
+        currency: &str,
+        pay_currencies: &[&str],
+        order_id: &str,
+        duration: Option<u32>,
+        webhook: Option<&str>,
+        redirect_url: Option<&str>,
+        customer_info: Value,
+    ) -> Result<PayInvoice, BitfinexError> {
+        let url = String::from("auth/w/ext/pay/invoice/create");
+
+        let mut data = json!({
+            "amount": amount.to_string(),
+            "currency": currency,
+            "payCurrencies": pay_currencies,
+            "orderId": order_id,
+            "customerInfo": customer_info,
+        });
+        if let Some(duration) = duration {
+            data["duration"] = Value::from(duration);
+        }
+        if let Some(webhook) = webhook {
+            data["webhook"] = Value::from(webhook);
+        }
+        if let Some(redirect_url) = redirect_url {
+            data["redirectUrl"] = Value::from(redirect_url);
+        }
+
+        let body = self.post_with_payload(&url, data.to_string()).await?;
+        let invoice: PayInvoice = parse_one(&body)?;
+        Ok(invoice)
+    }
+
+    /// Get Bitfinex Pay invoices, or a single one by `id`.
+    ///
+    /// ## Parameters:
+    /// - `limit` is up to 100
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-invoices>
+    pub async fn request_pay_invoices(
+        &self,
+        id: Option<&str>,
+        limit: Option<u16>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PayInvoice>, BitfinexError> {
+        let url = String::from("auth/r/ext/pay/invoices");
+
+        let mut data = json!({});
+        if let Some(id) = id {
+            data["id"] = Value::from(id);
+        }
+        if let Some(limit) = limit {
+            // max 100
+            data["limit"] = Value::from(limit);
+        }
+        if let Some(start) = start {
+            data["start"] = Value::from(start.timestamp_millis());
+        }
+        if let Some(end) = end {
+            data["end"] = Value::from(end.timestamp_millis());
+        }
+
+        let body = self.post_with_payload(&url, data.to_string()).await?;
+        let invoices: Vec<PayInvoice> = parse_vec(&body)?;
+        Ok(invoices)
+    }
+
+    /// Manually complete an invoice paid by a deposit that was not matched,
+    /// e.g. with a wrong amount.
+    ///
+    /// ## Parameters:
+    /// - `pay_ccy`: currency the invoice was paid with.
+    /// - `deposit_id` or `ledger_id`: the deposit or its ledger entry.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-invoice-complete>
+    pub async fn complete_pay_invoice(
+        &self,
+        id: &str,
+        pay_ccy: &str,
+        deposit_id: Option<u64>,
+        ledger_id: Option<u64>,
+    ) -> Result<PayInvoice, BitfinexError> {
+        let url = String::from("auth/w/ext/pay/invoice/complete");
+
+        let mut data = json!({"id": id, "payCcy": pay_ccy});
+        if let Some(deposit_id) = deposit_id {
+            data["depositId"] = Value::from(deposit_id);
+        }
+        if let Some(ledger_id) = ledger_id {
+            data["ledgerId"] = Value::from(ledger_id);
+        }
+
+        let body = self.post_with_payload(&url, data.to_string()).await?;
+        let invoice: PayInvoice = parse_one(&body)?;
+        Ok(invoice)
+    }
+
+    /// Expire an open invoice, so it can no longer be paid.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-invoice-expire>
+    pub async fn expire_pay_invoice(&self, id: &str) -> Result<PayInvoice, BitfinexError> {
+        let url = String::from("auth/w/ext/pay/invoice/expire");
+        let payload = json!({"id": id}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        let invoice: PayInvoice = parse_one(&body)?;
+        Ok(invoice)
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    client::Client,
+    deserializer::{parse_one, parse_vec},
+    error::BitfinexError,
+    utils::Mts,
+};
+
+// --- Data Models --- //
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PulseMessage {
+    pub id: String,
+    pub created: Mts,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub user_id: String,
+
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+
+    pub title: Option<String>,
+    pub content: String,
+
+    #[serde(skip_serializing)]
+    _placeholder_3: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_4: Option<String>,
+
+    pub is_pin: Option<u8>,
+    pub is_public: Option<u8>,
+    pub comments_disabled: Option<u8>,
+    pub tags: Option<Vec<String>>,
+    pub attachments: Option<Vec<String>>,
+    pub meta: Option<Value>,
+    pub likes: Option<u64>,
+
+    #[serde(skip_serializing, default)]
+    _placeholder_5: Option<String>,
+    #[serde(skip_serializing, default)]
+    _placeholder_6: Option<String>,
+
+    /// Profile of the author, only included in the public history.
+    #[serde(default)]
+    pub profile: Option<Value>,
+    #[serde(default)]
+    pub comments: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PulseProfile {
+    pub id: String,
+    pub created: Mts,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub nickname: String,
+
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+
+    pub picture: Option<String>,
+    pub text: Option<String>,
+
+    #[serde(skip_serializing)]
+    _placeholder_3: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_4: Option<String>,
+
+    pub twitter_handle: Option<String>,
+
+    #[serde(skip_serializing)]
+    _placeholder_5: Option<String>,
+
+    pub followers: Option<u64>,
+    pub following: Option<u64>,
+
+    #[serde(skip_serializing, default)]
+    _placeholder_6: Option<String>,
+    #[serde(skip_serializing, default)]
+    _placeholder_7: Option<String>,
+    #[serde(skip_serializing, default)]
+    _placeholder_8: Option<String>,
+
+    #[serde(default)]
+    pub tipping_status: Option<u8>,
+}
+
+// --- Pulse APIs --- //
+impl Client {
+    /// Latest public Pulse messages.
+    ///
+    /// ## Parameters:
+    /// - `limit` is up to 100
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-pulse-hist>
+    pub async fn request_pulse_hist(
+        &self,
+        limit: Option<u16>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<PulseMessage>, BitfinexError> {
+        let mut url = String::from("pulse/hist?");
+        if let Some(limit) = limit {
+            // max 100
+            url = format!("{url}&limit={limit}");
+        }
+        if let Some(end) = end {
+            url = format!("{url}&end={}", end.timestamp_millis());
+        }
+
+        let body = self.get(&url).await?;
+        let messages: Vec<PulseMessage> = parse_vec(&body)?;
+        Ok(messages)
+    }
+
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-pulse-profile-details>
+    pub async fn request_pulse_profile(
+        &self,
+        nickname: &str,
+    ) -> Result<PulseProfile, BitfinexError> {
+        let url = format!("pulse/profile/{nickname}");
+        let body = self.get(&url).await?;
+        let profile: PulseProfile = parse_one(&body)?;
+        Ok(profile)
+    }
+
+    /// Post a Pulse message, or a comment when `parent` is given.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-pulse-add>
+    pub async fn add_pulse(
+        &self,
+        title: &str,
+        content: &str,
+        parent: Option<&str>,
+        is_public: bool,
+        is_pin: bool,
+    ) -> Result<PulseMessage, BitfinexError> {
+        let url = String::from("auth/w/pulse/add");
+
+        let mut data = json!({
+            "title": title,
+            "content": content,
+            "isPublic": u8::from(is_public),
+            "isPin": u8::from(is_pin),
+        });
+        if let Some(parent) = parent {
+            data["parent"] = Value::from(parent);
+        }
+
+        let body = self.post_with_payload(&url, data.to_string()).await?;
+        let message: PulseMessage = parse_one(&body)?;
+        Ok(message)
+    }
+
+    /// Delete one of the user's Pulse messages.
+    ///
+    /// Returns whether the message was deleted.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-pulse-del>
+    pub async fn delete_pulse(&self, id: &str) -> Result<bool, BitfinexError> {
+        let url = String::from("auth/w/pulse/del");
+        let payload = json!({"pid": id}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        // [STATUS]
+        let [status]: [u8; 1] = parse_one(&body)?;
+        Ok(status == 1)
+    }
+}
@@ -0,0 +1,784 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::{
+    client::{AccountSummary, Ledger, Wallet},
+    funding::{Candle, CandleTimeFrame, FundingCredit, FundingOffer},
+    trading::{TradingOrder, TradingTicker, UserTrade},
+    utils::{Mts, parse_ccy_from_symbol},
+};
+
+/// Tag of trades whose order has no strategy label.
+pub const UNTAGGED: &str = "untagged";
+
+/// Bitfinex trading fee schedule by 30-day USD volume, fees in percent.
+///
+/// Ref: <https://www.bitfinex.com/fees>
+pub const FEE_TIERS: [FeeTier; 11] = [
+    FeeTier {
+        min_volume_usd: 0.0,
+        maker_fee: 0.10,
+        taker_fee: 0.20,
+    },
+    FeeTier {
+        min_volume_usd: 500_000.0,
+        maker_fee: 0.08,
+        taker_fee: 0.20,
+    },
+    FeeTier {
+        min_volume_usd: 1_000_000.0,
+        maker_fee: 0.06,
+        taker_fee: 0.20,
+    },
+    FeeTier {
+        min_volume_usd: 2_500_000.0,
+        maker_fee: 0.04,
+        taker_fee: 0.20,
+    },
+    FeeTier {
+        min_volume_usd: 5_000_000.0,
+        maker_fee: 0.02,
+        taker_fee: 0.20,
+    },
+    FeeTier {
+        min_volume_usd: 7_500_000.0,
+        maker_fee: 0.0,
+        taker_fee: 0.20,
+    },
+    FeeTier {
+        min_volume_usd: 10_000_000.0,
+        maker_fee: 0.0,
+        taker_fee: 0.18,
+    },
+    FeeTier {
+        min_volume_usd: 15_000_000.0,
+        maker_fee: 0.0,
+        taker_fee: 0.16,
+    },
+    FeeTier {
+        min_volume_usd: 20_000_000.0,
+        maker_fee: 0.0,
+        taker_fee: 0.14,
+    },
+    FeeTier {
+        min_volume_usd: 25_000_000.0,
+        maker_fee: 0.0,
+        taker_fee: 0.12,
+    },
+    FeeTier {
+        min_volume_usd: 30_000_000.0,
+        maker_fee: 0.0,
+        taker_fee: 0.10,
+    },
+];
+
+// --- Data Models --- //
+#[derive(Clone, Copy, Serialize)]
+pub struct FeeTier {
+    pub min_volume_usd: f64,
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+}
+
+/// Current fees of the account and the distance to the next fee tier.
+#[derive(Serialize)]
+pub struct FeeTierProgress {
+    /// Current fees in percent, as reported for the account.
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+    pub volume_30d_usd: f64,
+    pub next_tier: Option<FeeTier>,
+    /// 30-day volume still needed to reach the next tier.
+    pub volume_needed_usd: Option<f64>,
+}
+
+/// Realized result of one strategy on one pair, valued in the quote currency.
+#[derive(Serialize)]
+pub struct TagPerformance {
+    pub tag: String,
+    pub symbol: String,
+    pub trades: usize,
+    pub volume: f64,
+    /// Realized PnL before fees, using the average cost of the open position.
+    pub realized_pnl: f64,
+    /// Paid fees, negative as reported by Bitfinex.
+    pub fees: f64,
+}
+
+/// Range of missing candles, both ends are open times of missing candles.
+#[derive(Serialize)]
+pub struct CandleGap {
+    pub from: Mts,
+    pub to: Mts,
+    pub missing: usize,
+}
+
+/// Result of [`verify_candles`].
+#[derive(Serialize)]
+pub struct CandleIntegrity {
+    pub count: usize,
+    pub gaps: Vec<CandleGap>,
+    /// Open times seen more than once.
+    pub duplicates: Vec<Mts>,
+    /// Open times earlier than the candle before them.
+    pub non_monotonic: Vec<Mts>,
+}
+
+impl CandleIntegrity {
+    pub fn is_ok(&self) -> bool {
+        self.gaps.is_empty() && self.duplicates.is_empty() && self.non_monotonic.is_empty()
+    }
+}
+
+/// Account equity at the end of a day (UTC), see [`equity_curve`].
+#[derive(Serialize)]
+pub struct EquityPoint {
+    pub date: NaiveDate,
+    /// Value of all wallets in the quote currency.
+    pub equity: f64,
+    /// Currencies with a balance but no price on that day, left out of
+    /// `equity`.
+    pub unpriced: Vec<String>,
+    /// Staking rewards received that day in the quote currency, included in
+    /// `equity`. See [`Ledger::is_staking_payment`].
+    pub staking_rewards: f64,
+}
+
+/// Outcome of past funding offers, see [`offer_stats`].
+#[derive(Serialize)]
+pub struct OfferStats {
+    pub offers: usize,
+    pub filled: usize,
+    pub partial: usize,
+    /// Offers closed without any fill.
+    pub cancelled: usize,
+    pub offered_amount: f64,
+    pub filled_amount: f64,
+    /// Average seconds from creation to the last fill of fully filled offers.
+    pub avg_fill_secs: Option<f64>,
+    /// Average rate of offers with any fill.
+    pub filled_avg_rate: Option<f64>,
+    /// Average rate of offers closed without any fill.
+    pub cancelled_avg_rate: Option<f64>,
+}
+
+impl OfferStats {
+    /// Share of offers that were fully filled.
+    pub fn fill_rate(&self) -> f64 {
+        if self.offers == 0 {
+            0.0
+        } else {
+            self.filled as f64 / self.offers as f64
+        }
+    }
+}
+
+/// Execution price against the order price for one pair and order type, see
+/// [`slippage_by_symbol`].
+#[derive(Serialize)]
+pub struct Slippage {
+    pub symbol: String,
+    pub order_type: String,
+    pub trades: usize,
+    /// Traded volume in the quote currency.
+    pub volume: f64,
+    /// Volume-weighted slippage as a fraction of the order price, positive
+    /// when the execution was worse than the order price.
+    pub avg_slippage: f64,
+    /// Worst slippage of a single trade.
+    pub max_slippage: f64,
+}
+
+/// Expected interest of the active funding credits of one currency, see
+/// [`interest_forecast`].
+#[derive(Serialize)]
+pub struct InterestForecast {
+    pub symbol: String,
+    pub credits: usize,
+    pub amount: f64,
+    /// Amount-weighted average daily rate.
+    pub avg_rate: f64,
+    /// Interest of the next day, week and 30 days before the lending fee,
+    /// negative when borrowing.
+    pub next_1d: f64,
+    pub next_7d: f64,
+    pub next_30d: f64,
+}
+
+/// One conversion of a [`TriangleRoute`].
+#[derive(Serialize)]
+pub struct TriangleLeg {
+    pub from: String,
+    pub to: String,
+    pub symbol: String,
+    /// Units of `to` received per unit of `from`, after the taker fee.
+    pub rate: f64,
+}
+
+/// Round trip through three currencies, see [`triangle_routes`].
+#[derive(Serialize)]
+pub struct TriangleRoute {
+    /// e.g. `BTC > USD > UST > BTC`
+    pub route: String,
+    pub legs: Vec<TriangleLeg>,
+    /// Round trip at mid prices without fees, in percent. Far from 0 when the
+    /// cross rates are inconsistent.
+    pub mid_gap: f64,
+    /// Round trip trading at the bid and ask with taker fees, in percent.
+    /// Positive for an arbitrage.
+    pub net_return: f64,
+}
+
+#[derive(Default)]
+struct TagState {
+    trades: usize,
+    volume: f64,
+    position: f64,
+    avg_price: f64,
+    realized_pnl: f64,
+    fees: f64,
+}
+
+/// Positions smaller than this are treated as closed, absorbing the float
+/// error of summing trade amounts.
+const POSITION_EPSILON: f64 = 1e-9;
+
+impl TagState {
+    fn apply(&mut self, amount: f64, price: f64) {
+        if self.position.abs() < POSITION_EPSILON || self.position.signum() == amount.signum() {
+            let size = self.position.abs() + amount.abs();
+            self.avg_price = (self.avg_price * self.position.abs() + price * amount.abs()) / size;
+            self.position += amount;
+            return;
+        }
+
+        let closed = amount.abs().min(self.position.abs());
+        self.realized_pnl += (price - self.avg_price) * closed * self.position.signum();
+        self.position += amount;
+        if self.position.abs() < POSITION_EPSILON {
+            self.position = 0.0;
+            self.avg_price = 0.0;
+        } else if self.position.signum() == amount.signum() {
+            // The trade flipped the position, the remainder opens at this price.
+            self.avg_price = price;
+        }
+    }
+}
+
+// --- Report Functions --- //
+/// Group realized PnL and fees of `trades` by the strategy tag of their order
+/// (see [`TradingOrder::tag`]) and pair.
+///
+/// Trades of orders missing from `orders` are reported as [`UNTAGGED`].
+pub fn attribute_by_tag(orders: &[TradingOrder], trades: &[UserTrade]) -> Vec<TagPerformance> {
+    let tags: HashMap<u64, &str> = orders
+        .iter()
+        .filter_map(|o| Some((o.id, o.tag()?)))
+        .collect();
+
+    let mut sorted: Vec<&UserTrade> = trades.iter().collect();
+    sorted.sort_by_key(|t| (t.time, t.id));
+
+    let mut states: HashMap<(&str, &str), TagState> = HashMap::new();
+    for t in sorted {
+        let tag = tags.get(&t.order_id).copied().unwrap_or(UNTAGGED);
+        let state = states.entry((tag, t.symbol.as_str())).or_default();
+        state.trades += 1;
+        state.volume += (t.amount * t.price).abs();
+        state.fees += match t.fee_ccy == parse_ccy_from_symbol(&t.symbol) {
+            true => t.fee,
+            // Fee charged in the base currency
+            false => t.fee * t.price,
+        };
+        state.apply(t.amount, t.price);
+    }
+
+    let mut result: Vec<TagPerformance> = states
+        .into_iter()
+        .map(|((tag, symbol), state)| TagPerformance {
+            tag: tag.to_string(),
+            symbol: symbol.to_string(),
+            trades: state.trades,
+            volume: state.volume,
+            realized_pnl: state.realized_pnl,
+            fees: state.fees,
+        })
+        .collect();
+    result.sort_by(|a, b| a.tag.cmp(&b.tag).then(a.symbol.cmp(&b.symbol)));
+    result
+}
+
+/// Locate the account in [`FEE_TIERS`] by its 30-day volume.
+///
+/// The current fees are taken from the summary, as they also include LEO and
+/// other discounts.
+pub fn fee_tier_progress(summary: &AccountSummary) -> FeeTierProgress {
+    let volume = summary.total_volume_30d_usd;
+    let next_tier = FEE_TIERS
+        .iter()
+        .find(|t| t.min_volume_usd > volume)
+        .copied();
+    FeeTierProgress {
+        maker_fee: summary.maker_fee * 100.0,
+        taker_fee: summary.taker_fee_crypto * 100.0,
+        volume_30d_usd: volume,
+        next_tier,
+        volume_needed_usd: next_tier.map(|t| t.min_volume_usd - volume),
+    }
+}
+
+/// Check candles, expected from the oldest, for gaps, duplicates and
+/// timestamps going backwards.
+///
+/// Gaps are not checked for [`CandleTimeFrame::Month1`]. Note that Bitfinex
+/// does not return candles of intervals without trades, so gaps on illiquid
+/// pairs are expected.
+pub fn verify_candles(candles: &[Candle], time_frame: CandleTimeFrame) -> CandleIntegrity {
+    let mut duplicates = Vec::new();
+    let mut non_monotonic = Vec::new();
+    let mut seen = HashSet::new();
+    for (idx, c) in candles.iter().enumerate() {
+        if !seen.insert(c.time) {
+            duplicates.push(c.time);
+        } else if idx > 0 && c.time < candles[idx - 1].time {
+            non_monotonic.push(c.time);
+        }
+    }
+
+    let mut gaps = Vec::new();
+    if let Some(interval) = time_frame.duration() {
+        let interval_ms = interval.as_millis() as i64;
+        let mut times: Vec<i64> = seen.iter().map(|t| t.timestamp_millis()).collect();
+        times.sort_unstable();
+        for pair in times.windows(2) {
+            let missing = (pair[1] - pair[0]) / interval_ms - 1;
+            if missing <= 0 {
+                continue;
+            }
+            let (Some(from), Some(to)) = (
+                Mts::from_millis(pair[0] + interval_ms),
+                Mts::from_millis(pair[1] - interval_ms),
+            ) else {
+                continue;
+            };
+            gaps.push(CandleGap {
+                from,
+                to,
+                missing: missing as usize,
+            });
+        }
+    }
+
+    CandleIntegrity {
+        count: candles.len(),
+        gaps,
+        duplicates,
+        non_monotonic,
+    }
+}
+
+/// Summary helpers over a series of candles, in any order.
+pub trait CandleSeries {
+    /// Percent change from the open of the oldest candle to the close of the
+    /// newest. `None` if empty or the open is 0.
+    fn pct_change(&self) -> Option<f64>;
+
+    /// Highest high and lowest low. `None` if empty.
+    fn high_low(&self) -> Option<(f64, f64)>;
+
+    /// Average volume per candle. `None` if empty.
+    fn avg_volume(&self) -> Option<f64>;
+}
+
+impl CandleSeries for [Candle] {
+    fn pct_change(&self) -> Option<f64> {
+        let first = self.iter().min_by_key(|c| c.time)?;
+        let last = self.iter().max_by_key(|c| c.time)?;
+        if first.open == 0.0 {
+            return None;
+        }
+        Some((last.close - first.open) / first.open * 100.0)
+    }
+
+    fn high_low(&self) -> Option<(f64, f64)> {
+        self.iter().fold(None, |acc, c| match acc {
+            None => Some((c.high, c.low)),
+            Some((high, low)) => Some((high.max(c.high), low.min(c.low))),
+        })
+    }
+
+    fn avg_volume(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.iter().map(|c| c.volume).sum::<f64>() / self.len() as f64)
+    }
+}
+
+/// Daily account equity from `start` to `end`, valued in `quote`.
+///
+/// Balances of each currency and wallet are replayed from the `ledgers`.
+/// Before the oldest entry the balance preceding it is used, and wallets
+/// without entries keep their current balance from `wallets`. `prices` maps
+/// currencies to daily candles against `quote`, e.g. from
+/// [`Client::request_trading_candles`](crate::client::Client::request_trading_candles);
+/// the close of the latest candle of the day is used. Staked balances are part
+/// of the wallet balances, and staking rewards are also reported per day.
+pub fn equity_curve(
+    wallets: &[Wallet],
+    ledgers: &[Ledger],
+    prices: &HashMap<String, Vec<Candle>>,
+    quote: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<EquityPoint> {
+    let mut history: HashMap<(&str, &str), Vec<&Ledger>> = HashMap::new();
+    for l in ledgers {
+        history.entry((&l.ccy, &l.wallet)).or_default().push(l);
+    }
+    for entries in history.values_mut() {
+        entries.sort_by_key(|l| (l.time, l.id));
+    }
+    let mut current: HashMap<(&str, &str), f64> = HashMap::new();
+    for w in wallets {
+        history.entry((&w.ccy, &w.typ)).or_default();
+        current.insert((&w.ccy, &w.typ), w.balance);
+    }
+
+    let price_at = |ccy: &str, day_end: DateTime<Utc>| {
+        if ccy == quote {
+            return Some(1.0);
+        }
+        prices.get(ccy).and_then(|candles| {
+            candles
+                .iter()
+                .filter(|c| *c.time <= day_end)
+                .max_by_key(|c| c.time)
+                .map(|c| c.close)
+        })
+    };
+
+    let mut points = Vec::new();
+    let mut date = start.date_naive();
+    while date <= end.date_naive() {
+        let Some(day_end) = date.and_hms_milli_opt(23, 59, 59, 999) else {
+            break;
+        };
+        let day_end = day_end.and_utc();
+
+        let staking_rewards = ledgers
+            .iter()
+            .filter(|l| l.time.date_naive() == date && l.is_staking_payment())
+            .filter_map(|l| Some(l.amount * price_at(&l.ccy, day_end)?))
+            .sum();
+
+        let mut balances: HashMap<&str, f64> = HashMap::new();
+        for (key, entries) in &history {
+            let balance = match entries.iter().rev().find(|l| *l.time <= day_end) {
+                Some(l) => l.balance,
+                None => match entries.first() {
+                    Some(first) => first.balance - first.amount,
+                    None => current.get(key).copied().unwrap_or_default(),
+                },
+            };
+            *balances.entry(key.0).or_default() += balance;
+        }
+
+        let mut equity = 0.0;
+        let mut unpriced = Vec::new();
+        for (ccy, balance) in balances {
+            if balance.abs() < 1e-9 {
+                continue;
+            }
+            match price_at(ccy, day_end) {
+                Some(price) => equity += balance * price,
+                None => unpriced.push(ccy.to_string()),
+            }
+        }
+        unpriced.sort();
+        points.push(EquityPoint {
+            date,
+            equity,
+            unpriced,
+            staking_rewards,
+        });
+
+        let Some(next) = date.succ_opt() else {
+            break;
+        };
+        date = next;
+    }
+    points
+}
+
+/// Summarize the outcome of past funding offers, e.g. from
+/// [`Client::request_funding_offers_hist`](crate::client::Client::request_funding_offers_hist).
+///
+/// Offers are classified by their remaining amount rather than the status
+/// text. A high share of cancelled offers with rates above the filled ones
+/// suggests the pricing is too aggressive.
+pub fn offer_stats(offers: &[FundingOffer]) -> OfferStats {
+    let mut stats = OfferStats {
+        offers: offers.len(),
+        filled: 0,
+        partial: 0,
+        cancelled: 0,
+        offered_amount: 0.0,
+        filled_amount: 0.0,
+        avg_fill_secs: None,
+        filled_avg_rate: None,
+        cancelled_avg_rate: None,
+    };
+    let mut fill_secs = Vec::new();
+    let mut filled_rates = Vec::new();
+    let mut cancelled_rates = Vec::new();
+    for o in offers {
+        let filled = o.amount_ori.abs() - o.amount.abs();
+        stats.offered_amount += o.amount_ori.abs();
+        stats.filled_amount += filled.max(0.0);
+        if o.amount.abs() < f64::EPSILON {
+            stats.filled += 1;
+            fill_secs.push((*o.updated - *o.created).num_milliseconds() as f64 / 1000.0);
+            filled_rates.push(o.rate);
+        } else if filled > 0.0 {
+            stats.partial += 1;
+            filled_rates.push(o.rate);
+        } else {
+            stats.cancelled += 1;
+            cancelled_rates.push(o.rate);
+        }
+    }
+
+    let mean = |values: &[f64]| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    stats.avg_fill_secs = mean(&fill_secs);
+    stats.filled_avg_rate = mean(&filled_rates);
+    stats.cancelled_avg_rate = mean(&cancelled_rates);
+    stats
+}
+
+/// Group the slippage of `trades` against the price of their order by pair and
+/// order type.
+///
+/// Trades without an order price, e.g. of market orders, are skipped.
+pub fn slippage_by_symbol(trades: &[UserTrade]) -> Vec<Slippage> {
+    let mut groups: HashMap<(&str, &str), Slippage> = HashMap::new();
+    for t in trades {
+        let Some(order_price) = t.order_price.filter(|p| *p > 0.0) else {
+            continue;
+        };
+        let order_type = t.order_type.as_deref().unwrap_or("-");
+        // Buying above or selling below the order price is a loss.
+        let slippage = (t.price - order_price) / order_price * t.amount.signum();
+        let volume = (t.amount * t.price).abs();
+
+        let entry = groups
+            .entry((t.symbol.as_str(), order_type))
+            .or_insert_with(|| Slippage {
+                symbol: t.symbol.clone(),
+                order_type: order_type.to_string(),
+                trades: 0,
+                volume: 0.0,
+                avg_slippage: 0.0,
+                max_slippage: f64::MIN,
+            });
+        entry.trades += 1;
+        // Accumulate the weighted sum, divided by the volume below.
+        entry.avg_slippage += slippage * volume;
+        entry.volume += volume;
+        entry.max_slippage = entry.max_slippage.max(slippage);
+    }
+
+    let mut result: Vec<Slippage> = groups
+        .into_values()
+        .map(|mut s| {
+            if s.volume > 0.0 {
+                s.avg_slippage /= s.volume;
+            }
+            s
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        a.symbol
+            .cmp(&b.symbol)
+            .then(a.order_type.cmp(&b.order_type))
+    });
+    result
+}
+
+/// Project the interest of active `credits` over the next 1, 7 and 30 days
+/// from `now`, grouped by funding currency.
+///
+/// Each credit is assumed to be held until the end of its period at its
+/// current rate, so variable-rate credits follow the FRR of today.
+pub fn interest_forecast(credits: &[FundingCredit], now: DateTime<Utc>) -> Vec<InterestForecast> {
+    let mut groups: HashMap<&str, InterestForecast> = HashMap::new();
+    for c in credits {
+        let maturity = *c.opened + chrono::Duration::days(c.period.into());
+        let days_left = ((maturity - now).num_seconds() as f64 / 86400.0).max(0.0);
+        let sign = if c.side < 0 { -1.0 } else { 1.0 };
+        let daily = c.amount.abs() * c.rate * sign;
+
+        let entry = groups
+            .entry(c.symbol.as_str())
+            .or_insert_with(|| InterestForecast {
+                symbol: c.symbol.clone(),
+                credits: 0,
+                amount: 0.0,
+                avg_rate: 0.0,
+                next_1d: 0.0,
+                next_7d: 0.0,
+                next_30d: 0.0,
+            });
+        entry.credits += 1;
+        // Accumulate the weighted sum, divided by the amount below.
+        entry.avg_rate += c.rate * c.amount.abs();
+        entry.amount += c.amount.abs();
+        entry.next_1d += daily * days_left.min(1.0);
+        entry.next_7d += daily * days_left.min(7.0);
+        entry.next_30d += daily * days_left.min(30.0);
+    }
+
+    let mut result: Vec<InterestForecast> = groups
+        .into_values()
+        .map(|mut f| {
+            if f.amount > 0.0 {
+                f.avg_rate /= f.amount;
+            }
+            f
+        })
+        .collect();
+    result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    result
+}
+
+/// Candidate trading symbols of the pairs between `ccys`, in both directions,
+/// to fetch the tickers of for [`triangle_routes`].
+pub fn triangle_symbols(ccys: [&str; 3]) -> Vec<String> {
+    let mut symbols = Vec::new();
+    for from in ccys {
+        for to in ccys {
+            if from != to {
+                symbols.push(pair_symbol(from, to));
+            }
+        }
+    }
+    symbols
+}
+
+pub(crate) fn pair_symbol(base: &str, quote: &str) -> String {
+    if base.len() == 3 && quote.len() == 3 {
+        format!("t{base}{quote}")
+    } else {
+        format!("t{base}:{quote}")
+    }
+}
+
+/// Round trips `a > b > c > a` and `a > c > b > a` through the pairs of
+/// `tickers`, paying the `taker_fee` (in percent) on every conversion.
+///
+/// Routes missing a pair in either direction are skipped.
+pub fn triangle_routes(
+    ccys: [&str; 3],
+    tickers: &[(String, TradingTicker)],
+    taker_fee: f64,
+) -> Vec<TriangleRoute> {
+    let fee = 1.0 - taker_fee / 100.0;
+    // (net rate, mid rate) of one conversion
+    let leg = |from: &str, to: &str| -> Option<(TriangleLeg, f64)> {
+        let find = |symbol: String| tickers.iter().find(|(s, _)| *s == symbol);
+        let (symbol, rate, mid) = if let Some((s, t)) = find(pair_symbol(from, to)) {
+            // Sell `from` at the bid
+            (s, t.bid, (t.bid + t.ask) / 2.0)
+        } else {
+            // Buy `to` at the ask
+            let (s, t) = find(pair_symbol(to, from))?;
+            (s, 1.0 / t.ask, 2.0 / (t.bid + t.ask))
+        };
+        let leg = TriangleLeg {
+            from: from.to_string(),
+            to: to.to_string(),
+            symbol: symbol.clone(),
+            rate: rate * fee,
+        };
+        Some((leg, mid))
+    };
+
+    let [a, b, c] = ccys;
+    [[a, b, c, a], [a, c, b, a]]
+        .iter()
+        .filter_map(|path| {
+            let mut legs = Vec::new();
+            let mut mid_return = 1.0;
+            let mut net_return = 1.0;
+            for step in path.windows(2) {
+                let (leg, mid) = leg(step[0], step[1])?;
+                mid_return *= mid;
+                net_return *= leg.rate;
+                legs.push(leg);
+            }
+            Some(TriangleRoute {
+                route: path.join(" > "),
+                legs,
+                mid_gap: (mid_return - 1.0) * 100.0,
+                net_return: (net_return - 1.0) * 100.0,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_state_open_and_add() {
+        let mut state = TagState::default();
+        state.apply(1.0, 100.0);
+        state.apply(1.0, 200.0);
+        assert_eq!(state.position, 2.0);
+        assert_eq!(state.avg_price, 150.0);
+        assert_eq!(state.realized_pnl, 0.0);
+    }
+
+    #[test]
+    fn tag_state_partial_close() {
+        let mut state = TagState::default();
+        state.apply(2.0, 100.0);
+        state.apply(-0.5, 120.0);
+        assert_eq!(state.position, 1.5);
+        assert_eq!(state.avg_price, 100.0);
+        assert_eq!(state.realized_pnl, 10.0);
+    }
+
+    #[test]
+    fn tag_state_flip() {
+        let mut state = TagState::default();
+        state.apply(-1.0, 100.0);
+        // Closes the short at a loss and opens a long of 2 at 110
+        state.apply(3.0, 110.0);
+        assert_eq!(state.position, 2.0);
+        assert_eq!(state.avg_price, 110.0);
+        assert_eq!(state.realized_pnl, -10.0);
+    }
+
+    #[test]
+    fn tag_state_full_close() {
+        let mut state = TagState::default();
+        state.apply(0.1, 100.0);
+        state.apply(0.2, 100.0);
+        // 0.1 + 0.2 - 0.3 is not exactly 0
+        state.apply(-0.3, 110.0);
+        assert_eq!(state.position, 0.0);
+        assert_eq!(state.avg_price, 0.0);
+        assert!((state.realized_pnl - 3.0).abs() < 1e-9);
+
+        // The next trade opens a fresh position at its own price
+        state.apply(-1.0, 90.0);
+        assert_eq!(state.position, -1.0);
+        assert_eq!(state.avg_price, 90.0);
+    }
+}
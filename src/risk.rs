@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    client::Client,
+    error::BitfinexError,
+    funding::FundingCredit,
+    trading::{OrderMeta, Position, TradingOrder, TradingOrderType, TradingTicker},
+    utils::parse_base_ccy_from_symbol,
+};
+
+// --- Data Models --- //
+/// Unrealized PnL of a single position, valued in the quote currency of its pair.
+#[derive(Serialize)]
+pub struct PositionPnl {
+    pub symbol: String,
+    pub amount: f64,
+    pub base_price: f64,
+    pub mark_price: f64,
+    pub pnl: f64,
+    /// PnL relative to the position value at the base price, in percent.
+    pub pnl_perc: f64,
+    /// Share of this position in the absolute PnL of all positions, in percent.
+    /// Negative for losing positions.
+    pub contribution: f64,
+}
+
+/// Lent funds used by one trading pair.
+#[derive(Serialize)]
+pub struct PairExposure {
+    pub pair: String,
+    pub credits: usize,
+    pub amount: f64,
+    /// Share of this pair in all lent funds, in percent.
+    pub share: f64,
+    /// Average daily rate weighted by amount.
+    pub avg_rate: f64,
+}
+
+/// Derivative order that delta-hedges the exposure to a currency, see
+/// [`hedge_order`].
+#[derive(Serialize)]
+pub struct HedgeOrder {
+    /// Derivative pair of the hedge, e.g. `tBTCF0:USTF0`.
+    pub symbol: String,
+    /// Exposure to hedge in the currency, positive when long.
+    pub exposure: f64,
+    /// Amount of the current position in `symbol`.
+    pub current_hedge: f64,
+    /// Order amount bringing the net exposure to zero, negative to sell.
+    pub amount: f64,
+}
+
+// --- Risk Functions --- //
+/// Compute the unrealized PnL of `positions`, marked at the last price of
+/// `tickers`. Positions without a matching ticker are skipped.
+pub fn unrealized_pnl(
+    positions: &[Position],
+    tickers: &[(String, TradingTicker)],
+) -> Vec<PositionPnl> {
+    let mut pnls: Vec<PositionPnl> = positions
+        .iter()
+        .filter_map(|p| {
+            let (_, ticker) = tickers.iter().find(|(s, _)| *s == p.symbol)?;
+            let mark_price = ticker.last_price;
+            let pnl = (mark_price - p.base_price) * p.amount;
+            let cost = (p.base_price * p.amount).abs();
+            Some(PositionPnl {
+                symbol: p.symbol.clone(),
+                amount: p.amount,
+                base_price: p.base_price,
+                mark_price,
+                pnl,
+                pnl_perc: if cost == 0.0 { 0.0 } else { pnl / cost * 100.0 },
+                contribution: 0.0,
+            })
+        })
+        .collect();
+
+    let total: f64 = pnls.iter().map(|p| p.pnl.abs()).sum();
+    if total > 0.0 {
+        for p in &mut pnls {
+            p.contribution = p.pnl / total * 100.0;
+        }
+    }
+    pnls
+}
+
+/// Group active funding credits by the trading pair using them, from the
+/// largest exposure.
+pub fn credit_exposure(credits: &[FundingCredit]) -> Vec<PairExposure> {
+    let mut groups: HashMap<&str, (usize, f64, f64)> = HashMap::new();
+    for c in credits {
+        let (count, amount, weighted_rate) = groups.entry(c.pair.as_str()).or_default();
+        *count += 1;
+        *amount += c.amount.abs();
+        *weighted_rate += c.amount.abs() * c.rate;
+    }
+
+    let total: f64 = groups.values().map(|(_, amount, _)| amount).sum();
+    let mut exposures: Vec<PairExposure> = groups
+        .into_iter()
+        .map(|(pair, (credits, amount, weighted_rate))| PairExposure {
+            pair: pair.to_string(),
+            credits,
+            amount,
+            share: if total == 0.0 {
+                0.0
+            } else {
+                amount / total * 100.0
+            },
+            avg_rate: if amount == 0.0 {
+                0.0
+            } else {
+                weighted_rate / amount
+            },
+        })
+        .collect();
+    exposures.sort_by(|a, b| b.amount.total_cmp(&a.amount));
+    exposures
+}
+
+/// Compute the order on the derivative `symbol` offsetting `exposure`, taking
+/// the current position in `symbol` into account.
+pub fn hedge_order(symbol: &str, exposure: f64, positions: &[Position]) -> HedgeOrder {
+    let current_hedge: f64 = positions
+        .iter()
+        .filter(|p| p.symbol == symbol)
+        .map(|p| p.amount)
+        .sum();
+    HedgeOrder {
+        symbol: symbol.to_string(),
+        exposure,
+        current_hedge,
+        amount: -(exposure + current_hedge),
+    }
+}
+
+impl Client {
+    /// Compute the order on the derivative `symbol` (e.g. tBTCF0:USTF0) that
+    /// delta-hedges the exposure to `ccy` (e.g. BTC): its balance in all
+    /// wallets plus the margin positions of pairs based on it. Nothing is
+    /// submitted, see [`Client::submit_hedge_order`].
+    pub async fn request_hedge_order(
+        &self,
+        ccy: &str,
+        symbol: &str,
+    ) -> Result<HedgeOrder, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let wallets = self.request_wallets().await?;
+        let positions = self.request_positions().await?;
+
+        let balance: f64 = wallets
+            .iter()
+            .filter(|w| w.ccy == ccy)
+            .map(|w| w.balance)
+            .sum();
+        let margin: f64 = positions
+            .iter()
+            .filter(|p| p.typ != Some(1) && parse_base_ccy_from_symbol(&p.symbol).ok() == Some(ccy))
+            .map(|p| p.amount)
+            .sum();
+        Ok(hedge_order(symbol, balance + margin, &positions))
+    }
+
+    /// Submit `hedge` as a market order, with the amount rounded to 8
+    /// decimals. Nothing is submitted when the exposure is already hedged or
+    /// the amount is below the minimum order size of the pair.
+    pub async fn submit_hedge_order(
+        &self,
+        hedge: &HedgeOrder,
+    ) -> Result<Vec<TradingOrder>, BitfinexError> {
+        let amount = format!("{:.8}", hedge.amount);
+        let rounded: f64 = amount.parse().unwrap_or_default();
+        if rounded == 0.0 {
+            return Ok(Vec::new());
+        }
+        let pairs = self.request_pair_info().await?;
+        if let Some(info) = pairs.iter().find(|p| p.symbol == hedge.symbol) {
+            match info.check_amount(rounded) {
+                Err(BitfinexError::MinimumSizeNotMet(_)) => return Ok(Vec::new()),
+                result => result?,
+            }
+        }
+        self.submit_trading_order(
+            &hedge.symbol,
+            TradingOrderType::Market,
+            &amount,
+            "0",
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(OrderMeta::with_tag("hedge")),
+        )
+        .await
+    }
+
+    /// Fetch the active positions and the tickers of their pairs with a single
+    /// batched request, then compute their unrealized PnL.
+    pub async fn request_unrealized_pnl(&self) -> Result<Vec<PositionPnl>, BitfinexError> {
+        let positions = self.request_positions().await?;
+        if positions.is_empty() {
+            return Ok(Vec::new());
+        }
+        let symbols: Vec<&str> = positions.iter().map(|p| p.symbol.as_str()).collect();
+        let tickers = self.request_trading_tickers(&symbols).await?;
+        Ok(unrealized_pnl(&positions, &tickers))
+    }
+}
@@ -1,14 +1,16 @@
 use std::convert::{From, Into};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
-use serde_json::{from_str, json, Value};
+use serde_json::{json, Value};
 
 use crate::{
     client::Client,
-    deserializer::from_mts,
+    deserializer::{parse_notification, parse_one, parse_vec},
     error::BitfinexError,
-    funding::{BookPrecision, Candle, CandleTimeFrame},
+    funding::{BookPrecision, Candle, CandleTimeFrame, check_candles},
+    utils::{Mts, OrderFlags, Side, parse_base_ccy_from_symbol, parse_ccy_from_symbol},
 };
 
 // --- Trading Enums --- /
@@ -80,7 +82,7 @@ impl std::fmt::Display for TradingOrderType {
 }
 
 // --- Trading Models --- //
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TradingTicker {
     pub bid: f64,
     pub bid_size: f64,
@@ -120,29 +122,39 @@ pub struct TradingTickerHist {
     _placeholder_7: Option<String>,
     #[serde(skip_serializing)]
     _placeholder_8: Option<String>,
-    #[serde(skip_serializing)]
-    _placeholder_9: Option<String>,
 
-    #[serde(deserialize_with = "from_mts")]
-    time: DateTime<Local>,
+    pub time: Mts,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct TradingTrade {
     pub id: u64,
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
     pub amount: f64,
     pub price: f64,
 }
 
-#[derive(Serialize, Deserialize)]
+impl TradingTrade {
+    /// Side of the taker.
+    pub fn side(&self) -> Side {
+        Side::from_trading_amount(self.amount)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct TradingBook {
     pub price: f64,
     pub count: u32,
     pub amount: f64,
 }
 
+impl TradingBook {
+    /// `Buy` for bids, `Sell` for asks.
+    pub fn side(&self) -> Side {
+        Side::from_trading_amount(self.amount)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TradingBookRaw {
     pub order_id: u64,
@@ -150,26 +162,58 @@ pub struct TradingBookRaw {
     pub amount: f64,
 }
 
+impl TradingBookRaw {
+    /// `Buy` for bids, `Sell` for asks.
+    pub fn side(&self) -> Side {
+        Side::from_trading_amount(self.amount)
+    }
+}
+
+/// Meta of a trading order, passed to [`Client::submit_trading_order`] and
+/// returned on [`TradingOrder::meta`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderMeta {
+    /// Affiliate code credited with the order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aff_code: Option<String>,
+    /// 1 to make a hidden order visible once it is partially filled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub make_visible: Option<u8>,
+    /// Strategy label, see [`TradingOrder::tag`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Any other custom fields.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+impl OrderMeta {
+    pub fn with_tag(tag: &str) -> Self {
+        OrderMeta {
+            tag: Some(tag.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TradingOrder {
     pub id: u64,
     pub group_id: Option<u64>,
     pub client_order_id: u64,
     pub symbol: String,
-    #[serde(deserialize_with = "from_mts")]
-    pub created: DateTime<Local>,
-    #[serde(deserialize_with = "from_mts")]
-    pub updated: DateTime<Local>,
+    pub created: Mts,
+    pub updated: Mts,
     pub amount: f64,
     pub amount_orig: f64,
     pub order_type: TradingOrderType,
     pub type_prev: Option<TradingOrderType>,
-    pub mts_time_in_force: Option<u64>,
+    pub mts_time_in_force: Option<Mts>,
 
     #[serde(skip_serializing)]
     _placeholder_1: Option<String>,
 
-    pub flags: Option<u64>,
+    pub flags: Option<OrderFlags>,
     pub status: String,
 
     #[serde(skip_serializing)]
@@ -205,13 +249,124 @@ pub struct TradingOrder {
     #[serde(skip_serializing)]
     _placeholder_10: Option<String>,
 
-    pub meta: Option<String>,
+    pub meta: Option<OrderMeta>,
+}
+
+impl TradingOrder {
+    pub fn side(&self) -> Side {
+        Side::from_trading_amount(self.amount_orig)
+    }
+
+    /// Strategy label set with the `meta` of [`Client::submit_trading_order`].
+    pub fn tag(&self) -> Option<&str> {
+        self.meta.as_ref()?.tag.as_deref()
+    }
+}
+
+/// One operation of [`Client::order_multi`].
+pub enum OrderOp {
+    /// Submit a new order. `params` holds optional fields of
+    /// [`Client::submit_trading_order`], e.g. `{"flags": 4096, "cid": 1}` where
+    /// 4096 is [`OrderFlags::POST_ONLY`].
+    New {
+        symbol: String,
+        order_type: TradingOrderType,
+        amount: String,
+        price: String,
+        params: Option<Value>,
+    },
+    /// Update an order. `params` holds the fields to change, as in
+    /// [`Client::update_trading_order`], e.g. `{"amount": "0.1", "price": "25000"}`.
+    Update {
+        id: u64,
+        params: Value,
+    },
+    Cancel {
+        id: u64,
+    },
+    CancelMulti {
+        ids: Vec<u64>,
+    },
+}
+
+impl OrderOp {
+    /// `[OP, PARAMS]` entry of the request.
+    fn to_value(&self) -> Value {
+        let merge = |mut data: Value, params: &Option<Value>| {
+            if let (Some(Value::Object(params)), Some(data)) = (params, data.as_object_mut()) {
+                data.extend(params.clone());
+            }
+            data
+        };
+        match self {
+            OrderOp::New {
+                symbol,
+                order_type,
+                amount,
+                price,
+                params,
+            } => {
+                let data = json!({
+                    "symbol": symbol,
+                    "type": order_type.to_string(),
+                    "amount": amount,
+                    "price": price,
+                });
+                json!(["on", merge(data, params)])
+            }
+            OrderOp::Update { id, params } => {
+                json!(["ou", merge(json!({"id": id}), &Some(params.clone()))])
+            }
+            OrderOp::Cancel { id } => json!(["oc", {"id": id}]),
+            OrderOp::CancelMulti { ids } => json!(["oc_multi", {"id": ids}]),
+        }
+    }
+}
+
+/// Outcome of one operation of [`Client::order_multi`], in the order of the
+/// operations.
+#[derive(Serialize)]
+pub struct OrderOpResult {
+    /// Notification type, e.g. `on-req`, `ou-req`, `oc-req` or `oc_multi-req`.
+    pub op: String,
+    pub status: String,
+    pub message: Option<String>,
+    pub orders: Vec<TradingOrder>,
+}
+
+impl OrderOpResult {
+    pub fn is_success(&self) -> bool {
+        self.status == "SUCCESS"
+    }
+}
+
+/// Execution (fill) of one of the user's orders.
+#[derive(Serialize, Deserialize)]
+pub struct UserTrade {
+    pub id: u64,
+    pub symbol: String,
+    pub time: Mts,
+    pub order_id: u64,
+    pub amount: f64,
+    pub price: f64,
+    pub order_type: Option<String>,
+    pub order_price: Option<f64>,
+    /// 1 if the trade was maker, -1 if taker.
+    pub maker: i8,
+    pub fee: f64,
+    pub fee_ccy: String,
+    pub client_order_id: Option<u64>,
+}
+
+impl UserTrade {
+    pub fn side(&self) -> Side {
+        Side::from_trading_amount(self.amount)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct TradingOrderMultiResult {
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
     pub noti_type: String,
     pub message_id: Option<u64>,
 
@@ -226,8 +381,7 @@ pub struct TradingOrderMultiResult {
 
 #[derive(Serialize, Deserialize)]
 pub struct TradingOrderResult {
-    #[serde(deserialize_with = "from_mts")]
-    pub time: DateTime<Local>,
+    pub time: Mts,
     pub noti_type: String,
     pub message_id: Option<u64>,
 
@@ -240,6 +394,256 @@ pub struct TradingOrderResult {
     pub message: Option<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct Position {
+    pub symbol: String,
+    pub status: String,
+    pub amount: f64,
+    pub base_price: f64,
+    pub margin_funding: f64,
+    pub margin_funding_type: u8,
+    pub pl: Option<f64>,
+    pub pl_perc: Option<f64>,
+    pub liquidation_price: Option<f64>,
+    pub leverage: Option<f64>,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub id: u64,
+    pub created: Mts,
+    pub updated: Mts,
+
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+
+    /// 0 for margin positions, 1 for derivatives positions.
+    pub typ: Option<u8>,
+
+    #[serde(skip_serializing)]
+    _placeholder_3: Option<String>,
+
+    pub collateral: Option<f64>,
+    pub collateral_min: Option<f64>,
+    #[serde(default)]
+    pub meta: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionResult {
+    pub time: Mts,
+    pub noti_type: String,
+    pub message_id: Option<u64>,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub position: Position,
+    pub code: Option<u16>,
+    pub status: String,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionIncreaseResult {
+    pub time: Mts,
+    pub noti_type: String,
+    pub message_id: Option<u64>,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    /// Position data as returned by Bitfinex.
+    pub data: Option<Value>,
+    pub code: Option<u16>,
+    pub status: String,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionIncreaseLimits {
+    pub max_pos: f64,
+    pub current_pos: f64,
+    pub base_ccy_balance: f64,
+    pub tradable_balance_quote_ccy: f64,
+    pub tradable_balance_quote_total: f64,
+    pub tradable_balance_base_ccy: f64,
+    pub tradable_balance_base_total: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionIncreaseFunding {
+    pub funding_avail: f64,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_3: Option<String>,
+    #[serde(skip_serializing)]
+    _placeholder_4: Option<String>,
+
+    pub funding_value: f64,
+    pub funding_required: f64,
+    pub funding_value_ccy: String,
+    pub funding_required_ccy: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionIncreaseInfo {
+    pub limits: PositionIncreaseLimits,
+    pub funding: PositionIncreaseFunding,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DerivCollateralLimits {
+    pub min_collateral: f64,
+    pub max_collateral: f64,
+}
+
+/// Order size and margin limits of a pair, see [`Client::request_pair_info`].
+#[derive(Serialize)]
+pub struct PairInfo {
+    /// Trading symbol, e.g. `tBTCUSD` or `tBTCF0:USTF0`.
+    pub symbol: String,
+    pub min_order_size: f64,
+    pub max_order_size: f64,
+    /// Initial margin of margin and derivatives pairs.
+    pub initial_margin: Option<f64>,
+    /// Maintenance margin of margin and derivatives pairs.
+    pub maintenance_margin: Option<f64>,
+}
+
+impl PairInfo {
+    pub fn margin_available(&self) -> bool {
+        self.initial_margin.is_some()
+    }
+
+    /// Fail when the absolute `amount` is outside of the order size limits.
+    pub fn check_amount(&self, amount: f64) -> Result<(), BitfinexError> {
+        let size = amount.abs();
+        if size < self.min_order_size {
+            return Err(BitfinexError::MinimumSizeNotMet(format!(
+                "minimum size for {} is {}",
+                self.symbol, self.min_order_size
+            )));
+        }
+        if size > self.max_order_size {
+            return Err(BitfinexError::BitfinexGenericError(format!(
+                "maximum size for {} is {}",
+                self.symbol, self.max_order_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for PairInfo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // [PAIR, [_, _, _, MIN_ORDER_SIZE, MAX_ORDER_SIZE, _, _, _, INITIAL_MARGIN, MIN_MARGIN]]
+        let (pair, info): (String, Vec<Value>) = Deserialize::deserialize(deserializer)?;
+        // Sizes are sent as strings and margins as numbers.
+        let number = |i: usize| -> Option<f64> {
+            match info.get(i)? {
+                Value::String(s) => s.parse().ok(),
+                value => value.as_f64(),
+            }
+        };
+        let size = |i: usize, what: &str| {
+            number(i).ok_or_else(|| serde::de::Error::custom(format!("missing {what} of {pair}")))
+        };
+        Ok(PairInfo {
+            symbol: format!("t{pair}"),
+            min_order_size: size(3, "min order size")?,
+            max_order_size: size(4, "max order size")?,
+            initial_margin: number(8),
+            maintenance_margin: number(9),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MarginBase {
+    pub user_pl: f64,
+    pub user_swaps: f64,
+    pub margin_balance: f64,
+    pub margin_net: f64,
+    pub margin_min: f64,
+}
+
+#[derive(Serialize)]
+pub struct MarginSymbol {
+    pub symbol: String,
+    pub tradable_balance: f64,
+    pub gross_balance: f64,
+    /// Maximum amount available to buy.
+    pub buy: f64,
+    /// Maximum amount available to sell.
+    pub sell: f64,
+}
+
+impl<'de> Deserialize<'de> for MarginSymbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // ["sym", SYMBOL, [TRADABLE_BALANCE, GROSS_BALANCE, BUY, SELL, ...]]
+        let (_, symbol, info): (String, String, Vec<f64>) = Deserialize::deserialize(deserializer)?;
+        if info.len() < 4 {
+            return Err(serde::de::Error::invalid_length(
+                info.len(),
+                &"4 margin values",
+            ));
+        }
+        Ok(MarginSymbol {
+            symbol,
+            tradable_balance: info[0],
+            gross_balance: info[1],
+            buy: info[2],
+            sell: info[3],
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Liquidation {
+    #[serde(skip_serializing)]
+    _placeholder_0: Option<String>,
+
+    pub position_id: u64,
+    pub time: Mts,
+
+    #[serde(skip_serializing)]
+    _placeholder_1: Option<String>,
+
+    pub symbol: String,
+    pub amount: f64,
+    pub base_price: f64,
+
+    #[serde(skip_serializing)]
+    _placeholder_2: Option<String>,
+
+    pub is_match: u8,
+    pub is_market_sold: u8,
+
+    #[serde(skip_serializing)]
+    _placeholder_3: Option<String>,
+
+    pub liquidation_price: f64,
+}
+
+/// Spread and visible liquidity of a single pair, see [`Client::screen_trading_market`].
+#[derive(Serialize)]
+pub struct TradingMarket {
+    pub symbol: String,
+    pub bid: f64,
+    pub ask: f64,
+    /// Top-of-book spread in basis points of the mid price.
+    pub spread_bps: f64,
+    /// Visible bid depth in USD. `None` if no USD price exists for the quote.
+    pub bid_depth_usd: Option<f64>,
+    /// Visible ask depth in USD. `None` if no USD price exists for the quote.
+    pub ask_depth_usd: Option<f64>,
+}
+
 // --- Trading Functions --- //
 impl Client {
     // --- Public Endpoints --- //
@@ -249,13 +653,14 @@ impl Client {
         symbol: &str,
         prec: BookPrecision,
     ) -> Result<Vec<TradingBook>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("t") {
             panic!("You must specify trading symbol for trading book");
         }
         let prec = u8::from(prec);
         let url = format!("book/{symbol}/P{prec}?len=250");
         let body = self.get(&url).await?;
-        let books: Vec<TradingBook> = from_str(&body).unwrap();
+        let books: Vec<TradingBook> = parse_vec(&body)?;
         Ok(books)
     }
 
@@ -264,12 +669,13 @@ impl Client {
         &self,
         symbol: &str,
     ) -> Result<Vec<TradingBookRaw>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("t") {
             panic!("You must specify trading symbol for trading book raw");
         }
         let url = format!("book/{symbol}/R0?len=250");
         let body = self.get(&url).await?;
-        let books: Vec<TradingBookRaw> = from_str(&body).unwrap();
+        let books: Vec<TradingBookRaw> = parse_vec(&body)?;
         Ok(books)
     }
 
@@ -278,9 +684,10 @@ impl Client {
         &self,
         symbol: &str,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<TradingTrade>, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("t") {
             panic!("You must specify trading symbol for trading trades");
         }
@@ -296,36 +703,130 @@ impl Client {
             url = format!("{url}&end={}", end.timestamp_millis());
         }
         let body = self.get(&url).await?;
-        let trades: Vec<TradingTrade> = from_str(&body).unwrap();
+        let trades: Vec<TradingTrade> = parse_vec(&body)?;
         Ok(trades)
     }
 
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-liquidations>
+    pub async fn request_liquidations(
+        &self,
+        limit: Option<u16>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Liquidation>, BitfinexError> {
+        let mut url = String::from("liquidations/hist?sort=-1");
+        if let Some(limit) = limit {
+            // max: 500
+            url = format!("{url}&limit={limit}");
+        }
+        if let Some(start) = start {
+            url = format!("{url}&start={}", start.timestamp_millis());
+        }
+        if let Some(end) = end {
+            url = format!("{url}&end={}", end.timestamp_millis());
+        }
+        let body = self.get(&url).await?;
+        // Each entry is wrapped in its own array: [[["pos", ...]], ...]
+        let rows: Vec<Vec<Liquidation>> = parse_vec(&body)?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    /// Order size limits and margins of all exchange and derivatives pairs.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-conf>
+    pub async fn request_pair_info(&self) -> Result<Vec<PairInfo>, BitfinexError> {
+        let url = String::from("conf/pub:info:pair,pub:info:pair:futures");
+        let body = self.get(&url).await?;
+        let lists: Vec<Vec<PairInfo>> = parse_one(&body)?;
+        Ok(lists.into_iter().flatten().collect())
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-ticker#response-fields-trading-pairs-ex-tbtcusd>
     pub async fn request_trading_ticker(
         &self,
         symbol: &str,
     ) -> Result<TradingTicker, BitfinexError> {
+        self.check_symbol(symbol).await?;
         if !symbol.starts_with("t") {
             panic!("You must specify trading symbol for trading ticker");
         }
         let url = format!("ticker/{symbol}");
         let body = self.get(&url).await?;
-        let ticker: TradingTicker = from_str(&body).unwrap();
+        let ticker: TradingTicker = parse_one(&body)?;
         Ok(ticker)
     }
 
+    /// ## Parameters:
+    /// - `symbols`: trading pairs to query (e.g. tBTCUSD). `["ALL"]` for all pairs.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-tickers>
+    pub async fn request_trading_tickers(
+        &self,
+        symbols: &[&str],
+    ) -> Result<Vec<(String, TradingTicker)>, BitfinexError> {
+        let url = format!("tickers?symbols={}", symbols.join(","));
+        let body = self.get(&url).await?;
+        let rows: Vec<Vec<Value>> = parse_vec(&body)?;
+
+        // Each row is [SYMBOL, ...TICKER_FIELDS]
+        let tickers = rows
+            .into_iter()
+            .filter(|row| {
+                row.first()
+                    .and_then(Value::as_str)
+                    .is_some_and(|s| s.starts_with("t"))
+            })
+            .filter_map(|mut row| {
+                let symbol = row.remove(0).as_str()?.to_string();
+                let ticker: TradingTicker = serde_json::from_value(Value::from(row)).ok()?;
+                Some((symbol, ticker))
+            })
+            .collect();
+        Ok(tickers)
+    }
+
+    /// Historic bid and ask snapshots of trading pairs, taken about once an hour.
+    ///
+    /// ## Parameters:
+    /// - `symbols`: trading pairs to query (e.g. tBTCUSD). `["ALL"]` for all pairs.
+    /// - `limit` is up to 250
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-public-tickers-history>
+    pub async fn request_tickers_hist(
+        &self,
+        symbols: &[&str],
+        limit: Option<u16>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<TradingTickerHist>, BitfinexError> {
+        let mut url = format!("tickers/hist?symbols={}", symbols.join(","));
+        if let Some(limit) = limit {
+            // max 250
+            url = format!("{url}&limit={limit}");
+        }
+        if let Some(start) = start {
+            url = format!("{url}&start={}", start.timestamp_millis());
+        }
+        if let Some(end) = end {
+            url = format!("{url}&end={}", end.timestamp_millis());
+        }
+
+        let body = self.get(&url).await?;
+        let tickers: Vec<TradingTickerHist> = parse_vec(&body)?;
+        Ok(tickers)
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-public-candles#trading-pair-candles>
     pub async fn request_trading_candles(
         &self,
         symbol: &str,
         time_frame: CandleTimeFrame,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<Candle>, BitfinexError> {
-        if !symbol.starts_with("t") {
-            panic!("You must specify trading pair for trading candles");
-        }
+        self.check_symbol(symbol).await?;
+        check_candles(symbol, time_frame, None)?;
 
         let time_frame: String = time_frame.into();
         let mut url = format!("candles/trade:{time_frame}:{symbol}/hist?sort=-1");
@@ -341,10 +842,105 @@ impl Client {
         }
 
         let body = self.get(&url).await?;
-        let candles: Vec<Candle> = from_str(&body).unwrap();
+        let candles: Vec<Candle> = parse_vec(&body)?;
+        Ok(candles)
+    }
+
+    /// Get all candles between `start` and `end`, from the oldest, paging
+    /// through the 10000 candles limit of a single request.
+    pub async fn request_trading_candles_between(
+        &self,
+        symbol: &str,
+        time_frame: CandleTimeFrame,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Candle>, BitfinexError> {
+        let mut candles: Vec<Candle> = Vec::new();
+        let mut end = end;
+        while end >= start {
+            let page = self
+                .request_trading_candles(symbol, time_frame, Some(10000), Some(start), Some(end))
+                .await?;
+            let Some(oldest) = page.last() else {
+                break;
+            };
+            end = *oldest.time - chrono::Duration::milliseconds(1);
+            let full = page.len() == 10000;
+            candles.extend(page);
+            if !full {
+                break;
+            }
+        }
+        candles.reverse();
         Ok(candles)
     }
 
+    /// Screen all trading pairs by top-of-book spread and visible book depth.
+    ///
+    /// Pairs with a spread wider than `max_spread_bps` are dropped before their
+    /// books are fetched, concurrently in small batches to stay within the rate
+    /// limit.
+    pub async fn screen_trading_market(
+        &self,
+        max_spread_bps: Option<f64>,
+    ) -> Result<Vec<TradingMarket>, BitfinexError> {
+        const BATCH_SIZE: usize = 5;
+
+        let tickers = self.request_trading_tickers(&["ALL"]).await?;
+        let usd_price = |ccy: &str| -> Option<f64> {
+            if ccy == "USD" {
+                return Some(1.0);
+            }
+            tickers
+                .iter()
+                .find(|(s, _)| *s == format!("t{ccy}USD") || *s == format!("t{ccy}:USD"))
+                .map(|(_, t)| t.last_price)
+        };
+
+        let candidates: Vec<(&String, &TradingTicker, f64)> = tickers
+            .iter()
+            .filter(|(_, t)| t.bid > 0.0 && t.ask > 0.0)
+            .map(|(symbol, t)| {
+                let mid = (t.bid + t.ask) / 2.0;
+                (symbol, t, (t.ask - t.bid) / mid * 10_000.0)
+            })
+            .filter(|(_, _, spread)| max_spread_bps.is_none_or(|max| *spread <= max))
+            .collect();
+
+        let mut markets = Vec::new();
+        for batch in candidates.chunks(BATCH_SIZE) {
+            let books = join_all(
+                batch
+                    .iter()
+                    .map(|(symbol, _, _)| self.request_trading_book(symbol, BookPrecision::One)),
+            )
+            .await;
+
+            for ((symbol, ticker, spread_bps), books) in batch.iter().zip(books) {
+                let (bid_depth, ask_depth) = books.map_or((0.0, 0.0), |books| {
+                    books.iter().fold((0.0, 0.0), |(bid, ask), b| {
+                        let value = b.price * b.amount.abs();
+                        if b.amount > 0.0 {
+                            (bid + value, ask)
+                        } else {
+                            (bid, ask + value)
+                        }
+                    })
+                });
+                let quote_usd = usd_price(parse_ccy_from_symbol(symbol));
+                markets.push(TradingMarket {
+                    symbol: symbol.to_string(),
+                    bid: ticker.bid,
+                    ask: ticker.ask,
+                    spread_bps: *spread_bps,
+                    bid_depth_usd: quote_usd.map(|p| p * bid_depth),
+                    ask_depth_usd: quote_usd.map(|p| p * ask_depth),
+                });
+            }
+        }
+        Ok(markets)
+    }
+
     // --- Authenticated Endpoints --- //
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-retrieve-orders>
     pub async fn request_trading_orders(
@@ -354,7 +950,10 @@ impl Client {
         client_id: Option<String>,
         client_id_date: Option<String>, // YYYY-MM-DD format. Should be specified if client_id is provided
     ) -> Result<Vec<TradingOrder>, BitfinexError> {
-        let mut url = format!("auth/r/orders");
+        if let Some(symbol) = &symbol {
+            self.check_symbol(symbol).await?;
+        }
+        let mut url = String::from("auth/r/orders");
         if let Some(sym) = symbol {
             url = format!("{url}/{sym}");
         }
@@ -374,11 +973,12 @@ impl Client {
         let payload = data.to_string();
 
         let body = self.post_with_payload(&url, payload).await?;
-        let orders: Vec<TradingOrder> = from_str(&body).unwrap();
+        let orders: Vec<TradingOrder> = parse_vec(&body)?;
         Ok(orders)
     }
 
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-submit-order>
+    #[allow(clippy::too_many_arguments)]
     pub async fn submit_trading_order(
         &self,
         symbol: &str,
@@ -391,9 +991,16 @@ impl Client {
         price_oco_stop: Option<String>,  // Only for stop
         gid: Option<u32>,                // Group ID
         cid: Option<u32>,                // Client Order ID
-        flags: Option<u32>,              // The sum of all order flags
+        flags: Option<OrderFlags>,       // e.g. OrderFlags::HIDDEN | OrderFlags::POST_ONLY
         time_in_force: Option<String>,   // 2020-01-15 10:45:23
+        meta: Option<OrderMeta>,         // e.g. affiliate code or strategy tag
     ) -> Result<Vec<TradingOrder>, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        if let Some(headroom) = self.balance_check() {
+            self.check_order_balance(symbol, &order_type, amount, price, headroom)
+                .await?;
+        }
+
         let url = String::from("auth/w/order/submit");
 
         let mut data = json!({
@@ -422,22 +1029,73 @@ impl Client {
             data["cid"] = Value::from(cid);
         }
         if let Some(flags) = flags {
-            data["flags"] = Value::from(flags);
+            data["flags"] = Value::from(flags.bits());
         }
         if let Some(tif) = time_in_force {
             data["tif"] = Value::from(tif);
         }
+        if let Some(meta) = meta {
+            data["meta"] = json!(meta);
+        }
         let payload = data.to_string();
 
         let body = self.post_with_payload(&url, payload).await;
         let result: TradingOrderMultiResult = match body {
-            Ok(b) => from_str(&b).unwrap(),
+            Ok(b) => parse_notification(&b)?,
             Err(e) => return Err(e),
         };
         Ok(result.orders)
     }
 
+    /// Pre-flight of [`Client::submit_trading_order`], see [`Client::with_balance_check`].
+    async fn check_order_balance(
+        &self,
+        symbol: &str,
+        order_type: &TradingOrderType,
+        amount: &str,
+        price: &str,
+        headroom: f64,
+    ) -> Result<(), BitfinexError> {
+        let amount: f64 = amount.parse().map_err(|_| {
+            BitfinexError::BitfinexGenericError(format!("Invalid amount: {amount}"))
+        })?;
+        let required = amount.abs() * (1.0 + headroom);
+
+        if !order_type.to_string().starts_with("EXCHANGE") {
+            // Margin orders are bounded by the tradable amount of the pair
+            let info = self.request_margin_symbol(symbol).await?;
+            let available = match amount > 0.0 {
+                true => info.buy,
+                false => info.sell.abs(),
+            };
+            if available < required {
+                return Err(BitfinexError::InsufficientBalance {
+                    message: format!(
+                        "not enough margin: {available} {symbol} tradable, {required} required"
+                    ),
+                    shortfall: Some(required - available),
+                });
+            }
+            return Ok(());
+        }
+
+        // Rejects malformed symbols before the quote currency is sliced out
+        let base = parse_base_ccy_from_symbol(symbol)?;
+        if amount < 0.0 {
+            return self.ensure_wallet_balance("exchange", base, required).await;
+        }
+        let price = match price.parse::<f64>() {
+            Ok(price) if price > 0.0 => price,
+            // Market orders have no price, use the best ask instead
+            _ => self.request_trading_ticker(symbol).await?.ask,
+        };
+        let quote = parse_ccy_from_symbol(symbol);
+        self.ensure_wallet_balance("exchange", quote, required * price)
+            .await
+    }
+
     /// Ref: <https://docs.bitfinex.com/reference/rest-auth-update-order>
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_trading_order(
         &self,
         id: u64,
@@ -450,10 +1108,10 @@ impl Client {
         gid: Option<u32>, // Group ID
         cid: Option<u64>, // Client ID
         cid_date: Option<String>, // YYYY-MM-DD format
-        flags: Option<u32>, // The sum of all order flags
+        flags: Option<OrderFlags>, // e.g. OrderFlags::empty().reduce_only(true)
         time_in_force: Option<String>, // 2020-01-15 10:45:23
     ) -> Result<TradingOrder, BitfinexError> {
-        let url = String::from("auth/w/order/submit");
+        let url = String::from("auth/w/order/update");
 
         let mut data = json!({
             "id": id,
@@ -487,7 +1145,7 @@ impl Client {
             data["cid_date"] = Value::from(cid_date);
         }
         if let Some(flags) = flags {
-            data["flags"] = Value::from(flags);
+            data["flags"] = Value::from(flags.bits());
         }
         if let Some(tif) = time_in_force {
             data["tif"] = Value::from(tif);
@@ -495,7 +1153,7 @@ impl Client {
         let payload = data.to_string();
 
         let body = self.post_with_payload(&url, payload).await?;
-        let result: TradingOrderResult = from_str(&body).unwrap();
+        let result: TradingOrderResult = parse_notification(&body)?;
         Ok(result.order)
     }
 
@@ -526,7 +1184,7 @@ impl Client {
         let payload = data.to_string();
 
         let body = self.post_with_payload(&url, payload).await?;
-        let result: TradingOrderResult = from_str(&body).unwrap();
+        let result: TradingOrderResult = parse_notification(&body)?;
         Ok(result.order)
     }
 
@@ -535,7 +1193,111 @@ impl Client {
         let url = String::from("auth/w/order/cancel/multi");
         let payload = json!({"all": 1}).to_string();
         let body = self.post_with_payload(&url, payload).await?;
-        let result: TradingOrderMultiResult = from_str(&body).unwrap();
+        let result: TradingOrderMultiResult = parse_notification(&body)?;
+        Ok(result.orders)
+    }
+
+    /// Submit, update and cancel orders with a single request, e.g. to
+    /// re-quote many levels within the rate limit. Up to 75 operations.
+    ///
+    /// Operations succeed or fail independently, see [`OrderOpResult::status`].
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-order-multi>
+    pub async fn order_multi(
+        &self,
+        ops: Vec<OrderOp>,
+    ) -> Result<Vec<OrderOpResult>, BitfinexError> {
+        for op in &ops {
+            if let OrderOp::New { symbol, .. } = op {
+                self.check_symbol(symbol).await?;
+            }
+        }
+        let url = String::from("auth/w/order/multi");
+        let ops: Vec<Value> = ops.iter().map(OrderOp::to_value).collect();
+        let payload = json!({"ops": ops}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        // Notification whose data holds one notification per operation
+        let notifications: Vec<Value> = parse_notification::<Vec<Value>>(&body)?
+            .get(4)
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        notifications
+            .into_iter()
+            .map(|n| {
+                // Orders of new and multi-cancel operations are nested in an array
+                let orders = match n.get(4) {
+                    Some(Value::Array(data)) if data.first().is_some_and(Value::is_array) => {
+                        serde_json::from_value(Value::from(data.clone()))
+                    }
+                    Some(data @ Value::Array(_)) => {
+                        serde_json::from_value(data.clone()).map(|order| vec![order])
+                    }
+                    _ => Ok(Vec::new()),
+                }
+                .map_err(|e| BitfinexError::ParseError(e.to_string()))?;
+                Ok(OrderOpResult {
+                    op: n
+                        .get(1)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    status: n
+                        .get(6)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    message: n.get(7).and_then(Value::as_str).map(str::to_string),
+                    orders,
+                })
+            })
+            .collect()
+    }
+
+    /// Cancel multiple orders with a single request.
+    ///
+    /// ## Parameters:
+    /// - `ids`: IDs of the orders.
+    /// - `gid`: Group ID; all orders of the group are canceled.
+    /// - `cid_pairs`: Client Order IDs with their dates in YYYY-MM-DD format.
+    ///
+    /// Fails with [`BitfinexError::InvalidArgument`] if none of them is given
+    /// or non-empty.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-cancel-orders-multiple>
+    pub async fn cancel_trading_orders(
+        &self,
+        ids: Option<Vec<u64>>,
+        gid: Option<u64>,
+        cid_pairs: Option<Vec<(u64, String)>>,
+    ) -> Result<Vec<TradingOrder>, BitfinexError> {
+        let ids = ids.filter(|ids| !ids.is_empty());
+        let cid_pairs = cid_pairs.filter(|pairs| !pairs.is_empty());
+        if ids.is_none() && gid.is_none() && cid_pairs.is_none() {
+            return Err(BitfinexError::InvalidArgument(String::from(
+                "ids, gid or cid_pairs is required to cancel trading orders",
+            )));
+        }
+        let url = String::from("auth/w/order/cancel/multi");
+
+        let mut data = json!({});
+        if let Some(ids) = ids {
+            data["id"] = Value::from(ids);
+        }
+        if let Some(gid) = gid {
+            data["gid"] = json!([gid]);
+        }
+        if let Some(cid_pairs) = cid_pairs {
+            data["cid"] = cid_pairs
+                .into_iter()
+                .map(|(cid, cid_date)| json!([cid, cid_date]))
+                .collect();
+        }
+        let payload = data.to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        let result: TradingOrderMultiResult = parse_notification(&body)?;
         Ok(result.orders)
     }
 
@@ -546,9 +1308,12 @@ impl Client {
         &self,
         symbol: Option<String>,
         limit: Option<u16>,
-        start: Option<DateTime<Local>>,
-        end: Option<DateTime<Local>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
     ) -> Result<Vec<TradingOrder>, BitfinexError> {
+        if let Some(symbol) = &symbol {
+            self.check_symbol(symbol).await?;
+        }
         let mut url = String::from("auth/r/orders");
 
         if let Some(sym) = symbol {
@@ -570,7 +1335,193 @@ impl Client {
 
         let payload = data.to_string();
         let body = self.post_with_payload(&url, payload).await?;
-        let orders: Vec<TradingOrder> = from_str(&body).unwrap();
+        let orders: Vec<TradingOrder> = parse_vec(&body)?;
         Ok(orders)
     }
+
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-positions>
+    pub async fn request_positions(&self) -> Result<Vec<Position>, BitfinexError> {
+        let body = self.post_url(&String::from("auth/r/positions")).await?;
+        let positions: Vec<Position> = parse_vec(&body)?;
+        Ok(positions)
+    }
+
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-info-margin>
+    pub async fn request_margin_base(&self) -> Result<MarginBase, BitfinexError> {
+        let body = self
+            .post_url(&String::from("auth/r/info/margin/base"))
+            .await?;
+        // ["base", [USER_PL, USER_SWAPS, MARGIN_BALANCE, MARGIN_NET, MARGIN_MIN]]
+        let (_, base): (String, MarginBase) = parse_one(&body)?;
+        Ok(base)
+    }
+
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-info-margin>
+    pub async fn request_margin_symbol(&self, symbol: &str) -> Result<MarginSymbol, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        if !symbol.starts_with("t") {
+            panic!("You must specify trading symbol for margin info");
+        }
+        let url = format!("auth/r/info/margin/{symbol}");
+        let body = self.post_url(&url).await?;
+        let info: MarginSymbol = parse_one(&body)?;
+        Ok(info)
+    }
+
+    /// Get the user's trades on all symbols or on the given one.
+    ///
+    /// ## Parameters:
+    /// - `sort`: `1` for oldest first, `-1` for newest first (default).
+    ///
+    /// Ref:
+    /// - <https://docs.bitfinex.com/reference/rest-auth-trades>
+    /// - <https://docs.bitfinex.com/reference/rest-auth-trades-by-symbol>
+    pub async fn request_user_trades(
+        &self,
+        symbol: Option<String>,
+        limit: Option<u16>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        sort: Option<i8>,
+    ) -> Result<Vec<UserTrade>, BitfinexError> {
+        if let Some(symbol) = &symbol {
+            self.check_symbol(symbol).await?;
+        }
+        let mut url = String::from("auth/r/trades");
+        if let Some(sym) = symbol {
+            url = format!("{url}/{sym}");
+        }
+        url = format!("{url}/hist");
+
+        let mut data = json!({});
+        if let Some(limit) = limit {
+            // Max 2500
+            data["limit"] = Value::from(limit);
+        }
+        if let Some(start) = start {
+            data["start"] = Value::from(start.timestamp_millis());
+        }
+        if let Some(end) = end {
+            data["end"] = Value::from(end.timestamp_millis());
+        }
+        if let Some(sort) = sort {
+            data["sort"] = Value::from(sort);
+        }
+
+        let payload = data.to_string();
+        let body = self.post_with_payload(&url, payload).await?;
+        let trades: Vec<UserTrade> = parse_vec(&body)?;
+        Ok(trades)
+    }
+
+    /// Get the fills of a single order, e.g. to reconcile its executed amount and fees.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-order-trades>
+    pub async fn request_order_trades(
+        &self,
+        symbol: &str,
+        order_id: u64,
+    ) -> Result<Vec<UserTrade>, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = format!("auth/r/order/{symbol}:{order_id}/trades");
+        let body = self.post_url(&url).await?;
+        let trades: Vec<UserTrade> = parse_vec(&body)?;
+        Ok(trades)
+    }
+
+    /// Claim a margin position, converting it to exchange holdings.
+    ///
+    /// ## Parameters:
+    /// - `amount`: amount to claim. The whole position if not specified.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-claim-position>
+    pub async fn claim_position(
+        &self,
+        id: u64,
+        amount: Option<&str>,
+    ) -> Result<PositionResult, BitfinexError> {
+        let url = String::from("auth/w/position/claim");
+
+        let mut data = json!({"id": id});
+        if let Some(amount) = amount {
+            data["amount"] = Value::from(amount);
+        }
+        let payload = data.to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        let result: PositionResult = parse_notification(&body)?;
+        Ok(result)
+    }
+
+    /// Preview the limits and collateral required to increase a position.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-increase-position-info>
+    pub async fn request_position_increase_info(
+        &self,
+        symbol: &str,
+        amount: &str,
+    ) -> Result<PositionIncreaseInfo, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = String::from("auth/r/position/increase/info");
+        let payload = json!({"symbol": symbol, "amount": amount}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        let info: PositionIncreaseInfo = parse_one(&body)?;
+        Ok(info)
+    }
+
+    /// Increase an existing derivative position.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-increase-position>
+    pub async fn increase_position(
+        &self,
+        symbol: &str,
+        amount: &str,
+    ) -> Result<PositionIncreaseResult, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = String::from("auth/w/position/increase");
+        let payload = json!({"symbol": symbol, "amount": amount}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        let result: PositionIncreaseResult = parse_notification(&body)?;
+        Ok(result)
+    }
+
+    /// Set the collateral of a derivative position. The current collateral is
+    /// in [`Position::collateral`].
+    ///
+    /// Returns whether the collateral was updated.
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-deriv-pos-collateral-set>
+    pub async fn set_deriv_collateral(
+        &self,
+        symbol: &str,
+        collateral: f64,
+    ) -> Result<bool, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = String::from("auth/w/deriv/collateral/set");
+        let payload = json!({"symbol": symbol, "collateral": collateral}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        // [[STATUS]]
+        let [[status]]: [[u8; 1]; 1] = parse_one(&body)?;
+        Ok(status == 1)
+    }
+
+    /// Range the collateral of a derivative position can be set to with
+    /// [`Client::set_deriv_collateral`].
+    ///
+    /// Ref: <https://docs.bitfinex.com/reference/rest-auth-calc-deriv-collateral-limits>
+    pub async fn calc_deriv_collateral_limits(
+        &self,
+        symbol: &str,
+    ) -> Result<DerivCollateralLimits, BitfinexError> {
+        self.check_symbol(symbol).await?;
+        let url = String::from("auth/calc/deriv/collateral/limits");
+        let payload = json!({"symbol": symbol}).to_string();
+
+        let body = self.post_with_payload(&url, payload).await?;
+        let limits: DerivCollateralLimits = parse_one(&body)?;
+        Ok(limits)
+    }
 }
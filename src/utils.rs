@@ -1,6 +1,426 @@
 use std::env;
+use std::fmt;
 use std::io::Write;
+use std::ops::{BitOr, BitOrAssign, Deref};
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::error::BitfinexError;
+
+/// Timestamp in milliseconds as used by Bitfinex, (de)serialized as an integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mts(pub DateTime<Utc>);
+
+impl Mts {
+    pub fn from_millis(mts: i64) -> Option<Self> {
+        DateTime::from_timestamp_millis(mts).map(Mts)
+    }
+
+    pub fn now() -> Self {
+        Mts(Utc::now())
+    }
+
+    /// The timestamp in the local timezone, for display.
+    pub fn local(&self) -> DateTime<Local> {
+        self.0.with_timezone(&Local)
+    }
+}
+
+impl Deref for Mts {
+    type Target = DateTime<Utc>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Tz: TimeZone> From<DateTime<Tz>> for Mts {
+    fn from(value: DateTime<Tz>) -> Self {
+        Mts(value.with_timezone(&Utc))
+    }
+}
+
+impl From<Mts> for DateTime<Utc> {
+    fn from(value: Mts) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for Mts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339())
+    }
+}
+
+impl Serialize for Mts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0.timestamp_millis())
+    }
+}
+
+impl<'de> Deserialize<'de> for Mts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mts = i64::deserialize(deserializer)?;
+        Mts::from_millis(mts).ok_or_else(|| de::Error::custom("Failed to parse"))
+    }
+}
+
+/// Funding period in days, validated to the 2-120 range Bitfinex accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FundingPeriod(u8);
+
+impl FundingPeriod {
+    pub const MIN: u8 = 2;
+    pub const MAX: u8 = 120;
+
+    pub fn new(days: u8) -> Result<Self, BitfinexError> {
+        if (Self::MIN..=Self::MAX).contains(&days) {
+            Ok(FundingPeriod(days))
+        } else {
+            Err(BitfinexError::InvalidPeriod(days))
+        }
+    }
+
+    pub fn days(&self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for FundingPeriod {
+    type Error = BitfinexError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FundingPeriod::new(value)
+    }
+}
+
+impl From<FundingPeriod> for u8 {
+    fn from(value: FundingPeriod) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for FundingPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let days = s.parse::<u8>().map_err(|e| e.to_string())?;
+        FundingPeriod::new(days).map_err(|_| {
+            format!(
+                "{days} is not in {}..={}",
+                FundingPeriod::MIN,
+                FundingPeriod::MAX
+            )
+        })
+    }
+}
+
+impl fmt::Display for FundingPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for FundingPeriod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for FundingPeriod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let days = u8::deserialize(deserializer)?;
+        FundingPeriod::new(days)
+            .map_err(|_| de::Error::custom(format!("Out of available period range: {days}")))
+    }
+}
+
+/// Side of a trade, order or book entry, derived from the sign of its amount.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Side {
+    Buy,
+    Sell,
+    Lend,
+    Borrow,
+}
+
+impl Side {
+    /// Trades, orders and book entries of trading pairs: positive amounts buy.
+    pub fn from_trading_amount(amount: f64) -> Self {
+        if amount > 0.0 { Side::Buy } else { Side::Sell }
+    }
+
+    /// Funding offers and book entries: positive amounts are offers to lend,
+    /// negative ones are bids to borrow.
+    pub fn from_funding_amount(amount: f64) -> Self {
+        if amount > 0.0 {
+            Side::Lend
+        } else {
+            Side::Borrow
+        }
+    }
+
+    /// Public funding trades, signed by the taker: a positive amount took
+    /// an offer, so the taker borrowed.
+    pub fn from_funding_trade_amount(amount: f64) -> Self {
+        if amount > 0.0 {
+            Side::Borrow
+        } else {
+            Side::Lend
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Buy => write!(f, "buy"),
+            Side::Sell => write!(f, "sell"),
+            Side::Lend => write!(f, "lend"),
+            Side::Borrow => write!(f, "borrow"),
+        }
+    }
+}
+
+/// Flags of a trading order, combined with `|` or the builder methods,
+/// e.g. `OrderFlags::empty().hidden(true).post_only(true)`.
+///
+/// Ref: <https://docs.bitfinex.com/docs/flag-values>
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OrderFlags(u32);
+
+impl OrderFlags {
+    /// The order does not appear in the order book.
+    pub const HIDDEN: Self = OrderFlags(64);
+    /// Close the position if present.
+    pub const CLOSE: Self = OrderFlags(512);
+    /// Only reduce the current position.
+    pub const REDUCE_ONLY: Self = OrderFlags(1024);
+    /// Cancel the order if it would match immediately.
+    pub const POST_ONLY: Self = OrderFlags(4096);
+    /// One-Cancels-Other, together with `price_oco_stop`.
+    pub const OCO: Self = OrderFlags(16384);
+    /// Exclude variable rate funding offers from matching.
+    pub const NO_VAR_RATES: Self = OrderFlags(524288);
+
+    pub fn empty() -> Self {
+        OrderFlags(0)
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        OrderFlags(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Set or clear `flag`.
+    pub fn set(self, flag: Self, on: bool) -> Self {
+        if on {
+            OrderFlags(self.0 | flag.0)
+        } else {
+            OrderFlags(self.0 & !flag.0)
+        }
+    }
+
+    pub fn hidden(self, on: bool) -> Self {
+        self.set(Self::HIDDEN, on)
+    }
+
+    pub fn close(self, on: bool) -> Self {
+        self.set(Self::CLOSE, on)
+    }
+
+    pub fn reduce_only(self, on: bool) -> Self {
+        self.set(Self::REDUCE_ONLY, on)
+    }
+
+    pub fn post_only(self, on: bool) -> Self {
+        self.set(Self::POST_ONLY, on)
+    }
+
+    pub fn oco(self, on: bool) -> Self {
+        self.set(Self::OCO, on)
+    }
+
+    pub fn no_var_rates(self, on: bool) -> Self {
+        self.set(Self::NO_VAR_RATES, on)
+    }
+}
+
+impl BitOr for OrderFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        OrderFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for OrderFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Serialize for OrderFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(OrderFlags)
+    }
+}
+
+/// Fixed decimals applied to prices, amounts and rates when serializing
+/// models to JSON, so downstream systems receive stable representations.
+///
+/// Prices in the quote currency of a pair, rounded by [`Rounding::price`].
+pub const PRICE_FIELDS: &[&str] = &[
+    "price",
+    "price_avg",
+    "price_trailing",
+    "price_aux_limit",
+    "base_price",
+    "mark_price",
+    "liquidation_price",
+    "order_price",
+    "deriv_price",
+    "spot_price",
+    "last_price",
+    "bid",
+    "ask",
+    "open",
+    "close",
+    "high",
+    "low",
+];
+
+/// Amounts and balances of a currency, rounded by [`Rounding::amount`].
+pub const AMOUNT_FIELDS: &[&str] = &[
+    "amount",
+    "amount_ori",
+    "amount_orig",
+    "filled_amount",
+    "offered_amount",
+    "funding_amount",
+    "funding_amount_used",
+    "frr_amount_available",
+    "balance",
+    "free",
+    "unsettled_amount",
+    "gross_balance",
+    "base_ccy_balance",
+    "bid_size",
+    "ask_size",
+    "min_order_size",
+    "max_order_size",
+    "volume",
+    "exposure",
+    "current_hedge",
+];
+
+/// Daily funding rates, rounded by [`Rounding::rate`].
+pub const RATE_FIELDS: &[&str] = &[
+    "rate",
+    "frr",
+    "avg_rate",
+    "last_rate",
+    "filled_avg_rate",
+    "cancelled_avg_rate",
+];
+
+/// Fields are classified by their exact name, see [`PRICE_FIELDS`],
+/// [`AMOUNT_FIELDS`] and [`RATE_FIELDS`]. Other fields, such as fees and
+/// percentages, are left untouched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rounding {
+    pub price: Option<u32>,
+    pub amount: Option<u32>,
+    pub rate: Option<u32>,
+}
+
+impl Rounding {
+    /// Serialize `data` to JSON with the numbers rounded.
+    pub fn to_value<T: Serialize + ?Sized>(
+        &self,
+        data: &T,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        let mut value = serde_json::to_value(data)?;
+        self.apply(&mut value);
+        Ok(value)
+    }
+
+    /// Round the numbers of already serialized JSON in place.
+    pub fn apply(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| self.apply(v)),
+            serde_json::Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    match (self.decimals(key), field.as_f64()) {
+                        (Some(decimals), Some(number)) if field.is_f64() => {
+                            let factor = 10f64.powi(decimals as i32);
+                            *field = serde_json::Value::from((number * factor).round() / factor);
+                        }
+                        _ => self.apply(field),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn decimals(&self, key: &str) -> Option<u32> {
+        if RATE_FIELDS.contains(&key) {
+            self.rate
+        } else if PRICE_FIELDS.contains(&key) {
+            self.price
+        } else if AMOUNT_FIELDS.contains(&key) {
+            self.amount
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    /// Parse e.g. `price=2,amount=8,rate=6`; omitted classes are not rounded.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rounding = Rounding::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (class, decimals) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Expected <class>=<decimals>, got: {part}"))?;
+            let decimals = decimals
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("Invalid decimals of {class}: {e}"))?;
+            match class.trim() {
+                "price" => rounding.price = Some(decimals),
+                "amount" => rounding.amount = Some(decimals),
+                "rate" => rounding.rate = Some(decimals),
+                other => return Err(format!("Unknown field class: {other}")),
+            }
+        }
+        Ok(rounding)
+    }
+}
 
 pub fn parse_ccy_from_symbol(symbol: &str) -> &str {
     match symbol.get(0..1) {
@@ -18,6 +438,27 @@ pub fn parse_ccy_from_symbol(symbol: &str) -> &str {
     }
 }
 
+/// Base currency of a trading symbol, e.g. BTC of tBTCUSD. Fails with
+/// [`BitfinexError::InvalidArgument`] for trading symbols too short to hold
+/// one.
+pub fn parse_base_ccy_from_symbol(symbol: &str) -> Result<&str, BitfinexError> {
+    match symbol.get(0..1) {
+        Some("f") => Ok(&symbol[1..]),
+        Some("t") => {
+            let base = match symbol.find(":") {
+                // tETH:USDT
+                Some(idx) => symbol.get(1..idx),
+                // BTCUSD
+                None => symbol.get(1..4),
+            };
+            base.filter(|b| !b.is_empty()).ok_or_else(|| {
+                BitfinexError::InvalidArgument(format!("Malformed trading symbol: {symbol}"))
+            })
+        }
+        _ => Ok(symbol),
+    }
+}
+
 pub fn home_dir() -> Option<PathBuf> {
     if cfg!(windows) {
         env::var("USERPROFILE").map(PathBuf::from).ok()
@@ -63,8 +504,83 @@ pub fn resolve_env_path_or_create() -> PathBuf {
         .read_line(&mut api_secret)
         .expect("Failed to read from stdin");
 
-    fs.write(format!("API_KEY={api_key}API_SECRET={api_secret}").as_bytes())
+    fs.write_all(format!("API_KEY={api_key}API_SECRET={api_secret}").as_bytes())
         .expect("Failed to write content to env file");
 
     env_path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rounding_from_str() {
+        let rounding: Rounding = "price=2, amount=8,rate=6".parse().unwrap();
+        assert_eq!(
+            rounding,
+            Rounding {
+                price: Some(2),
+                amount: Some(8),
+                rate: Some(6),
+            }
+        );
+        assert_eq!("".parse::<Rounding>(), Ok(Rounding::default()));
+        assert!("price".parse::<Rounding>().is_err());
+        assert!("price=-1".parse::<Rounding>().is_err());
+        assert!("fee=2".parse::<Rounding>().is_err());
+    }
+
+    #[test]
+    fn rounding_per_class() {
+        let rounding: Rounding = "price=2,amount=4,rate=6".parse().unwrap();
+        let value = rounding
+            .to_value(&json!([{
+                "id": 12345,
+                "price": 27123.456789,
+                "mark_price": 27000.005,
+                "high": 27200.129,
+                "amount": -0.30000000000000004,
+                "balance": 1.234567,
+                "rate": 0.000123456789,
+                "avg_rate": 0.000234567891,
+                "fee": 0.123456789,
+                "daily_change_perc": 1.23456789,
+                "rate_limit": 1.23456789,
+                "liquidation_price_hint": 1.23456789,
+                "nested": [{"price": 1.005001, "status": "ACTIVE"}],
+            }]))
+            .unwrap();
+        assert_eq!(
+            value,
+            json!([{
+                "id": 12345,
+                "price": 27123.46,
+                "mark_price": 27000.01,
+                "high": 27200.13,
+                "amount": -0.3,
+                "balance": 1.2346,
+                "rate": 0.000123,
+                "avg_rate": 0.000235,
+                "fee": 0.123456789,
+                "daily_change_perc": 1.23456789,
+                "rate_limit": 1.23456789,
+                "liquidation_price_hint": 1.23456789,
+                "nested": [{"price": 1.01, "status": "ACTIVE"}],
+            }])
+        );
+    }
+
+    #[test]
+    fn rounding_leaves_unset_classes() {
+        let rounding: Rounding = "amount=2".parse().unwrap();
+        let value = rounding
+            .to_value(&json!({"price": 1.23456, "amount": 1.23456, "rate": 0.000123456}))
+            .unwrap();
+        assert_eq!(
+            value,
+            json!({"price": 1.23456, "amount": 1.23, "rate": 0.000123456})
+        );
+    }
+}
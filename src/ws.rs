@@ -0,0 +1,1949 @@
+#![cfg(feature = "ws")]
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::poll_fn,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, Stream, StreamExt, future::join_all};
+use hex::encode;
+use ring::hmac;
+use serde_json::{Value, from_value, json};
+use tokio::{
+    net::TcpStream,
+    sync::{Notify, mpsc, oneshot},
+    time::Instant,
+};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async, tungstenite::protocol::Message,
+};
+
+use crate::{
+    client::{Client, DerivativesStatus},
+    error::BitfinexError,
+    funding::{
+        BookPrecision, Candle, CandleTimeFrame, FundingBookRaw, FundingTicker, FundingTrade,
+    },
+    trading::{
+        Liquidation, TradingBook, TradingBookRaw, TradingOrder, TradingTicker, TradingTrade,
+    },
+    utils::Mts,
+};
+
+static BITFINEX_WS_PUB_HOST: &str = "wss://api-pub.bitfinex.com/ws/2";
+static BITFINEX_WS_AUTH_HOST: &str = "wss://api.bitfinex.com/ws/2";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// --- Channels --- //
+#[derive(Clone, Debug, PartialEq)]
+pub enum Channel {
+    /// Public trades of a trading pair (e.g. tBTCUSD) or funding currency
+    /// (e.g. fUSD).
+    Trades { symbol: String },
+    /// Ticker of a trading pair (e.g. tBTCUSD) or funding currency (e.g. fUSD).
+    Ticker { symbol: String },
+    /// Order book of a trading pair, aggregated by price level.
+    ///
+    /// With `conflate` set, bursts of updates are applied to a local book and
+    /// at most one full snapshot is delivered per interval (e.g. 100ms).
+    Book {
+        symbol: String,
+        precision: BookPrecision,
+        conflate: Option<Duration>,
+    },
+    /// Raw order book of a trading pair or funding currency, one entry per
+    /// order/offer (`R0`). See [`RawBook`] to maintain it locally.
+    RawBook { symbol: String },
+    /// Derivatives status of a perpetual pair (e.g. tBTCF0:USTF0).
+    DerivStatus { key: String },
+    /// Liquidations of all derivatives and margin positions (`liq:global`).
+    Liquidations,
+}
+
+impl Channel {
+    fn subscribe_payload(&self) -> Value {
+        match self {
+            Channel::Trades { symbol } => json!({
+                "event": "subscribe",
+                "channel": "trades",
+                "symbol": symbol,
+            }),
+            Channel::Ticker { symbol } => json!({
+                "event": "subscribe",
+                "channel": "ticker",
+                "symbol": symbol,
+            }),
+            Channel::Book {
+                symbol, precision, ..
+            } => json!({
+                "event": "subscribe",
+                "channel": "book",
+                "symbol": symbol,
+                "prec": format!("P{}", u8::from(*precision)),
+                "len": "25",
+            }),
+            Channel::RawBook { symbol } => json!({
+                "event": "subscribe",
+                "channel": "book",
+                "symbol": symbol,
+                "prec": "R0",
+                "len": "250",
+            }),
+            Channel::DerivStatus { key } => json!({
+                "event": "subscribe",
+                "channel": "status",
+                "key": format!("deriv:{key}"),
+            }),
+            Channel::Liquidations => json!({
+                "event": "subscribe",
+                "channel": "status",
+                "key": "liq:global",
+            }),
+        }
+    }
+
+    /// Whether a `subscribed` event from Bitfinex confirms this channel.
+    fn matches(&self, event: &Value) -> bool {
+        match self {
+            Channel::Trades { symbol } => {
+                event["channel"] == "trades" && event["symbol"] == symbol.as_str()
+            }
+            Channel::Ticker { symbol } => {
+                event["channel"] == "ticker" && event["symbol"] == symbol.as_str()
+            }
+            Channel::Book {
+                symbol, precision, ..
+            } => {
+                event["channel"] == "book"
+                    && event["symbol"] == symbol.as_str()
+                    && event["prec"] == format!("P{}", u8::from(*precision))
+            }
+            Channel::RawBook { symbol } => {
+                event["channel"] == "book"
+                    && event["symbol"] == symbol.as_str()
+                    && event["prec"] == "R0"
+            }
+            Channel::DerivStatus { key } => {
+                event["channel"] == "status" && event["key"] == format!("deriv:{key}")
+            }
+            Channel::Liquidations => event["channel"] == "status" && event["key"] == "liq:global",
+        }
+    }
+}
+
+// --- Events --- //
+pub enum WsEvent {
+    /// Trades of a subscribed trading pair. `snapshot` is true for the initial
+    /// batch of recent trades sent right after subscribing.
+    TradingTrades {
+        symbol: String,
+        trades: Vec<TradingTrade>,
+        snapshot: bool,
+    },
+    /// Trades of a subscribed funding currency, see [`WsEvent::TradingTrades`].
+    FundingTrades {
+        symbol: String,
+        trades: Vec<FundingTrade>,
+        snapshot: bool,
+    },
+    TradingTicker {
+        symbol: String,
+        ticker: TradingTicker,
+    },
+    FundingTicker {
+        symbol: String,
+        ticker: FundingTicker,
+    },
+    /// Price levels of a subscribed order book. Without conflation, updates
+    /// carry a single level where a `count` of 0 removes the level. With
+    /// conflation, every event is a full snapshot of the local book, bids
+    /// first from best to worst, then asks.
+    TradingBook {
+        symbol: String,
+        books: Vec<TradingBook>,
+        snapshot: bool,
+    },
+    /// Orders of a subscribed raw trading book. In updates, a `price` of 0
+    /// removes the order.
+    TradingRawBook {
+        symbol: String,
+        orders: Vec<TradingBookRaw>,
+        snapshot: bool,
+    },
+    /// Offers of a subscribed raw funding book. In updates, a `rate` of 0
+    /// removes the offer.
+    FundingRawBook {
+        symbol: String,
+        offers: Vec<FundingBookRaw>,
+        snapshot: bool,
+    },
+    DerivStatus {
+        status: Box<DerivativesStatus>,
+    },
+    Liquidations {
+        liquidations: Vec<Liquidation>,
+    },
+    /// Error event returned by Bitfinex, e.g. for an invalid subscription.
+    Error { code: u32, message: String },
+    /// The connection was re-established after being lost, and Bitfinex
+    /// answered the subscription of every channel again. Events between
+    /// `disconnected_at` and now may be missing, so consumers should resync
+    /// their state. `failed` lists the channels whose subscription was
+    /// rejected, which receive no more events.
+    Reconnected {
+        disconnected_at: DateTime<Utc>,
+        failed: Vec<Channel>,
+    },
+    /// The connection was authenticated, see [`WsConfig::auth`].
+    Authenticated { user_id: u64 },
+    /// Message of the account channel (channel 0), e.g. `os`/`on`/`ou`/`oc` for
+    /// orders, `ws`/`wu` for wallets or `n` for notifications.
+    ///
+    /// Ref: <https://docs.bitfinex.com/docs/ws-auth>
+    Account { event: String, data: Value },
+    /// No message nor heartbeat was received on `channel` for longer than
+    /// [`WsConfig::stale_timeout`]. Emitted once until the channel resumes.
+    ChannelStale {
+        channel: Channel,
+        silent_for: Duration,
+    },
+    /// The event buffer overflowed with [`OverflowPolicy::Error`]. The
+    /// connection is closed and no more events follow.
+    Overflow,
+}
+
+// --- Config --- //
+#[derive(Clone)]
+pub struct WsConfig {
+    /// Reconnect and resubscribe automatically when the connection drops.
+    pub reconnect: bool,
+    /// Delay before the first reconnect attempt, doubled after each failure.
+    pub reconnect_backoff_min: Duration,
+    /// Upper bound of the delay between reconnect attempts.
+    pub reconnect_backoff_max: Duration,
+    /// Authenticate the connection to receive account channel messages.
+    /// Authentication is replayed after every reconnect.
+    pub auth: Option<WsAuth>,
+    /// A channel is considered stale when nothing, not even a heartbeat, was
+    /// received on it for this long. Bitfinex sends heartbeats every 15s.
+    pub stale_timeout: Option<Duration>,
+    /// Force a reconnect instead of only emitting [`WsEvent::ChannelStale`].
+    pub reconnect_on_stale: bool,
+    /// Maximum number of events buffered for a slow consumer.
+    pub event_buffer: usize,
+    /// What to do when the event buffer is full.
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        WsConfig {
+            reconnect: true,
+            reconnect_backoff_min: Duration::from_secs(1),
+            reconnect_backoff_max: Duration::from_secs(30),
+            auth: None,
+            stale_timeout: Some(Duration::from_secs(30)),
+            reconnect_on_stale: false,
+            event_buffer: 1024,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WsAuth {
+    pub api_key: String,
+    pub api_secret: String,
+    /// Dead-man's-switch: Bitfinex cancels all open orders if this
+    /// connection drops (`dms: 4`).
+    pub dead_man_switch: bool,
+}
+
+impl WsAuth {
+    pub fn new(api_key: String, api_secret: String) -> Self {
+        WsAuth {
+            api_key,
+            api_secret,
+            dead_man_switch: false,
+        }
+    }
+
+    /// Ref: <https://docs.bitfinex.com/docs/ws-auth>
+    fn payload(&self) -> Value {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let nonce = (since_epoch.as_millis() * 1000).to_string();
+        let auth_payload = format!("AUTH{nonce}");
+        let key = hmac::Key::new(hmac::HMAC_SHA384, self.api_secret.as_bytes());
+        let signature = encode(hmac::sign(&key, auth_payload.as_bytes()).as_ref());
+
+        let mut payload = json!({
+            "event": "auth",
+            "apiKey": self.api_key,
+            "authSig": signature,
+            "authNonce": nonce,
+            "authPayload": auth_payload,
+        });
+        if self.dead_man_switch {
+            payload["dms"] = Value::from(4);
+        }
+        payload
+    }
+}
+
+enum Command {
+    Subscribe(Channel),
+    Shutdown {
+        cancel_orders: bool,
+        done: oneshot::Sender<()>,
+    },
+}
+
+/// Ask a connection task to shut down and wait for it within `timeout`.
+async fn shutdown_connection(
+    cmd_tx: &mpsc::UnboundedSender<Command>,
+    cancel_orders: bool,
+    timeout: Duration,
+) -> Result<(), BitfinexError> {
+    let (done, done_rx) = oneshot::channel();
+    let command = Command::Shutdown {
+        cancel_orders,
+        done,
+    };
+    if cmd_tx.send(command).is_err() {
+        // Already closed
+        return Ok(());
+    }
+    match tokio::time::timeout(timeout, done_rx).await {
+        // The task also drops the sender when it ends for another reason
+        Ok(_) => Ok(()),
+        Err(_) => Err(BitfinexError::WebSocketError("Shutdown timed out".into())),
+    }
+}
+
+// --- WebSocket Client --- //
+///
+/// Events can be consumed with [`WsClient::next_event`] or as a [`Stream`].
+pub struct WsClient {
+    cmd_tx: mpsc::UnboundedSender<Command>,
+    event_rx: EventReceiver,
+}
+
+impl WsClient {
+    /// Ref: <https://docs.bitfinex.com/docs/ws-general>
+    pub async fn connect() -> Result<Self, BitfinexError> {
+        Self::connect_with_config(WsConfig::default()).await
+    }
+
+    pub async fn connect_with_config(config: WsConfig) -> Result<Self, BitfinexError> {
+        let (event_tx, event_rx) = event_queue(&config);
+        let cmd_tx = spawn_connection(config, event_tx).await?;
+        Ok(WsClient { cmd_tx, event_rx })
+    }
+
+    /// Subscriptions are kept and replayed automatically after a reconnect.
+    pub fn subscribe(&self, channel: Channel) -> Result<(), BitfinexError> {
+        self.cmd_tx
+            .send(Command::Subscribe(channel))
+            .map_err(|_| BitfinexError::WebSocketError("Connection closed".into()))
+    }
+
+    /// Wait for the next event. Returns `None` once the connection is closed.
+    pub async fn next_event(&mut self) -> Option<WsEvent> {
+        poll_fn(|cx| self.event_rx.poll_recv(cx)).await
+    }
+
+    /// Close the connection gracefully: pending conflated book snapshots are
+    /// delivered, then the socket is closed. With `cancel_orders`, all open
+    /// orders are canceled first on an authenticated connection.
+    ///
+    /// Events still buffered can be consumed afterwards, until the stream ends.
+    pub async fn shutdown(
+        &self,
+        cancel_orders: bool,
+        timeout: Duration,
+    ) -> Result<(), BitfinexError> {
+        shutdown_connection(&self.cmd_tx, cancel_orders, timeout).await
+    }
+}
+
+impl Stream for WsClient {
+    type Item = WsEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<WsEvent>> {
+        self.event_rx.poll_recv(cx)
+    }
+}
+
+// --- Subscription Manager --- //
+/// Bitfinex limits the number of channels per connection.
+/// Ref: <https://docs.bitfinex.com/docs/ws-general#subscribe-to-channels>
+pub const MAX_CHANNELS_PER_CONNECTION: usize = 25;
+
+/// Spreads subscriptions over as many connections as needed to stay under the
+/// per-connection channel limit, and merges the events of all connections into
+/// a single stream.
+///
+/// Only the first connection is authenticated when [`WsConfig::auth`] is set.
+/// Keep in mind that Bitfinex also rate limits new connections (20 per minute).
+pub struct SubscriptionManager {
+    config: WsConfig,
+    max_channels: usize,
+    connections: Vec<(mpsc::UnboundedSender<Command>, usize)>,
+    event_tx: EventSender,
+    event_rx: EventReceiver,
+}
+
+impl SubscriptionManager {
+    pub fn new(config: WsConfig) -> Self {
+        Self::with_limit(
+            config,
+            const { NonZeroUsize::new(MAX_CHANNELS_PER_CONNECTION).unwrap() },
+        )
+    }
+
+    pub fn with_limit(config: WsConfig, max_channels: NonZeroUsize) -> Self {
+        let (event_tx, event_rx) = event_queue(&config);
+        SubscriptionManager {
+            config,
+            max_channels: max_channels.get(),
+            connections: Vec::new(),
+            event_tx,
+            event_rx,
+        }
+    }
+
+    /// Subscribe on the first connection with a free slot, opening a new
+    /// connection if all of them are full.
+    pub async fn subscribe(&mut self, channel: Channel) -> Result<(), BitfinexError> {
+        let idx = match self
+            .connections
+            .iter()
+            .position(|(_, count)| *count < self.max_channels)
+        {
+            Some(idx) => idx,
+            None => {
+                let mut config = self.config.clone();
+                if !self.connections.is_empty() {
+                    config.auth = None;
+                }
+                let cmd_tx = spawn_connection(config, self.event_tx.clone()).await?;
+                self.connections.push((cmd_tx, 0));
+                self.connections.len() - 1
+            }
+        };
+
+        let (cmd_tx, count) = &mut self.connections[idx];
+        cmd_tx
+            .send(Command::Subscribe(channel))
+            .map_err(|_| BitfinexError::WebSocketError("Connection closed".into()))?;
+        *count += 1;
+        Ok(())
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Wait for the next event of any connection.
+    pub async fn next_event(&mut self) -> Option<WsEvent> {
+        poll_fn(|cx| self.event_rx.poll_recv(cx)).await
+    }
+
+    /// Shut down all connections concurrently, see [`WsClient::shutdown`].
+    pub async fn shutdown(
+        &mut self,
+        cancel_orders: bool,
+        timeout: Duration,
+    ) -> Result<(), BitfinexError> {
+        let results = join_all(
+            self.connections
+                .iter()
+                .map(|(cmd_tx, _)| shutdown_connection(cmd_tx, cancel_orders, timeout)),
+        )
+        .await;
+        self.connections.clear();
+        results.into_iter().collect()
+    }
+}
+
+impl Stream for SubscriptionManager {
+    type Item = WsEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<WsEvent>> {
+        self.event_rx.poll_recv(cx)
+    }
+}
+
+// --- Event Queue --- //
+/// What to do when a consumer does not keep up and [`WsConfig::event_buffer`]
+/// is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest buffered event to make room for the new one.
+    DropOldest,
+    /// Merge the new event into a buffered one of the same ticker or book it
+    /// supersedes: tickers are replaced, book updates are merged by price
+    /// level or order ID, and a book snapshot replaces all buffered events of
+    /// its book. Other events drop the oldest buffered event instead.
+    Coalesce,
+    /// Stop reading from the socket until the consumer catches up.
+    Block,
+    /// Close the connection and end the stream with [`WsEvent::Overflow`].
+    Error,
+}
+
+struct QueueState {
+    events: VecDeque<WsEvent>,
+    senders: usize,
+    receiver_alive: bool,
+    overflowed: bool,
+    overflow_reported: bool,
+    waker: Option<Waker>,
+}
+
+/// Bounded event queue shared by the connection tasks and the consumer.
+struct Queue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    space: Notify,
+}
+
+impl Queue {
+    fn wake_receiver(state: &mut QueueState) {
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+fn event_queue(config: &WsConfig) -> (EventSender, EventReceiver) {
+    let queue = Arc::new(Queue {
+        state: Mutex::new(QueueState {
+            events: VecDeque::new(),
+            senders: 1,
+            receiver_alive: true,
+            overflowed: false,
+            overflow_reported: false,
+            waker: None,
+        }),
+        capacity: config.event_buffer.max(1),
+        policy: config.overflow,
+        space: Notify::new(),
+    });
+    (
+        EventSender {
+            queue: queue.clone(),
+        },
+        EventReceiver { queue },
+    )
+}
+
+/// Whether `a` and `b` are events of the same ticker or book.
+fn same_stream(a: &WsEvent, b: &WsEvent) -> bool {
+    match (a, b) {
+        (WsEvent::TradingTicker { symbol: x, .. }, WsEvent::TradingTicker { symbol: y, .. })
+        | (WsEvent::FundingTicker { symbol: x, .. }, WsEvent::FundingTicker { symbol: y, .. })
+        | (WsEvent::TradingBook { symbol: x, .. }, WsEvent::TradingBook { symbol: y, .. })
+        | (WsEvent::TradingRawBook { symbol: x, .. }, WsEvent::TradingRawBook { symbol: y, .. })
+        | (WsEvent::FundingRawBook { symbol: x, .. }, WsEvent::FundingRawBook { symbol: y, .. }) => {
+            x == y
+        }
+        _ => false,
+    }
+}
+
+/// Replace the entries of `buffered` with the `newer` ones of the same key,
+/// appending the others.
+fn merge_by<T, K: PartialEq>(buffered: &mut Vec<T>, newer: Vec<T>, key: impl Fn(&T) -> K) {
+    for entry in newer {
+        match buffered.iter().position(|b| key(b) == key(&entry)) {
+            Some(i) => buffered[i] = entry,
+            None => buffered.push(entry),
+        }
+    }
+}
+
+/// Merge `event` into the buffered events for [`OverflowPolicy::Coalesce`].
+/// Gives `event` back when no buffered event is superseded by it.
+fn coalesce(events: &mut VecDeque<WsEvent>, event: WsEvent) -> Option<WsEvent> {
+    let Some(latest) = events.iter().rposition(|e| same_stream(e, &event)) else {
+        return Some(event);
+    };
+    let is_snapshot = matches!(
+        event,
+        WsEvent::TradingBook { snapshot: true, .. }
+            | WsEvent::TradingRawBook { snapshot: true, .. }
+            | WsEvent::FundingRawBook { snapshot: true, .. }
+    );
+    if is_snapshot {
+        events.retain(|e| !same_stream(e, &event));
+        events.push_back(event);
+        return None;
+    }
+
+    match (&mut events[latest], event) {
+        (WsEvent::TradingTicker { ticker: old, .. }, WsEvent::TradingTicker { ticker, .. }) => {
+            *old = ticker;
+        }
+        (WsEvent::FundingTicker { ticker: old, .. }, WsEvent::FundingTicker { ticker, .. }) => {
+            *old = ticker;
+        }
+        (
+            WsEvent::TradingBook {
+                books: old,
+                snapshot: false,
+                ..
+            },
+            WsEvent::TradingBook { books, .. },
+        ) => merge_by(old, books, |b| b.price.to_bits()),
+        (
+            WsEvent::TradingRawBook {
+                orders: old,
+                snapshot: false,
+                ..
+            },
+            WsEvent::TradingRawBook { orders, .. },
+        ) => merge_by(old, orders, |o| o.order_id),
+        (
+            WsEvent::FundingRawBook {
+                offers: old,
+                snapshot: false,
+                ..
+            },
+            WsEvent::FundingRawBook { offers, .. },
+        ) => merge_by(old, offers, |o| o.id),
+        (_, event) => return Some(event),
+    }
+    None
+}
+
+/// The consumer is gone or the queue overflowed with [`OverflowPolicy::Error`].
+struct QueueClosed;
+
+struct EventSender {
+    queue: Arc<Queue>,
+}
+
+impl EventSender {
+    async fn send(&self, event: WsEvent) -> Result<(), QueueClosed> {
+        let mut event = Some(event);
+        loop {
+            // Created before checking the state so a wakeup in between is not lost.
+            let space = self.queue.space.notified();
+            {
+                let mut state = self.queue.state.lock().unwrap();
+                if !state.receiver_alive || state.overflowed {
+                    return Err(QueueClosed);
+                }
+                if state.events.len() >= self.queue.capacity {
+                    match self.queue.policy {
+                        OverflowPolicy::DropOldest => {
+                            state.events.pop_front();
+                        }
+                        OverflowPolicy::Coalesce => {
+                            event = coalesce(&mut state.events, event.take().unwrap());
+                            if event.is_none() {
+                                Queue::wake_receiver(&mut state);
+                                return Ok(());
+                            }
+                            state.events.pop_front();
+                        }
+                        OverflowPolicy::Error => {
+                            state.overflowed = true;
+                            Queue::wake_receiver(&mut state);
+                            return Err(QueueClosed);
+                        }
+                        OverflowPolicy::Block => {}
+                    }
+                }
+                if state.events.len() < self.queue.capacity {
+                    state.events.push_back(event.take().unwrap());
+                    Queue::wake_receiver(&mut state);
+                    return Ok(());
+                }
+            }
+            space.await;
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        let state = self.queue.state.lock().unwrap();
+        !state.receiver_alive || state.overflowed
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.queue.state.lock().unwrap().senders += 1;
+        EventSender {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            Queue::wake_receiver(&mut state);
+        }
+    }
+}
+
+struct EventReceiver {
+    queue: Arc<Queue>,
+}
+
+impl EventReceiver {
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<WsEvent>> {
+        let mut state = self.queue.state.lock().unwrap();
+        if let Some(event) = state.events.pop_front() {
+            drop(state);
+            self.queue.space.notify_one();
+            return Poll::Ready(Some(event));
+        }
+        if state.overflowed {
+            if state.overflow_reported {
+                return Poll::Ready(None);
+            }
+            state.overflow_reported = true;
+            return Poll::Ready(Some(WsEvent::Overflow));
+        }
+        if state.senders == 0 {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().receiver_alive = false;
+        self.queue.space.notify_waiters();
+    }
+}
+
+async fn spawn_connection(
+    config: WsConfig,
+    event_tx: EventSender,
+) -> Result<mpsc::UnboundedSender<Command>, BitfinexError> {
+    let socket = open_socket(&config).await?;
+    let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+    tokio::spawn(run(config, socket, cmd_rx, event_tx));
+    Ok(cmd_tx)
+}
+
+async fn open_socket(config: &WsConfig) -> Result<Socket, BitfinexError> {
+    let host = match config.auth {
+        Some(_) => BITFINEX_WS_AUTH_HOST,
+        None => BITFINEX_WS_PUB_HOST,
+    };
+    let (socket, _) = connect_async(host)
+        .await
+        .map_err(|e| BitfinexError::WebSocketError(e.to_string()))?;
+    Ok(socket)
+}
+
+/// State of a single underlying connection, reset on every reconnect.
+#[derive(Default)]
+struct Connection {
+    pending: Vec<Channel>,
+    /// Set after a reconnect until all replayed subscriptions are answered.
+    disconnected_at: Option<DateTime<Utc>>,
+    /// Channels whose subscription was rejected since the reconnect.
+    failed: Vec<Channel>,
+    channels: HashMap<u64, Channel>,
+    last_seen: HashMap<u64, Instant>,
+    stale: HashSet<u64>,
+    books: HashMap<u64, LocalBook>,
+    restart: bool,
+}
+
+/// Local order book of a conflated [`Channel::Book`] subscription.
+struct LocalBook {
+    symbol: String,
+    interval: Duration,
+    levels: HashMap<u64, TradingBook>,
+    dirty: bool,
+    last_emit: Option<Instant>,
+}
+
+impl LocalBook {
+    fn new(symbol: String, interval: Duration) -> Self {
+        LocalBook {
+            symbol,
+            interval,
+            levels: HashMap::new(),
+            dirty: false,
+            last_emit: None,
+        }
+    }
+
+    fn apply(&mut self, level: TradingBook) {
+        if level.count == 0 {
+            self.levels.remove(&level.price.to_bits());
+        } else {
+            self.levels.insert(level.price.to_bits(), level);
+        }
+        self.dirty = true;
+    }
+
+    /// When the pending changes may be delivered.
+    fn due_at(&self) -> Option<Instant> {
+        if !self.dirty {
+            return None;
+        }
+        match self.last_emit {
+            Some(last_emit) => Some(last_emit + self.interval),
+            None => Some(Instant::now()),
+        }
+    }
+
+    fn take_snapshot(&mut self) -> WsEvent {
+        let (mut bids, mut asks): (Vec<TradingBook>, Vec<TradingBook>) = self
+            .levels
+            .values()
+            .cloned()
+            .partition(|level| level.amount > 0.0);
+        bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+        bids.extend(asks);
+
+        self.dirty = false;
+        self.last_emit = Some(Instant::now());
+        WsEvent::TradingBook {
+            symbol: self.symbol.clone(),
+            books: bids,
+            snapshot: true,
+        }
+    }
+}
+
+impl Connection {
+    /// [`WsEvent::Reconnected`] once no replayed subscription is pending.
+    fn take_reconnected(&mut self) -> Option<WsEvent> {
+        if !self.pending.is_empty() {
+            return None;
+        }
+        let disconnected_at = self.disconnected_at.take()?;
+        Some(WsEvent::Reconnected {
+            disconnected_at,
+            failed: std::mem::take(&mut self.failed),
+        })
+    }
+
+    /// Channels that went silent for longer than `timeout` and were not
+    /// reported yet.
+    fn check_stale(&mut self, timeout: Duration) -> Vec<WsEvent> {
+        let mut events = Vec::new();
+        for (chan_id, last_seen) in &self.last_seen {
+            let silent_for = last_seen.elapsed();
+            if silent_for > timeout
+                && self.stale.insert(*chan_id)
+                && let Some(channel) = self.channels.get(chan_id)
+            {
+                events.push(WsEvent::ChannelStale {
+                    channel: channel.clone(),
+                    silent_for,
+                });
+            }
+        }
+        events
+    }
+
+    /// Earliest time a conflated book has pending changes to deliver.
+    fn next_flush(&self) -> Option<Instant> {
+        self.books.values().filter_map(LocalBook::due_at).min()
+    }
+
+    /// Snapshots of all conflated books with undelivered changes.
+    fn drain_books(&mut self) -> Vec<WsEvent> {
+        self.books
+            .values_mut()
+            .filter(|book| book.dirty)
+            .map(LocalBook::take_snapshot)
+            .collect()
+    }
+
+    /// Snapshots of the conflated books whose interval has elapsed.
+    fn flush_books(&mut self) -> Vec<WsEvent> {
+        let now = Instant::now();
+        self.books
+            .values_mut()
+            .filter(|book| book.due_at().is_some_and(|at| at <= now))
+            .map(LocalBook::take_snapshot)
+            .collect()
+    }
+}
+
+enum Exit {
+    Closed,
+    Disconnected,
+    Shutdown(oneshot::Sender<()>),
+}
+
+async fn run(
+    config: WsConfig,
+    mut socket: Socket,
+    mut cmd_rx: mpsc::UnboundedReceiver<Command>,
+    event_tx: EventSender,
+) {
+    let mut subscriptions: Vec<Channel> = Vec::new();
+    let mut disconnected_at = None;
+
+    loop {
+        let mut conn = Connection {
+            disconnected_at: disconnected_at.take(),
+            ..Default::default()
+        };
+        if let Some(auth) = &config.auth {
+            let _ = socket.send(Message::text(auth.payload().to_string())).await;
+        }
+        for channel in &subscriptions {
+            let payload = channel.subscribe_payload().to_string();
+            if socket.send(Message::text(payload)).await.is_ok() {
+                conn.pending.push(channel.clone());
+            }
+        }
+
+        let exit = serve(
+            &config,
+            &mut socket,
+            &mut conn,
+            &mut subscriptions,
+            &mut cmd_rx,
+            &event_tx,
+        )
+        .await;
+        if let Exit::Shutdown(done) = exit {
+            let _ = socket.close(None).await;
+            let _ = done.send(());
+            return;
+        }
+        if matches!(exit, Exit::Closed) || !config.reconnect {
+            let _ = socket.close(None).await;
+            return;
+        }
+
+        disconnected_at = Some(Utc::now());
+        let mut backoff = config.reconnect_backoff_min;
+        socket = loop {
+            tokio::time::sleep(backoff).await;
+            if event_tx.is_closed() {
+                return;
+            }
+            match open_socket(&config).await {
+                Ok(socket) => break socket,
+                Err(err) => {
+                    tracing::warn!(
+                        error = ?err,
+                        backoff_ms = backoff.as_millis() as u64,
+                        "failed to reconnect, retrying"
+                    );
+                    backoff = (backoff * 2).min(config.reconnect_backoff_max);
+                }
+            }
+        };
+    }
+}
+
+async fn serve(
+    config: &WsConfig,
+    socket: &mut Socket,
+    conn: &mut Connection,
+    subscriptions: &mut Vec<Channel>,
+    cmd_rx: &mut mpsc::UnboundedReceiver<Command>,
+    event_tx: &EventSender,
+) -> Exit {
+    let mut watchdog = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        if let Some(event) = conn.take_reconnected()
+            && event_tx.send(event).await.is_err()
+        {
+            return Exit::Closed;
+        }
+        let flush_at = conn.next_flush();
+        tokio::select! {
+            _ = tokio::time::sleep_until(flush_at.unwrap_or_else(Instant::now)), if flush_at.is_some() => {
+                for event in conn.flush_books() {
+                    if event_tx.send(event).await.is_err() {
+                        return Exit::Closed;
+                    }
+                }
+            }
+            _ = watchdog.tick() => {
+                let Some(timeout) = config.stale_timeout else {
+                    continue;
+                };
+                let events = conn.check_stale(timeout);
+                let stale = !events.is_empty();
+                for event in events {
+                    if event_tx.send(event).await.is_err() {
+                        return Exit::Closed;
+                    }
+                }
+                if stale && config.reconnect_on_stale {
+                    return Exit::Disconnected;
+                }
+            }
+            cmd = cmd_rx.recv() => match cmd {
+                Some(Command::Subscribe(channel)) => {
+                    let payload = channel.subscribe_payload().to_string();
+                    subscriptions.push(channel.clone());
+                    if socket.send(Message::text(payload)).await.is_err() {
+                        return Exit::Disconnected;
+                    }
+                    conn.pending.push(channel);
+                }
+                Some(Command::Shutdown { cancel_orders, done }) => {
+                    if cancel_orders && config.auth.is_some() {
+                        // Ref: <https://docs.bitfinex.com/reference/ws-auth-input-order-cancel-multi>
+                        let payload = json!([0, "oc_multi", null, {"all": 1}]).to_string();
+                        let _ = socket.send(Message::text(payload)).await;
+                    }
+                    for event in conn.drain_books() {
+                        if event_tx.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    return Exit::Shutdown(done);
+                }
+                // Client dropped
+                None => return Exit::Closed,
+            },
+            msg = socket.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    let Ok(value) = serde_json::from_str::<Value>(text.as_str()) else {
+                        continue;
+                    };
+                    for event in handle_message(value, conn) {
+                        if event_tx.send(event).await.is_err() {
+                            return Exit::Closed;
+                        }
+                    }
+                    if conn.restart {
+                        return Exit::Disconnected;
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return Exit::Disconnected,
+            },
+        }
+    }
+}
+
+fn handle_message(value: Value, conn: &mut Connection) -> Vec<WsEvent> {
+    if value.is_object() {
+        return match value["event"].as_str() {
+            Some("subscribed") => {
+                if let Some(idx) = conn.pending.iter().position(|ch| ch.matches(&value)) {
+                    let chan_id = value["chanId"].as_u64().unwrap_or_default();
+                    conn.channels.insert(chan_id, conn.pending.remove(idx));
+                    conn.last_seen.insert(chan_id, Instant::now());
+                }
+                vec![]
+            }
+            Some("error") => {
+                // Rejected subscriptions echo the channel of the request
+                if let Some(idx) = conn.pending.iter().position(|ch| ch.matches(&value)) {
+                    let channel = conn.pending.remove(idx);
+                    if conn.disconnected_at.is_some() {
+                        conn.failed.push(channel);
+                    }
+                }
+                vec![WsEvent::Error {
+                    code: value["code"].as_u64().unwrap_or_default() as u32,
+                    message: value["msg"].as_str().unwrap_or_default().to_string(),
+                }]
+            }
+            Some("auth") => {
+                if value["status"] == "OK" {
+                    vec![WsEvent::Authenticated {
+                        user_id: value["userId"].as_u64().unwrap_or_default(),
+                    }]
+                } else {
+                    vec![WsEvent::Error {
+                        code: value["code"].as_u64().unwrap_or_default() as u32,
+                        message: value["msg"].as_str().unwrap_or_default().to_string(),
+                    }]
+                }
+            }
+            Some("info") => {
+                // 20051: server restarting, please reconnect
+                // 20061: maintenance ended, resubscribe all channels
+                if matches!(value["code"].as_u64(), Some(20051) | Some(20061)) {
+                    conn.restart = true;
+                }
+                vec![]
+            }
+            _ => vec![],
+        };
+    }
+
+    // Channel data: [CHAN_ID, ...]
+    let Some(chan_id) = value[0].as_u64() else {
+        return vec![];
+    };
+    if chan_id == 0 {
+        // Account channel: [0, EVENT, DATA]
+        return match value[1].as_str() {
+            Some("hb") | None => vec![],
+            Some(event) => vec![WsEvent::Account {
+                event: event.to_string(),
+                data: value[2].clone(),
+            }],
+        };
+    }
+    let Some(channel) = conn.channels.get(&chan_id) else {
+        return vec![];
+    };
+    conn.last_seen.insert(chan_id, Instant::now());
+    conn.stale.remove(&chan_id);
+    if value[1] == "hb" {
+        return vec![];
+    }
+
+    match channel {
+        Channel::Trades { symbol } if symbol.starts_with("f") => match &value[1] {
+            // Snapshot: [CHAN_ID, [[ID, MTS, AMOUNT, RATE, PERIOD], ...]]
+            Value::Array(_) => {
+                let trades: Vec<FundingTrade> = from_value(value[1].clone()).unwrap_or_default();
+                vec![WsEvent::FundingTrades {
+                    symbol: symbol.clone(),
+                    trades,
+                    snapshot: true,
+                }]
+            }
+            // Update: [CHAN_ID, "fte", [ID, MTS, AMOUNT, RATE, PERIOD]]
+            // "ftu" repeats the same trade and is skipped to avoid duplicates.
+            Value::String(s) if s == "fte" => match from_value::<FundingTrade>(value[2].clone()) {
+                Ok(trade) => vec![WsEvent::FundingTrades {
+                    symbol: symbol.clone(),
+                    trades: vec![trade],
+                    snapshot: false,
+                }],
+                Err(_) => vec![],
+            },
+            _ => vec![],
+        },
+        Channel::Trades { symbol } => match &value[1] {
+            // Snapshot: [CHAN_ID, [[ID, MTS, AMOUNT, PRICE], ...]]
+            Value::Array(_) => {
+                let trades: Vec<TradingTrade> = from_value(value[1].clone()).unwrap_or_default();
+                vec![WsEvent::TradingTrades {
+                    symbol: symbol.clone(),
+                    trades,
+                    snapshot: true,
+                }]
+            }
+            // Update: [CHAN_ID, "te", [ID, MTS, AMOUNT, PRICE]]
+            // "tu" repeats the same trade and is skipped to avoid duplicates.
+            Value::String(s) if s == "te" => match from_value::<TradingTrade>(value[2].clone()) {
+                Ok(trade) => vec![WsEvent::TradingTrades {
+                    symbol: symbol.clone(),
+                    trades: vec![trade],
+                    snapshot: false,
+                }],
+                Err(_) => vec![],
+            },
+            _ => vec![],
+        },
+        // [CHAN_ID, [TICKER_FIELDS]]
+        Channel::Ticker { symbol } if symbol.starts_with("f") => {
+            match from_value::<FundingTicker>(value[1].clone()) {
+                Ok(ticker) => vec![WsEvent::FundingTicker {
+                    symbol: symbol.clone(),
+                    ticker,
+                }],
+                Err(_) => vec![],
+            }
+        }
+        Channel::Ticker { symbol } => match from_value::<TradingTicker>(value[1].clone()) {
+            Ok(ticker) => vec![WsEvent::TradingTicker {
+                symbol: symbol.clone(),
+                ticker,
+            }],
+            Err(_) => vec![],
+        },
+        Channel::Book {
+            symbol, conflate, ..
+        } => {
+            // Snapshot: [CHAN_ID, [[PRICE, COUNT, AMOUNT], ...]]
+            // Update: [CHAN_ID, [PRICE, COUNT, AMOUNT]]
+            let snapshot = value[1][0].is_array();
+            let books: Vec<TradingBook> = if snapshot {
+                from_value(value[1].clone()).unwrap_or_default()
+            } else {
+                from_value(value[1].clone())
+                    .map(|level| vec![level])
+                    .unwrap_or_default()
+            };
+
+            let Some(interval) = conflate else {
+                return vec![WsEvent::TradingBook {
+                    symbol: symbol.clone(),
+                    books,
+                    snapshot,
+                }];
+            };
+            let book = conn
+                .books
+                .entry(chan_id)
+                .or_insert_with(|| LocalBook::new(symbol.clone(), *interval));
+            if snapshot {
+                book.levels.clear();
+            }
+            for level in books {
+                book.apply(level);
+            }
+            match book.due_at() {
+                Some(at) if at <= Instant::now() => vec![book.take_snapshot()],
+                // Delivered by the flush timer in `serve`
+                _ => vec![],
+            }
+        }
+        Channel::DerivStatus { key } => {
+            // [CHAN_ID, [MTS, _, DERIV_PRICE, ...]] which is the REST shape
+            // without the leading KEY.
+            let Value::Array(fields) = &value[1] else {
+                return vec![];
+            };
+            let mut fields = fields.clone();
+            fields.insert(0, Value::from(key.as_str()));
+            match from_value::<DerivativesStatus>(Value::from(fields)) {
+                Ok(status) => vec![WsEvent::DerivStatus {
+                    status: Box::new(status),
+                }],
+                Err(_) => vec![],
+            }
+        }
+        // [CHAN_ID, [["pos", POS_ID, MTS, _, SYMBOL, AMOUNT, ...], ...]]
+        Channel::Liquidations => {
+            let liquidations: Vec<Liquidation> = from_value(value[1].clone()).unwrap_or_default();
+            if liquidations.is_empty() {
+                return vec![];
+            }
+            vec![WsEvent::Liquidations { liquidations }]
+        }
+        Channel::RawBook { symbol } if symbol.starts_with("f") => {
+            // Snapshot: [CHAN_ID, [[OFFER_ID, PERIOD, RATE, AMOUNT], ...]]
+            // Update: [CHAN_ID, [OFFER_ID, PERIOD, RATE, AMOUNT]]
+            let snapshot = value[1][0].is_array();
+            let offers: Vec<FundingBookRaw> = if snapshot {
+                from_value(value[1].clone()).unwrap_or_default()
+            } else {
+                from_value(value[1].clone())
+                    .map(|offer| vec![offer])
+                    .unwrap_or_default()
+            };
+            vec![WsEvent::FundingRawBook {
+                symbol: symbol.clone(),
+                offers,
+                snapshot,
+            }]
+        }
+        Channel::RawBook { symbol } => {
+            // Snapshot: [CHAN_ID, [[ORDER_ID, PRICE, AMOUNT], ...]]
+            // Update: [CHAN_ID, [ORDER_ID, PRICE, AMOUNT]]
+            let snapshot = value[1][0].is_array();
+            let orders: Vec<TradingBookRaw> = if snapshot {
+                from_value(value[1].clone()).unwrap_or_default()
+            } else {
+                from_value(value[1].clone())
+                    .map(|order| vec![order])
+                    .unwrap_or_default()
+            };
+            vec![WsEvent::TradingRawBook {
+                symbol: symbol.clone(),
+                orders,
+                snapshot,
+            }]
+        }
+    }
+}
+
+// --- Candle Builder --- //
+/// Builds candles of an arbitrary time frame (e.g. 45m or 2h) from a stream of
+/// trades, such as the ones received from [`Channel::Trades`].
+///
+/// Candles are aligned to the unix epoch, and a candle is only emitted once it is
+/// closed, i.e. when a trade of a later candle arrives or [`CandleBuilder::flush`]
+/// is called after the candle end.
+pub struct CandleBuilder {
+    interval_ms: i64,
+    current: Option<Candle>,
+}
+
+impl CandleBuilder {
+    /// Fails with [`BitfinexError::InvalidArgument`] for an `interval` shorter
+    /// than 1 millisecond.
+    pub fn new(interval: Duration) -> Result<Self, BitfinexError> {
+        if interval.as_millis() == 0 {
+            return Err(BitfinexError::InvalidArgument(String::from(
+                "Candle interval must be at least 1 millisecond",
+            )));
+        }
+        Ok(CandleBuilder {
+            interval_ms: interval.as_millis() as i64,
+            current: None,
+        })
+    }
+
+    fn bucket_start(&self, time: &Mts) -> i64 {
+        let mts = time.timestamp_millis();
+        mts - mts.rem_euclid(self.interval_ms)
+    }
+
+    /// Feed a trade into the builder. Returns the previous candle if the trade
+    /// belongs to a later candle. Trades older than the current candle are ignored.
+    pub fn push(&mut self, trade: &TradingTrade) -> Option<Candle> {
+        let start = self.bucket_start(&trade.time);
+        let volume = trade.amount.abs();
+
+        if let Some(candle) = self.current.as_mut() {
+            let current_start = candle.time.timestamp_millis();
+            if start == current_start {
+                candle.close = trade.price;
+                candle.high = candle.high.max(trade.price);
+                candle.low = candle.low.min(trade.price);
+                candle.volume += volume;
+                return None;
+            }
+            if start < current_start {
+                return None;
+            }
+        }
+
+        let time = Mts::from_millis(start)?;
+        self.current.replace(Candle {
+            time,
+            open: trade.price,
+            close: trade.price,
+            high: trade.price,
+            low: trade.price,
+            volume,
+        })
+    }
+
+    /// Close the current candle if `now` is past its end.
+    pub fn flush(&mut self, now: DateTime<Utc>) -> Option<Candle> {
+        let candle = self.current.as_ref()?;
+        if now.timestamp_millis() >= candle.time.timestamp_millis() + self.interval_ms {
+            self.current.take()
+        } else {
+            None
+        }
+    }
+
+    /// The candle currently being built, if any.
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+}
+
+// --- Rolling Rate --- //
+/// Amount-weighted average rate of the funding trades within a time window,
+/// such as the ones received from [`Channel::Trades`] of a funding currency.
+pub struct RollingRate {
+    window: Duration,
+    trades: VecDeque<(Mts, f64, f64)>,
+    weighted_sum: f64,
+    amount_sum: f64,
+}
+
+impl RollingRate {
+    pub fn new(window: Duration) -> Self {
+        RollingRate {
+            window,
+            trades: VecDeque::new(),
+            weighted_sum: 0.0,
+            amount_sum: 0.0,
+        }
+    }
+
+    /// Feed a trade and drop the trades that fell out of the window.
+    pub fn push(&mut self, trade: &FundingTrade) {
+        let amount = trade.amount.abs();
+        self.trades.push_back((trade.created, amount, trade.rate));
+        self.weighted_sum += amount * trade.rate;
+        self.amount_sum += amount;
+
+        let Some(cutoff) = chrono::Duration::from_std(self.window)
+            .ok()
+            .and_then(|window| trade.created.checked_sub_signed(window))
+        else {
+            return;
+        };
+        while let Some(&(time, amount, rate)) = self.trades.front() {
+            if *time >= cutoff {
+                break;
+            }
+            self.trades.pop_front();
+            self.weighted_sum -= amount * rate;
+            self.amount_sum -= amount;
+        }
+    }
+
+    /// Weighted average rate, `None` before the first trade.
+    pub fn rate(&self) -> Option<f64> {
+        (self.amount_sum > 0.0).then(|| self.weighted_sum / self.amount_sum)
+    }
+
+    /// Total amount traded within the window.
+    pub fn volume(&self) -> f64 {
+        self.amount_sum
+    }
+}
+
+// --- Book Imbalance --- //
+/// Condition on the bid/ask imbalance of an order book, sustained for a
+/// minimum duration, e.g. to raise an alert or place a conditional order.
+///
+/// Feed it full book snapshots, such as the events of [`Channel::Book`] with
+/// `conflate` set.
+pub struct BookImbalance {
+    threshold: f64,
+    sustain: Duration,
+    since: Option<DateTime<Utc>>,
+    fired: bool,
+}
+
+impl BookImbalance {
+    /// `threshold` above 1 matches bid-heavy books (bid amount / ask amount
+    /// at or above it), below 1 matches ask-heavy books (at or below it).
+    /// Fails with [`BitfinexError::InvalidArgument`] unless `threshold` is a
+    /// positive finite number.
+    pub fn new(threshold: f64, sustain: Duration) -> Result<Self, BitfinexError> {
+        if !(threshold.is_finite() && threshold > 0.0) {
+            return Err(BitfinexError::InvalidArgument(format!(
+                "Imbalance threshold must be positive, got {threshold}"
+            )));
+        }
+        Ok(BookImbalance {
+            threshold,
+            sustain,
+            since: None,
+            fired: false,
+        })
+    }
+
+    /// Total bid amount divided by total ask amount. `None` if a side is empty.
+    pub fn ratio(books: &[TradingBook]) -> Option<f64> {
+        let bids: f64 = books
+            .iter()
+            .filter(|b| b.amount > 0.0)
+            .map(|b| b.amount)
+            .sum();
+        let asks: f64 = books
+            .iter()
+            .filter(|b| b.amount < 0.0)
+            .map(|b| -b.amount)
+            .sum();
+        (bids > 0.0 && asks > 0.0).then(|| bids / asks)
+    }
+
+    /// Check a new snapshot taken at `now`. Returns the ratio once the
+    /// condition has held for the sustain duration, then stays quiet until the
+    /// condition breaks and holds again.
+    pub fn update(&mut self, books: &[TradingBook], now: DateTime<Utc>) -> Option<f64> {
+        let ratio = Self::ratio(books);
+        let matched = ratio.is_some_and(|r| match self.threshold >= 1.0 {
+            true => r >= self.threshold,
+            false => r <= self.threshold,
+        });
+        if !matched {
+            self.since = None;
+            self.fired = false;
+            return None;
+        }
+
+        let since = *self.since.get_or_insert(now);
+        let held = (now - since).to_std().unwrap_or_default();
+        if self.fired || held < self.sustain {
+            return None;
+        }
+        self.fired = true;
+        ratio
+    }
+}
+
+// --- Raw Book --- //
+/// Raw order book keyed by order ID, built from the events of
+/// [`Channel::RawBook`].
+pub struct RawBook<T> {
+    orders: HashMap<u64, T>,
+}
+
+impl<T> Default for RawBook<T> {
+    fn default() -> Self {
+        RawBook {
+            orders: HashMap::new(),
+        }
+    }
+}
+
+impl<T> RawBook<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&T> {
+        self.orders.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+}
+
+impl RawBook<TradingBookRaw> {
+    /// Apply the orders of a [`WsEvent::TradingRawBook`] event.
+    pub fn apply(&mut self, orders: Vec<TradingBookRaw>, snapshot: bool) {
+        if snapshot {
+            self.orders.clear();
+        }
+        for order in orders {
+            if order.price == 0.0 {
+                self.orders.remove(&order.order_id);
+            } else {
+                self.orders.insert(order.order_id, order);
+            }
+        }
+    }
+
+    /// Bid orders from the highest price.
+    pub fn bids(&self) -> Vec<&TradingBookRaw> {
+        let mut bids: Vec<_> = self.orders.values().filter(|o| o.amount > 0.0).collect();
+        bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        bids
+    }
+
+    /// Ask orders from the lowest price.
+    pub fn asks(&self) -> Vec<&TradingBookRaw> {
+        let mut asks: Vec<_> = self.orders.values().filter(|o| o.amount < 0.0).collect();
+        asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+        asks
+    }
+}
+
+impl RawBook<FundingBookRaw> {
+    /// Apply the offers of a [`WsEvent::FundingRawBook`] event.
+    pub fn apply(&mut self, offers: Vec<FundingBookRaw>, snapshot: bool) {
+        if snapshot {
+            self.orders.clear();
+        }
+        for offer in offers {
+            if offer.rate == 0.0 {
+                self.orders.remove(&offer.id);
+            } else {
+                self.orders.insert(offer.id, offer);
+            }
+        }
+    }
+
+    /// Funding bids (amount < 0) from the highest rate.
+    pub fn bids(&self) -> Vec<&FundingBookRaw> {
+        let mut bids: Vec<_> = self.orders.values().filter(|o| o.amount < 0.0).collect();
+        bids.sort_by(|a, b| b.rate.total_cmp(&a.rate));
+        bids
+    }
+
+    /// Funding offers (amount > 0) from the lowest rate.
+    pub fn asks(&self) -> Vec<&FundingBookRaw> {
+        let mut asks: Vec<_> = self.orders.values().filter(|o| o.amount > 0.0).collect();
+        asks.sort_by(|a, b| a.rate.total_cmp(&b.rate));
+        asks
+    }
+}
+
+// --- Resync --- //
+/// Fetch the trades of `symbol` executed since `since` from the REST API,
+/// oldest first. Use it after [`WsEvent::Reconnected`] to fill the gap, and
+/// pass both the REST and streamed trades through a [`TradeMerger`].
+pub async fn resync_trades(
+    client: &Client,
+    symbol: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<TradingTrade>, BitfinexError> {
+    let limit: u16 = 10000;
+    let mut end = Utc::now();
+    let mut trades: Vec<TradingTrade> = Vec::new();
+    loop {
+        // Newest first
+        let page = client
+            .request_trading_trades(symbol, Some(limit), Some(since), Some(end))
+            .await?;
+        let full_page = page.len() == limit as usize;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        // Pages overlap on the boundary millisecond, duplicates are removed
+        // by the TradeMerger.
+        let stalled = *oldest.time == end;
+        end = *oldest.time;
+        trades.extend(page);
+        if !full_page || stalled {
+            break;
+        }
+    }
+    trades.reverse();
+    Ok(trades)
+}
+
+/// Fetch the candles of `symbol` starting from the one containing `since`,
+/// oldest first. The last candle may still be open.
+pub async fn resync_candles(
+    client: &Client,
+    symbol: &str,
+    time_frame: CandleTimeFrame,
+    since: DateTime<Utc>,
+) -> Result<Vec<Candle>, BitfinexError> {
+    let limit: u16 = 10000;
+    let mut end = Utc::now();
+    let mut candles: Vec<Candle> = Vec::new();
+    loop {
+        // Newest first
+        let page = client
+            .request_trading_candles(symbol, time_frame, Some(limit), Some(since), Some(end))
+            .await?;
+        let full_page = page.len() == limit as usize;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        // Candles have distinct times, so the next page ends right before
+        end = *oldest.time - chrono::Duration::milliseconds(1);
+        candles.extend(page);
+        if !full_page || end < since {
+            break;
+        }
+    }
+    candles.reverse();
+    Ok(candles)
+}
+
+/// Fetch the orders of `symbol` updated since `since` from the REST API, from
+/// the oldest update. Includes orders filled or canceled during the gap, from
+/// the order history, and all active orders. Pass them through an
+/// [`OrderMerger`] together with the streamed orders to keep the latest state
+/// of each order.
+pub async fn resync_orders(
+    client: &Client,
+    symbol: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<TradingOrder>, BitfinexError> {
+    let limit: u16 = 2500;
+    let mut end = Utc::now();
+    let mut orders: Vec<TradingOrder> = Vec::new();
+    loop {
+        // Newest update first
+        let page = client
+            .request_trading_orders_hist(
+                Some(symbol.to_string()),
+                Some(limit),
+                Some(since),
+                Some(end),
+            )
+            .await?;
+        let full_page = page.len() == limit as usize;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        // Pages overlap on the boundary millisecond, duplicates are removed
+        // by the OrderMerger.
+        let stalled = *oldest.updated == end;
+        end = *oldest.updated;
+        orders.extend(page);
+        if !full_page || stalled {
+            break;
+        }
+    }
+    orders.extend(
+        client
+            .request_trading_orders(Some(symbol.to_string()), None, None, None)
+            .await?,
+    );
+    orders.sort_by_key(|o| (o.updated, o.id));
+    Ok(orders)
+}
+
+/// Merges orders coming from the WS stream and from REST backfills, keeping
+/// only changes newer than the last known update of each order.
+pub struct OrderMerger {
+    updated: HashMap<u64, Mts>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Default for OrderMerger {
+    fn default() -> Self {
+        Self::new(10000)
+    }
+}
+
+impl OrderMerger {
+    /// `capacity` is the number of most recent order IDs remembered. It should
+    /// be larger than the number of orders expected between resyncs.
+    pub fn new(capacity: usize) -> Self {
+        OrderMerger {
+            updated: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the orders updated after their last seen state, ordered by
+    /// update time. Stale and duplicate states are dropped.
+    pub fn merge(&mut self, mut orders: Vec<TradingOrder>) -> Vec<TradingOrder> {
+        orders.sort_by_key(|o| (o.updated, o.id));
+        let mut fresh = Vec::new();
+        for o in orders {
+            match self.updated.get(&o.id) {
+                Some(updated) if *updated >= o.updated => continue,
+                Some(_) => {}
+                None => self.order.push_back(o.id),
+            }
+            self.updated.insert(o.id, o.updated);
+            fresh.push(o);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(id) = self.order.pop_front() {
+                self.updated.remove(&id);
+            }
+        }
+        fresh
+    }
+}
+
+/// Merges trades coming from the WS stream and from REST backfills into a
+/// single sequence without duplicates.
+pub struct TradeMerger {
+    seen: HashSet<u64>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Default for TradeMerger {
+    fn default() -> Self {
+        Self::new(10000)
+    }
+}
+
+impl TradeMerger {
+    /// `capacity` is the number of most recent trade IDs remembered for
+    /// de-duplication. It should be larger than the biggest expected backfill.
+    pub fn new(capacity: usize) -> Self {
+        TradeMerger {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the trades which were not seen before, ordered by time.
+    pub fn merge(&mut self, trades: Vec<TradingTrade>) -> Vec<TradingTrade> {
+        let mut fresh: Vec<TradingTrade> = trades
+            .into_iter()
+            .filter(|t| self.seen.insert(t.id))
+            .collect();
+        fresh.sort_by_key(|t| (t.time, t.id));
+
+        for t in &fresh {
+            self.order.push_back(t.id);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(id) = self.order.pop_front() {
+                self.seen.remove(&id);
+            }
+        }
+        fresh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z, a multiple of both 45m and 2h
+    const T0: i64 = 1_704_067_200_000;
+    const MINUTE: i64 = 60_000;
+
+    fn trade(id: u64, offset_ms: i64, price: f64, amount: f64) -> TradingTrade {
+        TradingTrade {
+            id,
+            time: Mts::from_millis(T0 + offset_ms).unwrap(),
+            amount,
+            price,
+        }
+    }
+
+    #[test]
+    fn candle_builder_45m() {
+        let mut builder = CandleBuilder::new(Duration::from_secs(45 * 60)).unwrap();
+
+        // The first trade opens a candle aligned to the epoch, not to itself
+        assert!(builder.push(&trade(1, 10 * MINUTE, 100.0, 1.0)).is_none());
+        assert_eq!(builder.current().unwrap().time.timestamp_millis(), T0);
+        assert!(builder.push(&trade(2, 20 * MINUTE, 110.0, -2.0)).is_none());
+        assert!(
+            builder
+                .push(&trade(3, 45 * MINUTE - 1, 90.0, 0.5))
+                .is_none()
+        );
+
+        let closed = builder.push(&trade(4, 45 * MINUTE, 95.0, 1.0)).unwrap();
+        assert_eq!(closed.time.timestamp_millis(), T0);
+        assert_eq!(closed.open, 100.0);
+        assert_eq!(closed.high, 110.0);
+        assert_eq!(closed.low, 90.0);
+        assert_eq!(closed.close, 90.0);
+        assert_eq!(closed.volume, 3.5);
+
+        // Late trades of a closed candle are ignored
+        assert!(builder.push(&trade(5, 30 * MINUTE, 80.0, 1.0)).is_none());
+        assert_eq!(builder.current().unwrap().low, 95.0);
+
+        // A gap closes the current candle without emitting empty ones
+        let closed = builder.push(&trade(6, 190 * MINUTE, 120.0, 2.0)).unwrap();
+        assert_eq!(closed.time.timestamp_millis(), T0 + 45 * MINUTE);
+        assert_eq!(
+            (closed.open, closed.close, closed.volume),
+            (95.0, 95.0, 1.0)
+        );
+        assert_eq!(
+            builder.current().unwrap().time.timestamp_millis(),
+            T0 + 180 * MINUTE
+        );
+    }
+
+    #[test]
+    fn candle_builder_2h() {
+        let mut builder = CandleBuilder::new(Duration::from_secs(2 * 3600)).unwrap();
+
+        assert!(builder.push(&trade(1, 90 * MINUTE, 50.0, -1.0)).is_none());
+        assert!(builder.push(&trade(2, 119 * MINUTE, 55.0, 1.0)).is_none());
+        assert_eq!(builder.current().unwrap().time.timestamp_millis(), T0);
+
+        // Not closed before its end
+        assert!(
+            builder
+                .flush(Mts::from_millis(T0 + 120 * MINUTE - 1).unwrap().0)
+                .is_none()
+        );
+
+        let closed = builder
+            .push(&trade(3, 120 * MINUTE + 1, 60.0, 1.0))
+            .unwrap();
+        assert_eq!(closed.time.timestamp_millis(), T0);
+        assert_eq!((closed.open, closed.close), (50.0, 55.0));
+        assert_eq!((closed.high, closed.low), (55.0, 50.0));
+        assert_eq!(closed.volume, 2.0);
+
+        let flushed = builder
+            .flush(Mts::from_millis(T0 + 240 * MINUTE).unwrap().0)
+            .unwrap();
+        assert_eq!(flushed.time.timestamp_millis(), T0 + 120 * MINUTE);
+        assert_eq!(flushed.close, 60.0);
+        assert!(builder.current().is_none());
+    }
+
+    #[rustfmt::skip]
+    fn order(id: u64, updated_offset_ms: i64, amount: f64, status: &str) -> TradingOrder {
+        serde_json::from_value(json!([
+            id, null, 0, "tBTCUSD", T0, T0 + updated_offset_ms, amount, 1.0,
+            "ExchangeLimit", null, null, null, 0, status, null, null,
+            100.0, 0.0, 0.0, 0.0, null, null, null, 0, 0, null, null, null,
+            "API>BFX", null, null, null
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn trade_merger_drops_overlap() {
+        let mut merger = TradeMerger::default();
+        let streamed = merger.merge(vec![trade(3, 3, 100.0, 1.0), trade(4, 4, 100.0, 1.0)]);
+        assert_eq!(streamed.len(), 2);
+
+        // REST page, newest first, overlapping the streamed trades
+        let backfill = merger.merge(vec![
+            trade(4, 4, 100.0, 1.0),
+            trade(3, 3, 100.0, 1.0),
+            trade(2, 2, 100.0, 1.0),
+            trade(1, 1, 100.0, 1.0),
+        ]);
+        let ids: Vec<u64> = backfill.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [1, 2]);
+
+        // Overlapping REST pages and late stream updates add nothing
+        let ids: Vec<u64> = merger
+            .merge(vec![
+                trade(2, 2, 100.0, 1.0),
+                trade(5, 5, 100.0, 1.0),
+                trade(4, 4, 100.0, 1.0),
+            ])
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, [5]);
+    }
+
+    #[test]
+    fn trade_merger_forgets_beyond_capacity() {
+        let mut merger = TradeMerger::new(2);
+        merger.merge(vec![
+            trade(1, 1, 100.0, 1.0),
+            trade(2, 2, 100.0, 1.0),
+            trade(3, 3, 100.0, 1.0),
+        ]);
+        assert_eq!(merger.merge(vec![trade(1, 1, 100.0, 1.0)]).len(), 1);
+        assert!(merger.merge(vec![trade(3, 3, 100.0, 1.0)]).is_empty());
+    }
+
+    #[test]
+    fn order_merger_keeps_latest_state() {
+        let mut merger = OrderMerger::default();
+        // Streamed: order 1 partially filled, order 2 placed
+        let streamed = merger.merge(vec![
+            order(1, 20, 0.5, "PARTIALLY FILLED"),
+            order(2, 10, 1.0, "ACTIVE"),
+        ]);
+        assert_eq!(streamed.len(), 2);
+
+        // REST backfill with an older state of order 1, the same state of
+        // order 2, a newer state of order 2 and an order missed during the gap
+        let backfill = merger.merge(vec![
+            order(1, 5, 1.0, "ACTIVE"),
+            order(2, 10, 1.0, "ACTIVE"),
+            order(2, 30, 0.0, "EXECUTED"),
+            order(3, 15, 0.0, "CANCELED"),
+        ]);
+        let states: Vec<(u64, &str)> = backfill.iter().map(|o| (o.id, o.status.as_str())).collect();
+        assert_eq!(states, [(3, "CANCELED"), (2, "EXECUTED")]);
+
+        // A stale streamed update after the backfill is dropped too
+        assert!(
+            merger
+                .merge(vec![order(2, 25, 0.2, "PARTIALLY FILLED")])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn candle_builder_rejects_sub_millisecond_interval() {
+        assert!(CandleBuilder::new(Duration::ZERO).is_err());
+        assert!(CandleBuilder::new(Duration::from_micros(999)).is_err());
+    }
+}